@@ -0,0 +1,181 @@
+// a simple computer opponent. It looks a few moves ahead with alpha-beta
+// search and mostly counts material, which is enough for a casual game
+use crate::{board::ChessBoard, chesspiece::*, event::GameEvent, game::Game, variant::Variant};
+
+// bigger than any material difference so mate always wins out
+const MATE_SCORE: i32 = 100_000;
+// bigger than any score a position can get
+const INFINITY: i32 = 10_000_000;
+
+// a move the computer can make, which is a piece moving or one being
+// dropped from the reserve in crazyhouse and bughouse
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AiMove {
+    Move([usize; 2], [usize; 2]),
+    Drop(PieceType, [usize; 2]),
+}
+
+// find the best move for the player whose turn it is, searching depth
+// moves ahead. None if the player has no legal moves or drops
+pub fn best_move(board: &ChessBoard, depth: u32) -> Option<AiMove> {
+    let mut position = board.without_history();
+    let is_white = position.get_player();
+    let mut best = None;
+    let mut alpha = -INFINITY;
+    for candidate in candidates(&mut position) {
+        let mut next = position.without_history();
+        play(&mut next, candidate);
+        let score = -search(&mut next, depth.saturating_sub(1), -INFINITY, -alpha, !is_white);
+        if best.is_none() || score > alpha {
+            alpha = score;
+            best = Some(candidate);
+        }
+    }
+    best
+}
+
+// every move and drop the player to move can make
+fn candidates(board: &mut ChessBoard) -> Vec<AiMove> {
+    let mut moves: Vec<AiMove> = board.legal_moves()
+        .into_iter()
+        .map(|(start, dest)| AiMove::Move(start, dest))
+        .collect();
+    let is_white = board.get_player();
+    moves.extend(board.legal_drops(is_white).into_iter().map(|(kind, square)| AiMove::Drop(kind, square)));
+    moves
+}
+
+// the piece the computer turns its pawns into, which is a queen unless
+// the rules don't allow one
+pub fn promotion(board: &ChessBoard) -> PieceType {
+    if board.can_promote_to(PieceType::Queen) {
+        return PieceType::Queen;
+    }
+    board.get_promotion_options().first().copied().unwrap_or(PieceType::Queen)
+}
+
+// play a move on a search board
+fn play(board: &mut ChessBoard, candidate: AiMove) {
+    let events = match candidate {
+        AiMove::Move(start, dest) => board.next_turn(start, dest),
+        AiMove::Drop(kind, square) => board.next_drop(kind, square),
+    };
+    for event in events.unwrap_or_default() {
+        if let GameEvent::PromotionPending { square } = event {
+            let kind = promotion(board);
+            board.upgrade_pawn(square, kind).ok();
+        }
+    }
+}
+
+// negamax with alpha-beta pruning. The score is from the point of view of
+// the player to move
+fn search(board: &mut ChessBoard, depth: u32, mut alpha: i32, beta: i32, is_white: bool) -> i32 {
+    if let Some(winner) = board.get_winner() {
+        // usually the player to move just got mated, but in antichess they
+        // can win on their opponent's move. Prefer quicker wins
+        return match winner {
+            0 => 0,
+            _ if (winner > 0) == is_white => MATE_SCORE + depth as i32,
+            _ => -MATE_SCORE - depth as i32,
+        };
+    }
+    if depth == 0 {
+        return evaluate(board, is_white);
+    }
+    for candidate in candidates(board) {
+        let mut next = board.without_history();
+        play(&mut next, candidate);
+        let score = -search(&mut next, depth - 1, -beta, -alpha, !is_white);
+        if score >= beta {
+            return beta;
+        }
+        if score > alpha {
+            alpha = score;
+        }
+    }
+    alpha
+}
+
+// material on the board for the given player minus the opponent's. Pieces
+// get a small bonus for being near the centre so quiet moves aren't random.
+// In antichess having less is better, and in racing kings getting the king
+// up the board matters more than anything
+fn evaluate(board: &ChessBoard, is_white: bool) -> i32 {
+    match board.get_variant() {
+        Variant::Antichess => -material(board, is_white),
+        Variant::RacingKings => material(board, is_white) + race_progress(board, is_white),
+        _ => material(board, is_white),
+    }
+}
+// how much further up the board the player's king is than the opponent's
+fn race_progress(board: &ChessBoard, is_white: bool) -> i32 {
+    let rank = |king_pos: Option<[usize; 2]>| king_pos.map_or(0, |pos| pos[1] as i32);
+    let lead = rank(board.get_white_king_pos()) - rank(board.get_black_king_pos());
+    if is_white {
+        lead * 50
+    } else {
+        -lead * 50
+    }
+}
+fn material(board: &ChessBoard, is_white: bool) -> i32 {
+    let mut score = reserve_material(board, is_white) - reserve_material(board, !is_white);
+    let size = board.get_size();
+    for (col, column) in board.get_board().iter().enumerate() {
+        for (row, piece) in column.iter().enumerate() {
+            if let Some(piece) = piece {
+                let mut value = piece.get_points() as i32 * 10;
+                if piece.get_piece_type() != PieceType::King {
+                    value += centre_bonus([col, row], size);
+                }
+                if piece.is_white() == is_white {
+                    score += value;
+                } else {
+                    score -= value;
+                }
+            }
+        }
+    }
+    score
+}
+
+// 3 for the middle four squares down to 0 three squares out. Bigger boards
+// have squares further out than that, which don't get anything
+fn centre_bonus(square: [usize; 2], size: [usize; 2]) -> i32 {
+    let from_centre = |x: usize, length: usize| (2*x as i32 - (length as i32 - 1)).abs() / 2;
+    let distance = from_centre(square[0], size[0]).max(from_centre(square[1], size[1]));
+    (3 - distance).max(0)
+}
+// pieces waiting to be dropped count the same as ones on the board
+fn reserve_material(board: &ChessBoard, is_white: bool) -> i32 {
+    PieceType::ALL.iter()
+        .zip(board.get_reserve(is_white).iter())
+        .map(|(kind, count)| Piece::new(*kind, is_white).get_points() as i32 * 10 * *count as i32)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_when_only_drops_are_left() {
+        let mut board = ChessBoard::new();
+        board.set_variant(Variant::Crazyhouse);
+        board.new_game();
+        // the white king can't move anywhere the queen doesn't cover
+        board.load_fen("k7/8/8/8/8/8/2q5/K7 w - - 0 1").unwrap();
+        board.add_to_reserve(true, PieceType::Knight);
+        match best_move(&board, 1) {
+            Some(AiMove::Drop(kind, _)) => assert_eq!(kind, PieceType::Knight),
+            other => panic!("expected a drop, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn no_move_without_moves_or_drops() {
+        let mut board = ChessBoard::new();
+        board.load_fen("k7/8/8/8/8/8/2q5/K7 w - - 0 1").unwrap();
+        assert_eq!(best_move(&board, 1), None);
+    }
+}
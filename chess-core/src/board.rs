@@ -0,0 +1,1807 @@
+use crate::{chesspiece::*, event::*, game::*, odds::Odds, rules::{self, RuleSet, StandardRules}, save::{self, LoadError, SaveFile, SavedMove}, variant::Variant};
+use std::{fmt, io, mem, sync::Arc, time::{SystemTime, UNIX_EPOCH}};
+
+mod editor;
+mod fen;
+mod save_file;
+mod validate;
+mod variants;
+
+pub use validate::PositionProblem;
+pub use variants::variant_rules;
+
+// the most files and ranks a board can have
+pub const MAX_SIZE: usize = 12;
+// the squares of the board, indexed by [col][row]. Boards smaller than
+// MAX_SIZE only use the squares in the bottom left corner
+pub type Squares = [[Option<Piece>; MAX_SIZE]; MAX_SIZE];
+
+// the history starts with lines like this for anything that isn't part of
+// a turn, which is how old saves keep the variant and the like
+const SAVE_HEADER: &str = "# ";
+// how many lines every turn has before the board in the save
+const TURN_INFO_LINES: usize = 9;
+
+// ChessBoard struct. Cloning it copies the whole history along with the
+// position, so anything that copies boards over and over, like the AI's
+// search, uses without_history instead
+#[derive(Clone)]
+pub struct ChessBoard {
+    board: Squares,
+    // how many files and ranks the board has
+    width: usize,
+    height: usize,
+    player: bool,
+    score: [u8; 2],
+    winner: Option<i8>,
+    white_captured: String,
+    black_captured: String,
+    white_en_passant: Option<[usize; 2]>,
+    black_en_passant: Option<[usize; 2]>,
+    // the files of the rooks each player can still castle with, left then right
+    white_castle_rooks: [Option<usize>; 2],
+    black_castle_rooks: [Option<usize>; 2],
+    // no king for a side that doesn't have one, like white in horde
+    white_king_pos: Option<[usize; 2]>,
+    black_king_pos: Option<[usize; 2]>,
+    game_save: String,
+    // the moves in the history as the players made them, one for each turn,
+    // which is what gets saved
+    moves: Vec<SavedMove>,
+    turn_num: usize,
+    // moves since the last capture or pawn move, for the fifty move rule
+    halfmove_clock: usize,
+    // how many moves were played before the starting position, which is
+    // more than 0 for games set up from a FEN
+    start_ply: usize,
+    // the position the game started from, so undo can go all the way back
+    start_fen: String,
+    variant: Variant,
+    // everything about the rules that changes from variant to variant
+    rules: &'static dyn RuleSet,
+    // the rules picked for the next new game, so changing them doesn't
+    // change the game being played
+    next_rules: &'static dyn RuleSet,
+    // the handicap a player is giving, and if it's white giving it
+    odds: Odds,
+    odds_giver: bool,
+    // the milliseconds white and black start with, if the game is timed,
+    // and how many they have left
+    time_control: Option<[u64; 2]>,
+    clocks: [u64; 2],
+    // who's playing and what for, which carries over to new games
+    players: [String; 2],
+    event: String,
+    // the day the game was first saved, which is empty until it is
+    date: String,
+    // why the game ended, if it has
+    termination: Option<GameOverReason>,
+    // how many times white and black have given check, for three-check
+    checks: [u8; 2],
+    // the pieces each player can drop in crazyhouse, counted by piece type
+    white_reserve: [u8; 5],
+    black_reserve: [u8; 5],
+    // which pieces got there by promotion, since they go back to being
+    // pawns when they're captured in crazyhouse
+    promoted: [[bool; MAX_SIZE]; MAX_SIZE],
+    // the square of a pawn that reached the last rank and is waiting for
+    // upgrade_pawn, which has to finish its turn before anything else moves
+    pending_promotion: Option<[usize; 2]>,
+    events: Vec<GameEvent>,
+    observers: Observers,
+}
+
+// make ChessBoard a game
+impl Game for ChessBoard {
+    fn get_player(&self) -> bool {
+        self.player
+    }
+    fn set_player(&mut self, player: bool) {
+        self.player = player;
+    }
+    // reset fields
+    fn new_game(&mut self) {
+        let back_rank = self.next_rules.back_rank();
+        self.set_up(&back_rank);
+    }
+    // called whenever the player does something
+    // represents the course of a turn
+    fn take_turn(&mut self, start: [usize; 2], dest: [usize;2]) -> Option<Vec<GameEvent>> {
+        if !self.on_board(start) || !self.on_board(dest) || self.pending_promotion.is_some() {
+            return None;
+        }
+        if let Some(piece) = self.board[start[0]][start[1]] {
+            let mut pawn_promoted = false;
+            if piece.is_white() == self.player
+            && self.test_legal(start, dest, piece)
+            && !(self.rules.must_capture()
+            && !self.is_capture(start, dest) && self.can_capture(self.player)) {
+                let castle = if piece.get_piece_type() == PieceType::King {
+                    self.find_castle(start, dest, self.player)
+                } else {
+                    None
+                };
+                // the move is kept the way it was made, since that's how it
+                // gets played back
+                let saved_move = SavedMove {
+                    from: Some(square_name(start)),
+                    to: square_name(dest),
+                    ..SavedMove::default()
+                };
+                // the king always ends up on the c file or next to the corner when castling
+                let dest = match castle {
+                    Some((king_dest, _, _)) => king_dest,
+                    None => dest,
+                };
+                self.truncate_save();
+                self.moves.push(saved_move);
+                self.turn_num += 1;
+                let move_made = GameEvent::MoveMade { turn: self.turn_num, piece, start, dest };
+                let move_string = move_made.log_entry().unwrap_or_default();
+                self.events.push(move_made);
+                // we need to store the string representation of any captures that happen and
+                // then write them later because of how pawn promotion is out of sync with the
+                // rust turn order.
+                let mut capture_string = String::new();
+                // if piece is a pawn
+                if piece.get_piece_type() == PieceType::Pawn {
+                    if self.player {
+                        if self.black_en_passant.is_some() && dest == self.black_en_passant.unwrap() {
+                            capture_string = self.capture([dest[0], dest[1]-1]);
+                        } else if dest[1]-start[1] == 2 {
+                            // the horde's double steps from the first rank can't be taken en passant
+                            if self.rules.en_passant_after(start, true) {
+                                self.white_en_passant = Some([dest[0], dest[1]-1]);
+                            }
+                        } else {
+                            capture_string = self.capture(dest);
+                        }
+                    } else {
+                        if self.white_en_passant.is_some() && dest == self.white_en_passant.unwrap() {
+                            capture_string = self.capture([dest[0], dest[1]+1]);
+                        } else if start[1]-dest[1] == 2 {
+                            if self.rules.en_passant_after(start, false) {
+                                self.black_en_passant = Some([dest[0], dest[1]+1]);
+                            }
+                        } else {
+                            capture_string = self.capture(dest);
+                        }
+                    }
+                } else {
+                    // If piece is a king
+                    if piece.get_piece_type() == PieceType::King {
+                        if let Some((king_dest, rook_start, rook_dest)) = castle {
+                            // take both pieces off first since in chess960 the rook
+                            // can end up where the king started or the other way round
+                            let rook = self.board[rook_start[0]][rook_start[1]];
+                            self.board[start[0]][start[1]] = None;
+                            self.board[rook_start[0]][rook_start[1]] = None;
+                            self.board[rook_dest[0]][rook_dest[1]] = rook;
+                            self.events.push(GameEvent::Castled { rook_start, rook_dest, king_dest });
+                        }
+                        if self.player {
+                            self.white_king_pos = Some(dest);
+                            self.white_castle_rooks = [None; 2];
+                        } else {
+                            self.black_king_pos = Some(dest);
+                            self.black_castle_rooks = [None; 2];
+                        }
+                    // if piece is a rook
+                    } else if piece.get_piece_type() == PieceType::Rook {
+                        self.remove_castle_rook(start, self.player);
+                    }
+                    capture_string = self.capture(dest);
+                }
+                if capture_string.is_empty() && piece.get_piece_type() != PieceType::Pawn {
+                    self.halfmove_clock += 1;
+                } else {
+                    self.halfmove_clock = 0;
+                }
+                // the rules might take more than what was captured, like
+                // the explosions in atomic chess
+                let mut exploded = false;
+                if !capture_string.is_empty() {
+                    let rules = self.rules;
+                    if let Some(also_taken) = rules.after_capture(self, start, dest) {
+                        capture_string.push_str(&also_taken);
+                        exploded = true;
+                    }
+                }
+                let was_promoted = mem::take(&mut self.promoted[start[0]][start[1]]);
+                if castle.is_none() {
+                    self.board[start[0]][start[1]] = None;
+                }
+                if !exploded {
+                    self.board[dest[0]][dest[1]] = Some(piece);
+                    self.promoted[dest[0]][dest[1]] = was_promoted;
+                }
+                if !exploded && piece.get_piece_type() == PieceType::Pawn 
+                && (dest[1] == 0 || dest[1] == self.height - 1) {
+                    pawn_promoted = true;
+                    self.pending_promotion = Some(dest);
+                    self.events.push(GameEvent::PromotionPending { square: dest });
+                }
+                self.clear_en_passant();
+                self.save_move(&move_string);
+                self.game_save.push_str(&capture_string);
+                // tell the front end to refresh the list of moves
+                self.events.push(GameEvent::MovesUpdated { turns: self.turn_num });
+                if !pawn_promoted {
+                    self.end_turn(self.player);
+                }
+                return Some(self.take_events());
+            }
+        }
+        None
+    }
+    fn get_score(&self) -> String {
+        format!("White Score: {} \n{}\nBlack Score: {}\n{}\n", 
+                self.score[0], self.white_captured,
+                self.score[1], self.black_captured)
+    }
+    fn get_winner(&self) -> Option<i8> {
+        self.winner
+    }
+}
+// allow ChessBoard to be represented as a string.
+// used to be for displaying the board, but now it's used
+// for saving the game into a file to be loaded later
+impl fmt::Display for ChessBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if let Some(chess_piece) = &self.board[col][row] {
+                    write!(f, "{}", chess_piece)?;
+                } else {
+                    write!(f, " ")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        write!(f, "")
+    }
+}
+impl Default for ChessBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl ChessBoard {
+    // truncate save to current move after a move is made, and start
+    // it over with the header for this game on the first move
+    fn truncate_save(&mut self) {
+        self.moves.truncate(self.turn_num);
+        if self.get_num_turns() > self.turn_num || self.turn_num == 0 {
+            let truncated_save = self.save_turns()
+                .take(self.turn_num*self.turn_lines())
+                .fold(self.save_header(), |mut save, line| {
+                    save.push_str(&format!("{}\n", line));
+                    save
+                });
+            self.game_save = truncated_save;
+        }
+    }
+    // the other player can only take en passant on the move right after
+    fn clear_en_passant(&mut self) {
+        if self.player && self.black_en_passant.is_some() {
+            self.black_en_passant = None;
+        } else if !self.player && self.white_en_passant.is_some() {
+            self.white_en_passant = None;
+        }
+    }
+    // save file stuff, everything in a turn up to the move that was made
+    fn save_move(&mut self, move_string: &str) {
+        // score
+        self.game_save.push_str(&self.get_score());
+        // white's allowed castling directions
+        self.game_save.push_str(&format!("{}\n", castle_save_string(self.white_castle_rooks)));
+        // black's allowed castling directions
+        self.game_save.push_str(&format!("{}\n", castle_save_string(self.black_castle_rooks)));
+        // white's en passant col
+        let white_en_passant_string = match self.white_en_passant {
+            Some(space) => format!("{}\n", space[0]),
+            None => String::from("\n"), 
+        };
+        // black's en passant col
+        self.game_save.push_str(&white_en_passant_string);
+        let black_en_passant_string = match self.black_en_passant {
+            Some(space) => format!("{}\n", space[0]),
+            None => String::from("\n"),
+        };
+        self.game_save.push_str(&black_en_passant_string);
+        // most recent move
+        self.game_save.push_str(move_string);
+    }
+    // finish off a turn once the piece that moved is in its final form
+    // is_white is the player who just moved
+    fn end_turn(&mut self, is_white: bool) {
+        self.test_checkmate_stalemate(is_white);
+        self.events.push(GameEvent::TurnEnded);
+        self.game_save.push('\n');
+        // the board
+        self.game_save.push_str(&format!("{}", self));
+        let save_lines = self.rules.save_lines(self);
+        self.game_save.push_str(&save_lines);
+    }
+    // test for checkmate and stalemate and report anything that was detected
+    // is_white is the player who just moved
+    fn test_checkmate_stalemate(&mut self, is_white: bool) {
+        let rules = self.rules;
+        if let Some((winner, reason)) = rules.game_over(self, is_white) {
+            self.end_game(winner, reason);
+        } else if rules.has_check() && self.test_checkmate(!is_white) {
+            self.events.push(GameEvent::Check { is_white: !is_white });
+        }
+    }
+    // end the game after a move and note why in the save
+    fn end_game(&mut self, winner: i8, reason: GameOverReason) {
+        self.finish(winner, reason);
+        if let Some(note) = reason.save_note() {
+            self.game_save.push_str(note);
+        }
+    }
+    // the game is over, however it ended
+    fn finish(&mut self, winner: i8, reason: GameOverReason) {
+        self.winner = Some(winner);
+        self.termination = Some(reason);
+        self.rewrite_header();
+        self.events.push(GameEvent::GameOver { winner, reason });
+    }
+    // test if the player has anything left on the board
+    pub fn has_pieces(&self, is_white: bool) -> bool {
+        self.board.iter().flatten().flatten().any(|piece| piece.is_white() == is_white)
+    }
+    // how many checks white and black have given
+    pub fn get_checks(&self) -> [u8; 2] {
+        self.checks
+    }
+    // the variant being played. Setting it picks the variant for the next
+    // new game, the one being played doesn't change
+    pub fn get_variant(&self) -> Variant {
+        self.variant
+    }
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.set_rules(rules::variant_rules(variant));
+    }
+    // the rules being played by. Like the variant, new rules wait for the
+    // next new game
+    pub fn get_rules(&self) -> &'static dyn RuleSet {
+        self.rules
+    }
+    pub fn set_rules(&mut self, rules: &'static dyn RuleSet) {
+        self.next_rules = rules;
+    }
+    // the rules the next new game will be played by
+    pub fn get_next_rules(&self) -> &'static dyn RuleSet {
+        self.next_rules
+    }
+    // play by the given rules now, which also sets the variant they build on
+    fn use_rules(&mut self, rules: &'static dyn RuleSet) {
+        self.rules = rules;
+        self.variant = rules.variant();
+    }
+    // the header lines that go at the top of the save
+    fn save_header(&self) -> String {
+        save_file::header_text(&self.metadata())
+    }
+    // the variant the saved game was played as
+    fn save_variant(&self) -> Variant {
+        self.header_value("variant")
+            .and_then(Variant::from_name)
+            .unwrap_or_default()
+    }
+    // the rules the saved game was played by, if they can still be found
+    fn save_rules(&self) -> &'static dyn RuleSet {
+        let variant = self.save_variant();
+        self.header_value("rules")
+            .and_then(rules::rule_set)
+            .filter(|rules| rules.variant() == variant)
+            .unwrap_or_else(|| rules::variant_rules(variant))
+    }
+    // the odds given in the saved game and if white gave them
+    fn save_odds(&self) -> (Odds, bool) {
+        let odds = self.header_value("odds").map(|value| value.split(' ').collect::<Vec<_>>());
+        match odds.as_deref() {
+            Some([odds, giver]) => (Odds::from_name(odds).unwrap_or_default(), *giver != "black"),
+            _ => (Odds::None, true),
+        }
+    }
+    // the time white and black started the saved game with
+    fn save_time_control(&self) -> Option<[u64; 2]> {
+        let mut times = self.header_value("time_control")?.split(' ').map(|time| time.parse().ok());
+        Some([times.next()??, times.next()??])
+    }
+    // how the saved game ended, if the header says
+    fn save_result(&self) -> Option<(i8, GameOverReason)> {
+        let mut result = self.header_value("result")?.split(' ');
+        let winner = save::parse_result(result.next()?)?;
+        let reason = result.next()?;
+        let reason = GameOverReason::ALL.iter().copied().find(|other| other.as_str() == reason)?;
+        Some((winner, reason))
+    }
+    // the rest of the header line starting with the key
+    fn header_value(&self, key: &str) -> Option<&str> {
+        self.game_save.lines()
+            .take_while(|line| line.starts_with(SAVE_HEADER))
+            .filter_map(|line| line[SAVE_HEADER.len()..].strip_prefix(key))
+            .find_map(|value| value.strip_prefix(' '))
+    }
+    // how many lines each turn of the save takes up
+    fn turn_lines(&self) -> usize {
+        let rules = self.save_rules();
+        TURN_INFO_LINES + rules.variant().size()[1] + rules.extra_save_lines()
+    }
+    // the lines of the save after the header
+    fn save_turns(&self) -> impl Iterator<Item = &str> {
+        self.game_save.lines().skip_while(|line| line.starts_with(SAVE_HEADER))
+    }
+    // write the contents of game_save field to a file
+    pub fn save_game(&self, save_name: &str) -> io::Result<()> {
+        let mut file = self.to_save_file().map_err(|e| invalid_data(e.to_string()))?;
+        // the date is the day the game was first saved
+        if file.metadata.date.is_empty() {
+            file.metadata.date = save::today();
+        }
+        save::write_save(save_name, &file.to_json())?;
+        // the recovery file is only for games that haven't been saved
+        save::clear_recovery()
+    }
+    // load the contents of a file into the game_save field. The game
+    // doesn't change if the last position in the save can't be played from
+    pub fn load_game(&mut self, save_name: &str) -> Result<(), LoadError> {
+        let contents = save::read_save(save_name)?;
+        let legacy = !SaveFile::is_save_file(&contents);
+        let loaded = self.read_game(contents)?;
+        // old saves get written back in the new format once they've been
+        // read, so they only need to be changed over once. The game still
+        // loads if that doesn't work, it'll just be changed over next time
+        if legacy {
+            if let Ok(file) = loaded.to_save_file() {
+                save::write_save(save_name, &file.to_json()).ok();
+            }
+        }
+        self.take_history(loaded);
+        Ok(())
+    }
+    // keep the game in the recovery file so it can be restored if the game
+    // crashes, call this after every move. Games that are over or haven't
+    // had a move yet have nothing to restore
+    pub fn autosave(&self) -> io::Result<()> {
+        if self.winner.is_some() || self.get_num_turns() == 0 {
+            return save::clear_recovery();
+        }
+        let file = self.to_save_file().map_err(|e| invalid_data(e.to_string()))?;
+        save::write_recovery(&file.to_json())
+    }
+    // load the game from the recovery file into the game_save field, the
+    // same way load_game does
+    pub fn restore_autosave(&mut self) -> Result<(), LoadError> {
+        let contents = save::read_recovery()?;
+        let loaded = self.read_game(contents)?;
+        self.take_history(loaded);
+        Ok(())
+    }
+    // a board with the history in the contents of a save, as long as the
+    // last position in it can be played from
+    fn read_game(&self, contents: String) -> Result<ChessBoard, LoadError> {
+        let mut loaded = self.without_history();
+        if SaveFile::is_save_file(&contents) {
+            let file = SaveFile::from_json(&contents)?;
+            loaded.read_save_file(&file)?;
+        } else {
+            // saves from before the format had a version are the history as
+            // it's kept in the game, without the position it started from
+            // or who played it
+            loaded.game_save = contents;
+            loaded.start_fen = String::new();
+            loaded.players = [String::new(), String::new()];
+            loaded.event = String::new();
+            loaded.date = String::new();
+        }
+        loaded.start_ply = match fen::start_ply(&loaded.start_fen) {
+            Some(start_ply) => start_ply,
+            // black gets the first move when white gives pawn and move
+            None => match loaded.save_odds() {
+                (Odds::PawnAndMove, true) => 1,
+                _ => 0,
+            },
+        };
+        loaded.check_complete()?;
+        // older saves only have the moves in the log
+        if loaded.moves.len() != loaded.get_num_turns() {
+            loaded.moves = loaded.moves_from_history()?;
+        }
+        // finished games can end in positions like a king that's been
+        // blown up, so only unfinished ones have to be playable
+        let turns = loaded.get_num_turns();
+        if turns > 0 {
+            loaded.load_turn(turns - 1)?;
+            let problems = loaded.validate_position();
+            if loaded.winner.is_none() && !problems.is_empty() {
+                return Err(LoadError::InconsistentState(validate::problems_message(&problems)));
+            }
+            // older headers only had how the game ended when it wasn't a
+            // move, and didn't have who played it
+            loaded.rewrite_header();
+        }
+        Ok(loaded)
+    }
+    // a board with the game in the contents of a save read into it, with
+    // its last turn loaded
+    pub(crate) fn from_save(contents: String) -> Result<ChessBoard, LoadError> {
+        ChessBoard::new().read_game(contents)
+    }
+    // take the history of a board that a game was read into
+    fn take_history(&mut self, loaded: ChessBoard) {
+        self.game_save = loaded.game_save;
+        self.moves = loaded.moves;
+        self.start_ply = loaded.start_ply;
+        self.start_fen = loaded.start_fen;
+        self.players = loaded.players;
+        self.event = loaded.event;
+        self.date = loaded.date;
+    }
+    // return how many turns the loaded game has
+    pub fn get_num_turns(&self) -> usize {
+        self.save_turns().count()/self.turn_lines()
+    }
+    // hand over everything that happened since the last call
+    // after letting the observers know about it
+    fn take_events(&mut self) -> Vec<GameEvent> {
+        let events: Vec<GameEvent> = self.events.drain(..).collect();
+        self.observers.notify(&events);
+        events
+    }
+    // get told about every event in the game from now on
+    pub fn subscribe(&mut self, observer: Arc<dyn GameObserver>) {
+        self.observers.subscribe(observer);
+    }
+    // the log lines of every turn up to and including the given one
+    pub fn get_log(&self, turn: usize) -> Vec<String> {
+        self.save_turns()
+            .skip(8)
+            .step_by(self.turn_lines())
+            .take(turn+1)
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+    // take back the last move by loading the turn before it
+    pub fn undo(&mut self) -> Option<Vec<GameEvent>> {
+        match self.turn_num {
+            0 => None,
+            // there's no saved turn before the first move, so start over
+            // but keep the save around in case the move gets redone
+            1 => {
+                let game_save = mem::take(&mut self.game_save);
+                let start_fen = self.start_fen.clone();
+                if self.load_fen(&start_fen).is_err() {
+                    self.new_game();
+                }
+                self.game_save = game_save;
+                Some(self.take_events())
+            },
+            turn_num => self.load_turn(turn_num-2).ok(),
+        }
+    }
+    // the given player gives up
+    pub fn resign(&mut self, is_white: bool) -> Vec<GameEvent> {
+        let winner = if is_white {
+            -1
+        } else {
+            1
+        };
+        self.finish(winner, GameOverReason::Resignation);
+        self.take_events()
+    }
+    // the names of the players, which go in the save
+    pub fn set_players(&mut self, white: &str, black: &str) {
+        self.players = [String::from(white), String::from(black)];
+        self.rewrite_header();
+    }
+    pub fn get_players(&self) -> [&str; 2] {
+        [&self.players[0], &self.players[1]]
+    }
+    // what the game is being played for, like a tournament
+    pub fn set_event(&mut self, event: &str) {
+        self.event = String::from(event);
+        self.rewrite_header();
+    }
+    pub fn get_event(&self) -> &str {
+        &self.event
+    }
+    // why the game ended, if it has
+    pub fn get_termination(&self) -> Option<GameOverReason> {
+        self.termination
+    }
+    // give odds from the next new game on. giver_is_white is the player
+    // giving them, who starts without the piece
+    pub fn set_odds(&mut self, odds: Odds, giver_is_white: bool) {
+        self.odds = odds;
+        self.odds_giver = giver_is_white;
+    }
+    // the odds being given and if it's white giving them
+    pub fn get_odds(&self) -> (Odds, bool) {
+        (self.odds, self.odds_giver)
+    }
+    // the milliseconds white and black start with from the next new game
+    // on, which can be different to give time odds. None plays without clocks
+    pub fn set_time_control(&mut self, time_control: Option<[u64; 2]>) {
+        self.time_control = time_control;
+    }
+    pub fn get_time_control(&self) -> Option<[u64; 2]> {
+        self.time_control
+    }
+    // milliseconds the player has left, if the game is timed
+    pub fn get_clock(&self, is_white: bool) -> Option<u64> {
+        let index = if is_white {
+            0
+        } else {
+            1
+        };
+        self.time_control.map(|_| self.clocks[index])
+    }
+    // run the clock of the player to move. Running out of time loses
+    pub fn tick(&mut self, elapsed_ms: u64) -> Vec<GameEvent> {
+        if self.time_control.is_none() || self.winner.is_some() {
+            return Vec::new();
+        }
+        // next_turn has already switched players while a pawn is waiting to
+        // be promoted, but it's still the promoting player's time
+        let player = match self.pending_promotion {
+            Some(square) => self.board[square[0]][square[1]].map_or(self.player, |pawn| pawn.is_white()),
+            None => self.player,
+        };
+        let index = if player {
+            0
+        } else {
+            1
+        };
+        self.clocks[index] = self.clocks[index].saturating_sub(elapsed_ms);
+        if self.clocks[index] == 0 {
+            return self.run_out_of_time(player);
+        }
+        Vec::new()
+    }
+    // the player's clock ran out
+    pub fn run_out_of_time(&mut self, is_white: bool) -> Vec<GameEvent> {
+        let winner = if is_white {
+            -1
+        } else {
+            1
+        };
+        self.finish(winner, GameOverReason::OutOfTime);
+        self.take_events()
+    }
+    // both players agreed to a draw
+    pub fn agree_draw(&mut self) -> Vec<GameEvent> {
+        self.finish(0, GameOverReason::DrawAgreed);
+        self.take_events()
+    }
+    // every legal move for the player whose turn it is
+    pub fn legal_moves(&mut self) -> Vec<([usize; 2], [usize; 2])> {
+        let mut moves = Vec::new();
+        for start_row in 0..self.height {
+            for start_col in 0..self.width {
+                let start = [start_col, start_row];
+                let piece = match self.board[start_col][start_row] {
+                    Some(piece) if piece.is_white() == self.player => piece,
+                    _ => continue,
+                };
+                for row in 0..self.height {
+                    for col in 0..self.width {
+                        if self.test_legal(start, [col, row], piece)
+                        && self.is_usual_castle(start, [col, row], piece) {
+                            moves.push((start, [col, row]));
+                        }
+                    }
+                }
+            }
+        }
+        // taking is compulsory in antichess
+        if self.rules.must_capture()
+        && moves.iter().any(|(start, dest)| self.is_capture(*start, *dest)) {
+            moves.retain(|(start, dest)| self.is_capture(*start, *dest));
+        }
+        moves
+    }
+    // drop a piece from the player's reserve onto an empty square in
+    // crazyhouse. Like take_turn this leaves switching players to the caller
+    pub fn try_drop(&mut self, kind: PieceType, square: [usize; 2]) -> Option<Vec<GameEvent>> {
+        if !self.on_board(square) || self.pending_promotion.is_some()
+        || !self.test_drop(kind, square, self.player) {
+            return None;
+        }
+        self.truncate_save();
+        self.moves.push(SavedMove {
+            to: square_name(square),
+            drop: Some(String::from(kind.as_str())),
+            ..SavedMove::default()
+        });
+        self.turn_num += 1;
+        let piece = Piece::new(kind, self.player);
+        self.board[square[0]][square[1]] = Some(piece);
+        let reserve = if self.player {
+            &mut self.white_reserve
+        } else {
+            &mut self.black_reserve
+        };
+        reserve[kind.index()] -= 1;
+        self.halfmove_clock += 1;
+        let dropped = GameEvent::Dropped { turn: self.turn_num, piece, square };
+        let move_string = dropped.log_entry().unwrap_or_default();
+        self.events.push(dropped);
+        self.events.push(GameEvent::ReserveChanged { is_white: self.player });
+        self.clear_en_passant();
+        self.save_move(&move_string);
+        self.events.push(GameEvent::MovesUpdated { turns: self.turn_num });
+        self.end_turn(self.player);
+        Some(self.take_events())
+    }
+    // drop a piece and switch players if it worked, the same way next_turn
+    // does for moves
+    pub fn next_drop(&mut self, kind: PieceType, square: [usize; 2]) -> Option<Vec<GameEvent>> {
+        let events = self.try_drop(kind, square)?;
+        self.switch_player();
+        Some(events)
+    }
+    // test if a piece can be dropped. It has to be in the reserve and go on an
+    // empty square, pawns can't go on the first or last rank, and the drop
+    // can't leave the player's king in check
+    pub fn test_drop(&mut self, kind: PieceType, square: [usize; 2], is_white: bool) -> bool {
+        let reserve = self.get_reserve(is_white);
+        reserve.get(kind.index()).is_some_and(|count| *count > 0)
+        && self.test_drop_square(kind, square, is_white)
+    }
+    // test a drop without caring if the player has the piece
+    fn test_drop_square(&mut self, kind: PieceType, square: [usize; 2], is_white: bool) -> bool {
+        if !self.rules.has_drops() || kind == PieceType::King
+        || self.board[square[0]][square[1]].is_some()
+        || (kind == PieceType::Pawn && (square[1] == 0 || square[1] == self.height - 1)) {
+            return false;
+        }
+        let king_pos = if is_white {
+            self.white_king_pos
+        } else {
+            self.black_king_pos
+        };
+        self.board[square[0]][square[1]] = Some(Piece::new(kind, is_white));
+        let in_check = match king_pos {
+            Some(king_pos) => self.is_king_attacked(king_pos, is_white),
+            None => false,
+        };
+        self.board[square[0]][square[1]] = None;
+        !in_check
+    }
+    // every drop the player can make
+    pub fn legal_drops(&mut self, is_white: bool) -> Vec<(PieceType, [usize; 2])> {
+        let mut drops = Vec::new();
+        if !self.rules.has_drops() {
+            return drops;
+        }
+        for kind in PieceType::ALL[..5].iter() {
+            for row in 0..self.height {
+                for col in 0..self.width {
+                    if self.test_drop(*kind, [col, row], is_white) {
+                        drops.push((*kind, [col, row]));
+                    }
+                }
+            }
+        }
+        drops
+    }
+    // test if a drop of anything could get the player out of check. In
+    // bughouse that isn't mate since the partner might still send the piece.
+    // A queen can go anywhere any other piece could block from
+    fn could_block_with_drop(&mut self, is_white: bool) -> bool {
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if self.test_drop_square(PieceType::Queen, [col, row], is_white) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+    // give the player a piece to drop, from a capture on the other board in bughouse
+    pub fn add_to_reserve(&mut self, is_white: bool, kind: PieceType) -> Vec<GameEvent> {
+        if is_white {
+            self.white_reserve[kind.index()] += 1;
+        } else {
+            self.black_reserve[kind.index()] += 1;
+        }
+        self.events.push(GameEvent::ReserveChanged { is_white });
+        self.take_events()
+    }
+    // if the piece on square got there by promotion
+    pub fn is_promoted(&self, square: [usize; 2]) -> bool {
+        self.on_board(square) && self.promoted[square[0]][square[1]]
+    }
+    // how many of each piece the player has to drop, in the order of PieceType
+    pub fn get_reserve(&self, is_white: bool) -> [u8; 5] {
+        if is_white {
+            self.white_reserve
+        } else {
+            self.black_reserve
+        }
+    }
+    // a castle can be entered two ways, so only list the one that moves
+    // the king where it ends up if that works, or onto the rook if not
+    fn is_usual_castle(&self, start: [usize; 2], dest: [usize; 2], piece: Piece) -> bool {
+        if piece.get_piece_type() != PieceType::King {
+            return true;
+        }
+        match self.find_castle(start, dest, piece.is_white()) {
+            Some(castle) => {
+                let king_dest = castle.0;
+                dest == king_dest || self.find_castle(start, king_dest, piece.is_white()) != Some(castle)
+            },
+            None => true,
+        }
+    }
+    // the cheap copy of a board. It has the current position without the
+    // saved history, which is all a search needs, so it can be copied over
+    // and over without the copies getting bigger as the game goes on
+    pub fn without_history(&self) -> Self {
+        ChessBoard {
+            board: self.board,
+            width: self.width,
+            height: self.height,
+            player: self.player,
+            score: self.score,
+            winner: self.winner,
+            white_captured: self.white_captured.clone(),
+            black_captured: self.black_captured.clone(),
+            white_en_passant: self.white_en_passant,
+            black_en_passant: self.black_en_passant,
+            white_castle_rooks: self.white_castle_rooks,
+            black_castle_rooks: self.black_castle_rooks,
+            white_king_pos: self.white_king_pos,
+            black_king_pos: self.black_king_pos,
+            game_save: String::new(),
+            moves: Vec::new(),
+            turn_num: self.turn_num,
+            halfmove_clock: self.halfmove_clock,
+            start_ply: self.start_ply,
+            start_fen: self.start_fen.clone(),
+            variant: self.variant,
+            rules: self.rules,
+            next_rules: self.next_rules,
+            odds: self.odds,
+            odds_giver: self.odds_giver,
+            time_control: self.time_control,
+            clocks: self.clocks,
+            players: self.players.clone(),
+            event: self.event.clone(),
+            date: self.date.clone(),
+            termination: self.termination,
+            checks: self.checks,
+            white_reserve: self.white_reserve,
+            black_reserve: self.black_reserve,
+            promoted: self.promoted,
+            pending_promotion: self.pending_promotion,
+            events: Vec::new(),
+            observers: Observers::default(),
+        }
+    }
+    // load a specific turn from the game. Nothing changes if the turn
+    // can't be read
+    pub fn load_turn(&mut self, turn: usize) -> Result<Vec<GameEvent>, LoadError> {
+        let saved = self.saved_turn(turn)?;
+        let board = fen::parse_placement(&saved.board, self.save_variant().size())
+            .map_err(|e| save_file::placement_error(turn, &saved.board, e))?;
+        // plus 1 since the current move will be AFTER the turn that
+        // gets loaded
+        self.turn_num =  turn+1;
+        let rules = self.save_rules();
+        self.use_rules(rules);
+        // keep what the game was played with so replays can show it
+        let (odds, odds_giver) = self.save_odds();
+        self.set_odds(odds, odds_giver);
+        self.time_control = self.save_time_control();
+        let [width, height] = self.variant.size();
+        self.width = width;
+        self.height = height;
+        // read the fields of the turn
+        self.score = saved.score;
+        let [white_captured, black_captured] = saved.captured;
+        self.white_captured = white_captured;
+        self.black_captured = black_captured;
+        self.events.push(GameEvent::ScoreChanged { score: self.score });
+        // set the player
+        self.player = (self.start_ply + turn)%2 == 1;
+        // set the rooks each player can castle with
+        let [white_castle_rooks, black_castle_rooks] = saved.castle_rooks;
+        self.white_castle_rooks = white_castle_rooks;
+        self.black_castle_rooks = black_castle_rooks;
+        // the save doesn't keep track of this
+        self.halfmove_clock = 0;
+        self.checks = saved.checks.unwrap_or_default();
+        // crazyhouse reserves and promoted pieces
+        let [white_reserve, black_reserve] = saved.reserves.unwrap_or_default();
+        self.white_reserve = white_reserve;
+        self.black_reserve = black_reserve;
+        self.promoted = [[false; MAX_SIZE]; MAX_SIZE];
+        for square in saved.promoted.iter() {
+            if let Some(square) = fen::parse_square(square).filter(|square| self.on_board(*square)) {
+                self.promoted[square[0]][square[1]] = true;
+            }
+        }
+        if self.rules.has_drops() {
+            self.events.push(GameEvent::ReserveChanged { is_white: true });
+            self.events.push(GameEvent::ReserveChanged { is_white: false });
+        }
+        // en passant squares are behind the pawn that moved
+        self.white_en_passant = saved.en_passant[0].map(|col| [col, 2]);
+        self.black_en_passant = saved.en_passant[1].map(|col| [col, height - 3]);
+        // load the board. Turns are only saved once they're finished
+        self.board = board;
+        self.pending_promotion = None;
+        self.white_king_pos = self.find_king(true);
+        self.black_king_pos = self.find_king(false);
+        self.events.push(GameEvent::TurnLoaded { turn });
+        let move_line = saved.log.trim();
+        // make the game end popup show up
+        let reason = GameOverReason::ALL.iter().copied().find(|reason| {
+            reason.save_note().is_some_and(|note| move_line.ends_with(note))
+        });
+        self.winner = reason.map(|reason| {
+            let mover_wins = if self.player {
+                -1
+            } else {
+                1
+            };
+            let winner = match reason {
+                GameOverReason::Stalemate if self.rules.stalemate_wins() => -mover_wins,
+                GameOverReason::Stalemate => 0,
+                GameOverReason::NoPiecesLeft => -mover_wins,
+                GameOverReason::RacingKings => self.race_winner().unwrap_or(mover_wins),
+                _ => mover_wins,
+            };
+            self.events.push(GameEvent::GameOver { winner, reason });
+            winner
+        });
+        self.termination = reason;
+        // games that ended some other way, like a resignation, have how in
+        // the header, which counts once the last turn is loaded
+        if reason.is_none() && turn + 1 == self.get_num_turns() {
+            if let Some((winner, reason)) = self.save_result() {
+                self.winner = Some(winner);
+                self.termination = Some(reason);
+                self.events.push(GameEvent::GameOver { winner, reason });
+            }
+        }
+        Ok(self.take_events())
+    }
+    // constructor
+    pub fn new() -> Self {
+        let mut chess_board = ChessBoard {
+            board: [[None; MAX_SIZE]; MAX_SIZE],
+            width: 8,
+            height: 8,
+            player: true,
+            score: [0; 2],
+            winner: None,
+            white_captured: String::new(),
+            black_captured: String::new(),
+            white_en_passant: None,
+            black_en_passant: None,
+            white_castle_rooks: [None; 2],
+            black_castle_rooks: [None; 2],
+            white_king_pos: Some([4, 0]),
+            black_king_pos: Some([4, 7]),
+            game_save: String::new(),
+            moves: Vec::new(),
+            turn_num: 0,
+            halfmove_clock: 0,
+            start_ply: 0,
+            start_fen: String::new(),
+            variant: Variant::Standard,
+            rules: &StandardRules,
+            next_rules: &StandardRules,
+            odds: Odds::None,
+            odds_giver: true,
+            time_control: None,
+            clocks: [0; 2],
+            players: [String::new(), String::new()],
+            event: String::new(),
+            date: String::new(),
+            termination: None,
+            checks: [0; 2],
+            white_reserve: [0; 5],
+            black_reserve: [0; 5],
+            promoted: [[false; MAX_SIZE]; MAX_SIZE],
+            pending_promotion: None,
+            events: Vec::new(),
+            observers: Observers::default(),
+        };
+        chess_board.new_game();
+        chess_board
+    }
+    // start a chess960 game from one of the 960 starting positions,
+    // numbered the usual way so that 518 is the normal setup
+    pub fn new_chess960(&mut self, index: u16) {
+        // the positions are only worked out for eight files
+        if self.next_rules.variant().size()[0] != 8 {
+            return self.new_game();
+        }
+        self.set_up(&chess960_back_rank(index % 960));
+    }
+    // reset everything for a new game with the given back rank, which is
+    // as wide as the board
+    fn set_up(&mut self, back_rank: &[PieceType]) {
+        self.use_rules(self.next_rules);
+        self.player = true;
+        self.width = back_rank.len();
+        self.height = self.variant.size()[1];
+        self.board = Self::new_board(back_rank, self.height);
+        self.score = [0; 2];
+        self.winner = None;
+        self.termination = None;
+        self.date = String::new();
+        self.white_captured = String::new();
+        self.black_captured = String::new();
+        self.white_en_passant = None;
+        self.black_en_passant = None;
+        let king_col = back_rank
+            .iter()
+            .position(|kind| *kind == PieceType::King)
+            .unwrap_or(4);
+        let left_rook = back_rank[..king_col]
+            .iter()
+            .position(|kind| *kind == PieceType::Rook);
+        let right_rook = back_rank[king_col..]
+            .iter()
+            .rposition(|kind| *kind == PieceType::Rook)
+            .map(|col| col + king_col);
+        self.white_castle_rooks = [left_rook, right_rook];
+        self.black_castle_rooks = [left_rook, right_rook];
+        self.white_king_pos = Some([king_col, 0]);
+        self.black_king_pos = Some([king_col, self.height - 1]);
+        // some variants like horde change the position from there
+        let rules = self.rules;
+        rules.set_up(self);
+        self.give_odds();
+        self.turn_num = 0;
+        self.halfmove_clock = 0;
+        // black goes first if white gave pawn and move
+        self.start_ply = if self.player {
+            0
+        } else {
+            1
+        };
+        self.clocks = self.time_control.unwrap_or_default();
+        self.checks = [0; 2];
+        self.white_reserve = [0; 5];
+        self.black_reserve = [0; 5];
+        self.promoted = [[false; MAX_SIZE]; MAX_SIZE];
+        self.pending_promotion = None;
+        self.start_fen = self.to_fen(false);
+    }
+    // take away what the player giving odds starts without
+    fn give_odds(&mut self) {
+        let is_white = self.odds_giver;
+        let (back_row, pawn_row) = if is_white {
+            (0, 1)
+        } else {
+            (self.height - 1, self.height - 2)
+        };
+        // the piece of that kind nearest the a file
+        let find = |board: &Squares, kind| {
+            (0..self.width).find(|col| board[*col][back_row] == Some(Piece::new(kind, is_white)))
+        };
+        match self.odds {
+            Odds::None => {},
+            Odds::PawnAndMove => {
+                if self.board[5][pawn_row] == Some(Piece::new(PieceType::Pawn, is_white)) {
+                    self.board[5][pawn_row] = None;
+                }
+                self.player = !is_white;
+            },
+            Odds::Knight => {
+                if let Some(col) = find(&self.board, PieceType::Knight) {
+                    self.board[col][back_row] = None;
+                }
+            },
+            Odds::Rook => {
+                if let Some(col) = find(&self.board, PieceType::Rook) {
+                    self.board[col][back_row] = None;
+                    self.remove_castle_rook([col, back_row], is_white);
+                }
+            },
+            Odds::Queen => {
+                if let Some(col) = find(&self.board, PieceType::Queen) {
+                    self.board[col][back_row] = None;
+                }
+            },
+        }
+    }
+    // test a move and see (regarless of actual legality) if it will put
+    // the current player's king in check
+    pub fn test_check(
+        &mut self, 
+        start: [usize; 2], 
+        dest: [usize; 2], 
+        is_white: bool) -> bool {
+        let start_piece = self.board[start[0]][start[1]];
+        let dest_piece = self.board[dest[0]][dest[1]];
+        self.board[dest[0]][dest[1]] = start_piece;
+        self.board[start[0]][start[1]] = None;
+        // we need to check a different position if it's the king that moved
+        let king_pos = if let Some(piece) = start_piece {
+            if piece.get_piece_type() == PieceType::King && piece.is_white() == is_white {
+                Some(dest)
+            } else {
+                if is_white {
+                    self.white_king_pos
+                } else {
+                    self.black_king_pos
+                }
+            }
+        } else {
+            if is_white {
+                self.white_king_pos
+            } else {
+                self.black_king_pos
+            }
+        };
+        // a side with no king can't be in check
+        let in_check = match king_pos {
+            Some(king_pos) => self.is_threatened(king_pos, is_white),
+            None => false,
+        };
+        self.board[start[0]][start[1]] = start_piece;
+        self.board[dest[0]][dest[1]] = dest_piece;
+        in_check
+    }
+    // test checkmate on one of the two kings
+    pub fn test_checkmate(&mut self, is_white: bool) -> bool {
+        let king_pos = if is_white {
+            self.white_king_pos
+        } else {
+            self.black_king_pos
+        };
+        let king_pos = match king_pos {
+            Some(king_pos) => king_pos,
+            None => return false,
+        };
+        if self.is_king_attacked(king_pos, is_white) {
+            return true;
+        }
+        false
+    }
+    // test if the king is in stalemate
+    pub fn test_stalemate(&mut self, is_white: bool) -> bool {
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if let Some(piece) = self.board[col][row] {
+                    if piece.is_white() == is_white
+                    && self.test_block([col, row], piece) {
+                        return false;
+                    }
+                }
+            }
+        }
+        // a piece from the reserve might be able to block check
+        if self.rules.passes_captures() && self.could_block_with_drop(is_white) {
+            return false;
+        }
+        self.legal_drops(is_white).is_empty()
+    }
+    // test if a piece can block check for resolving checkmate
+    fn test_block(&mut self, start: [usize; 2], piece: Piece) -> bool {
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if self.test_legal(start, [col, row], piece) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+    // test if a move is allowed and doesn't leave the king in check. Castles
+    // are already checked for check by test_move, and the king might have
+    // been dropped onto its own rook which test_check can't handle
+    fn test_legal(&mut self, start: [usize; 2], dest: [usize; 2], piece: Piece) -> bool {
+        if !self.on_board(dest) || !piece.test_move(start, dest, self) {
+            return false;
+        }
+        if piece.get_piece_type() == PieceType::King
+        && self.find_castle(start, dest, piece.is_white()).is_some() {
+            return true;
+        }
+        let rules = self.rules;
+        rules.is_legal(self, start, dest, piece)
+    }
+    // test if a move takes something, including en passant
+    pub fn is_capture(&self, start: [usize; 2], dest: [usize; 2]) -> bool {
+        if self.board[dest[0]][dest[1]].is_some() {
+            return true;
+        }
+        match self.board[start[0]][start[1]] {
+            Some(piece) => piece.get_piece_type() == PieceType::Pawn && start[0] != dest[0],
+            None => false,
+        }
+    }
+    // test if the player can take anything, since they have to in antichess
+    pub fn can_capture(&mut self, is_white: bool) -> bool {
+        for start_row in 0..self.height {
+            for start_col in 0..self.width {
+                let start = [start_col, start_row];
+                let piece = match self.board[start_col][start_row] {
+                    Some(piece) if piece.is_white() == is_white => piece,
+                    _ => continue,
+                };
+                for row in 0..self.height {
+                    for col in 0..self.width {
+                        if self.is_capture(start, [col, row]) && piece.test_move(start, [col, row], self) {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+    // find where the player's king is, if it hasn't been blown up
+    pub fn find_king(&self, is_white: bool) -> Option<[usize; 2]> {
+        let king = Some(Piece::new(PieceType::King, is_white));
+        for col in 0..self.width {
+            for row in 0..self.height {
+                if self.board[col][row] == king {
+                    return Some([col, row]);
+                }
+            }
+        }
+        None
+    }
+    // work out if moving the king from start to dest is meant as a castle,
+    // either by moving it onto one of its castling rooks or two or more
+    // squares towards the c or g file. Returns where the king ends up and
+    // where the rook starts and ends up
+    pub fn find_castle(
+        &self,
+        start: [usize; 2],
+        dest: [usize; 2],
+        is_white: bool) -> Option<([usize; 2], [usize; 2], [usize; 2])> {
+        let row = if is_white {
+            0
+        } else {
+            self.height - 1
+        };
+        if start[1] != row || dest[1] != row || !self.rules.can_castle() {
+            return None;
+        }
+        // the king ends up on the c file or next to the corner, with the
+        // rook on its other side
+        let king_files = [2, self.width - 2];
+        let rook_files = [3, self.width - 3];
+        let rooks = self.get_castle_rooks(is_white);
+        for side in 0..2 {
+            if let Some(rook_col) = rooks[side].filter(|col| *col < self.width) {
+                let x_dist = (start[0] as i32 - dest[0] as i32).abs();
+                if dest[0] == rook_col
+                || (dest[0] == king_files[side] && x_dist >= 2) {
+                    return Some((
+                        [king_files[side], row],
+                        [rook_col, row],
+                        [rook_files[side], row],
+                    ));
+                }
+            }
+        }
+        None
+    }
+    // test if a castle is allowed. Everything between the king, the rook and
+    // where they end up has to be empty, and the king can't castle out of,
+    // through or into check
+    pub fn test_castle(&mut self, start: [usize; 2], dest: [usize; 2], is_white: bool) -> bool {
+        let (king_dest, rook_start, rook_dest) = match self.find_castle(start, dest, is_white) {
+            Some(castle) => castle,
+            None => return false,
+        };
+        let row = start[1];
+        match self.board[rook_start[0]][row] {
+            Some(piece) if piece.is_white() == is_white
+            && piece.get_piece_type() == PieceType::Rook => {},
+            _ => return false,
+        }
+        let cols = [start[0], king_dest[0], rook_start[0], rook_dest[0]];
+        let left = *cols.iter().min().unwrap();
+        let right = *cols.iter().max().unwrap();
+        for col in left..=right {
+            if col != start[0] && col != rook_start[0]
+            && self.board[col][row].is_some() {
+                return false;
+            }
+        }
+        // take the king off while checking so it doesn't block anything
+        let king = self.board[start[0]][row].take();
+        let mut allowed = true;
+        for col in start[0].min(king_dest[0])..=start[0].max(king_dest[0]) {
+            if self.is_king_attacked([col, row], is_white) {
+                allowed = false;
+                break;
+            }
+        }
+        // the rook can block attacks on where the king ends up, or stop
+        // blocking them once it's moved
+        let rook = self.board[rook_start[0]][row].take();
+        if allowed {
+            self.board[rook_dest[0]][row] = rook;
+            self.board[king_dest[0]][row] = king;
+            allowed = !self.is_king_attacked(king_dest, is_white);
+            self.board[king_dest[0]][row] = None;
+            self.board[rook_dest[0]][row] = None;
+        }
+        self.board[start[0]][row] = king;
+        self.board[rook_start[0]][row] = rook;
+        allowed
+    }
+    // the player can't castle with a rook once it has moved or been captured
+    fn remove_castle_rook(&mut self, space: [usize; 2], is_white: bool) {
+        let (row, rooks) = if is_white {
+            (0, &mut self.white_castle_rooks)
+        } else {
+            (self.height - 1, &mut self.black_castle_rooks)
+        };
+        if space[1] == row {
+            for rook in rooks.iter_mut() {
+                if *rook == Some(space[0]) {
+                    *rook = None;
+                }
+            }
+        }
+    }
+    // capture a piece (remove it from board and increment score)
+    pub fn capture(&mut self, space: [usize; 2]) -> String {
+        if let Some(piece) = self.take_piece(space) {
+            let captured = GameEvent::Captured { piece, square: space };
+            let capture_string = captured.log_entry().unwrap_or_default();
+            self.events.push(captured);
+            self.events.push(GameEvent::ScoreChanged { score: self.score });
+            return capture_string;
+        }
+        String::new()
+    }
+    // take a piece off the board and give the other player its points
+    fn take_piece(&mut self, space: [usize; 2]) -> Option<Piece> {
+        let piece = self.board[space[0]][space[1]].take()?;
+        let was_promoted = mem::take(&mut self.promoted[space[0]][space[1]]);
+        // in crazyhouse the piece changes sides and goes in the reserve, and
+        // promoted pieces go back to being pawns. In bughouse it goes to the
+        // partner instead, who plays the same colour as the piece
+        if self.rules.has_drops() && PieceType::ALL[..5].contains(&piece.get_piece_type()) {
+            let kind = if was_promoted {
+                PieceType::Pawn
+            } else {
+                piece.get_piece_type()
+            };
+            if self.rules.passes_captures() {
+                self.events.push(GameEvent::Passed { piece: Piece::new(kind, piece.is_white()) });
+                return self.finish_take(space, piece);
+            }
+            let reserve = if piece.is_white() {
+                &mut self.black_reserve
+            } else {
+                &mut self.white_reserve
+            };
+            reserve[kind.index()] += 1;
+            self.events.push(GameEvent::ReserveChanged { is_white: !piece.is_white() });
+        }
+        self.finish_take(space, piece)
+    }
+    // the score and castling side of taking a piece
+    fn finish_take(&mut self, space: [usize; 2], piece: Piece) -> Option<Piece> {
+        if piece.is_white() {
+            self.score[1] += piece.get_points();
+            self.black_captured.push_str(piece.as_str());
+        } else {
+            self.score[0] += piece.get_points();
+            self.white_captured.push_str(piece.as_str());
+        }
+        // the rook can't be castled with any more
+        if piece.get_piece_type() == PieceType::Rook {
+            self.remove_castle_rook(space, piece.is_white());
+        }
+        Some(piece)
+    }
+    // check if the player's king would be attacked on a square, which is
+    // up to the rules
+    pub fn is_king_attacked(&mut self, space: [usize; 2], is_white: bool) -> bool {
+        let rules = self.rules;
+        rules.is_king_attacked(self, space, is_white)
+    }
+    // check if a square is threatened
+    pub fn is_threatened(&mut self, space: [usize; 2], is_white: bool) -> bool {
+        // Pawns won't return that they can move to a square they threaten unless
+        // there is a piece on it, so we create a "dummy" piece and then delete it
+        let space_is_empty = if self.board[space[0]][space[1]].is_none() {
+            self.board[space[0]][space[1]] = Some(Piece::new(PieceType::Pawn, is_white));
+            true
+        } else {
+            false
+        };
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if let Some(piece) = self.board[col][row] {
+                    // kings can't capture in atomic chess
+                    if piece.is_white() != is_white
+                    && (self.rules.kings_can_capture() || piece.get_piece_type() != PieceType::King)
+                    && piece.test_move([col, row], space, self) {
+                        if space_is_empty {
+                            self.board[space[0]][space[1]] = None;
+                        }
+                        return true;
+                    }
+                }
+            }
+        }
+        if space_is_empty {
+            self.board[space[0]][space[1]] = None;
+        }
+        false
+    }
+    // accessors and mutators for king positions
+    pub fn get_white_king_pos(&self) -> Option<[usize; 2]> {
+        self.white_king_pos
+    }
+    pub fn get_black_king_pos(&self) -> Option<[usize; 2]> {
+        self.black_king_pos
+    }
+    pub fn set_white_king_pos(&mut self, pos: Option<[usize; 2]>) {
+        self.white_king_pos = pos;
+    }
+    pub fn set_black_king_pos(&mut self, pos: Option<[usize; 2]>) {
+        self.black_king_pos = pos;
+    }
+    // accessors and mutators for en passant
+    pub fn get_white_en_passant(&self) -> Option<[usize; 2]> {
+        self.white_en_passant
+    }
+    pub fn get_black_en_passant(&self) -> Option<[usize; 2]> {
+        self.black_en_passant
+    }
+    pub fn set_white_en_passant(&mut self, space: Option<[usize; 2]>) {
+        self.white_en_passant = space;
+    }
+    pub fn set_black_en_passant(&mut self, space: Option<[usize; 2]>) {
+        self.black_en_passant = space;
+    }
+    // accessors and mutators for castling, as the files of the left and right rooks
+    pub fn get_castle_rooks(&self, is_white: bool) -> [Option<usize>; 2] {
+        if is_white {
+            self.white_castle_rooks
+        } else {
+            self.black_castle_rooks
+        }
+    }
+    pub fn set_castle_rooks(&mut self, is_white: bool, rooks: [Option<usize>; 2]) -> Result<(), String> {
+        if let Some(file) = rooks.iter().flatten().find(|file| **file >= self.width) {
+            return Err(format!("there's no file {} on a board {} files wide", file + 1, self.width));
+        }
+        if is_white {
+            self.white_castle_rooks = rooks;
+        } else {
+            self.black_castle_rooks = rooks;
+        }
+        Ok(())
+    }
+    // return board
+    pub fn get_board(&self) -> &Squares {
+        &self.board
+    }
+    // how many files and ranks the board has
+    pub fn get_size(&self) -> [usize; 2] {
+        [self.width, self.height]
+    }
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+    // test if a square is on the board
+    pub fn on_board(&self, square: [usize; 2]) -> bool {
+        square[0] < self.width && square[1] < self.height
+    }
+    // set a piece at the given position
+    pub fn set(&mut self, pos: [usize; 2], piece: Option<Piece>) {
+        self.board[pos[0]][pos[1]] = piece;
+    }
+    // turn the pawn waiting on dest into a different piece, which finishes
+    // its turn. The colour comes from the pawn rather than the player to
+    // move, since next_turn has already switched players by the time the
+    // front end asks
+    pub fn upgrade_pawn(&mut self, dest: [usize; 2], kind: PieceType) -> Result<Vec<GameEvent>, String> {
+        if !self.on_board(dest) {
+            return Err(format!("{} isn't on the board", square_name(dest)));
+        }
+        if self.pending_promotion != Some(dest) {
+            return Err(format!("there's no pawn waiting to be promoted on {}", square_name(dest)));
+        }
+        let is_white = match self.board[dest[0]][dest[1]] {
+            Some(pawn) => pawn.is_white(),
+            None => return Err(format!("there's no pawn on {}", square_name(dest))),
+        };
+        if !self.can_promote_to(kind) {
+            return Err(format!("pawns can't be promoted to a {} in {}", kind.as_str(), self.rules.name()));
+        }
+        self.pending_promotion = None;
+        if let Some(saved_move) = self.moves.last_mut() {
+            saved_move.promotion = Some(String::from(kind.as_str()));
+        }
+        let piece = Piece::new(kind, is_white);
+        self.board[dest[0]][dest[1]] = Some(piece);
+        self.promoted[dest[0]][dest[1]] = true;
+        let promoted = GameEvent::Promoted { square: dest, piece };
+        let promote_string = promoted.log_entry().unwrap_or_default();
+        self.events.push(promoted);
+        self.game_save.push_str(&promote_string);
+        self.end_turn(is_white);
+        Ok(self.take_events())
+    }
+    // the square of the pawn waiting to be promoted, if there is one
+    pub fn get_pending_promotion(&self) -> Option<[usize; 2]> {
+        self.pending_promotion
+    }
+    // the pieces a pawn can turn into, which is up to the rules
+    pub fn get_promotion_options(&self) -> Vec<PieceType> {
+        self.rules.promotion_options()
+    }
+    pub fn can_promote_to(&self, kind: PieceType) -> bool {
+        self.rules.promotion_options().contains(&kind)
+    }
+    // make a new board. I could have repurposed my file loading method here, but I want
+    // to have the default board hardcoded so that users can't delete the default board
+    // save and break the game.
+    fn new_board(back_rank: &[PieceType], height: usize) -> Squares {
+        let mut board: Squares = [[None; MAX_SIZE]; MAX_SIZE];
+        for (col, kind) in back_rank.iter().enumerate() {
+            // White Pawns
+            board[col][1] = Some(Piece::new(PieceType::Pawn, true));
+            // Black Pawns
+            board[col][height - 2] = Some(Piece::new(PieceType::Pawn, false));
+            // White and Black back ranks
+            board[col][0] = Some(Piece::new(*kind, true));
+            board[col][height - 1] = Some(Piece::new(*kind, false));
+        }
+        // return
+        board
+    }
+}
+
+// pick one of the 960 starting positions using the clock, which is random
+// enough for choosing a setup
+pub fn random_chess960_index() -> u16 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.subsec_nanos())
+        .unwrap_or(0);
+    (nanos % 960) as u16
+}
+// work out the back rank for a chess960 position from its number
+fn chess960_back_rank(index: u16) -> [PieceType; 8] {
+    let mut back_rank = [None; 8];
+    let mut n = index as usize;
+    // bishops go on opposite colours
+    back_rank[(n % 4)*2 + 1] = Some(PieceType::Bishop);
+    n /= 4;
+    back_rank[(n % 4)*2] = Some(PieceType::Bishop);
+    n /= 4;
+    // then the queen and knights on the empty squares that are left
+    place_on_empty(&mut back_rank, n % 6, PieceType::Queen);
+    n /= 6;
+    let knights = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+    let (first, second) = knights[n];
+    // place the second one first so the first doesn't shift it over
+    place_on_empty(&mut back_rank, second, PieceType::Knight);
+    place_on_empty(&mut back_rank, first, PieceType::Knight);
+    // and the king always goes between the rooks
+    for kind in [PieceType::Rook, PieceType::King, PieceType::Rook].iter() {
+        place_on_empty(&mut back_rank, 0, *kind);
+    }
+    let mut pieces = [PieceType::Pawn; 8];
+    for (col, kind) in back_rank.iter().enumerate() {
+        pieces[col] = kind.unwrap();
+    }
+    pieces
+}
+// put a piece on the nth empty square of the back rank
+fn place_on_empty(back_rank: &mut [Option<PieceType>; 8], n: usize, kind: PieceType) {
+    if let Some(square) = back_rank.iter_mut().filter(|square| square.is_none()).nth(n) {
+        *square = Some(kind);
+    }
+}
+// the squares around a square on a board of the given size, for explosions
+// in atomic chess
+fn neighbours(square: [usize; 2], size: [usize; 2]) -> impl Iterator<Item = [usize; 2]> {
+    let cols = square[0].saturating_sub(1)..=(square[0] + 1).min(size[0] - 1);
+    cols.flat_map(move |col| {
+        let rows = square[1].saturating_sub(1)..=(square[1] + 1).min(size[1] - 1);
+        rows.map(move |row| [col, row])
+    })
+    .filter(move |space| *space != square)
+}
+// the castling line of a save. Games that use the corner rooks keep the
+// old b, l and r letters, anything else is the file of each rook or a dash
+fn castle_save_string(rooks: [Option<usize>; 2]) -> String {
+    match rooks {
+        [Some(0), Some(7)] => String::from("b"),
+        [Some(0), None] => String::from("l"),
+        [None, Some(7)] => String::from("r"),
+        [None, None] => String::new(),
+        _ => rooks
+            .iter()
+            .map(|rook| match rook {
+                Some(col) if *col < MAX_SIZE => (b'A' + *col as u8) as char,
+                _ => '-',
+            })
+            .collect(),
+    }
+}
+// files and saves that can't be read are reported as invalid data
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+// read a castling line written by castle_save_string
+fn parse_castle_save(line: &str) -> [Option<usize>; 2] {
+    match line {
+        "b" => [Some(0), Some(7)],
+        "l" => [Some(0), None],
+        "r" => [None, Some(7)],
+        _ => {
+            let mut rooks = [None; 2];
+            for (rook, file) in rooks.iter_mut().zip(line.chars()) {
+                if file.is_ascii_uppercase() && (file as usize - 'A' as usize) < MAX_SIZE {
+                    *rook = Some(file as usize - 'A' as usize);
+                }
+            }
+            rooks
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a board set up from a FEN, which has to load
+    fn board(fen: &str) -> ChessBoard {
+        let mut board = ChessBoard::new();
+        board.load_fen(fen).unwrap();
+        board
+    }
+
+    #[test]
+    fn only_the_waiting_pawn_can_be_promoted() {
+        let mut board = board("8/P7/8/8/8/8/8/k6K w - - 0 1");
+        let events = board.next_turn([0, 6], [0, 7]).unwrap();
+        assert!(events.contains(&GameEvent::PromotionPending { square: [0, 7] }));
+        // nothing else moves until the promotion is picked
+        assert!(board.next_turn([0, 0], [1, 0]).is_none());
+        assert!(board.upgrade_pawn([7, 0], PieceType::Queen).is_err());
+        assert!(board.upgrade_pawn([20, 3], PieceType::Queen).is_err());
+        // kings are only allowed in antichess, and the pawn keeps waiting
+        assert!(board.upgrade_pawn([0, 7], PieceType::King).is_err());
+        assert_eq!(board.get_pending_promotion(), Some([0, 7]));
+        let events = board.upgrade_pawn([0, 7], PieceType::Queen).unwrap();
+        assert!(events.contains(&GameEvent::TurnEnded));
+        assert_eq!(board.get_board()[0][7], Some(Piece::new(PieceType::Queen, true)));
+        // and it can't be promoted again
+        assert!(board.upgrade_pawn([0, 7], PieceType::Knight).is_err());
+        assert_eq!(board.get_num_turns(), 1);
+    }
+
+    // a crazyhouse game from a FEN, with a knight and a pawn for white to drop
+    fn crazyhouse(fen: &str) -> ChessBoard {
+        let mut board = ChessBoard::new();
+        board.set_variant(Variant::Crazyhouse);
+        board.new_game();
+        board.load_fen(fen).unwrap();
+        board.add_to_reserve(true, PieceType::Knight);
+        board.add_to_reserve(true, PieceType::Pawn);
+        board
+    }
+
+    #[test]
+    fn drops_follow_the_rules() {
+        let mut board = crazyhouse("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        // pawns can't go on the first or last rank, nothing goes on a
+        // piece, and the player has to have the piece
+        assert!(board.try_drop(PieceType::Pawn, [0, 7]).is_none());
+        assert!(board.try_drop(PieceType::Pawn, [0, 0]).is_none());
+        assert!(board.try_drop(PieceType::Knight, [4, 0]).is_none());
+        assert!(board.try_drop(PieceType::Queen, [3, 3]).is_none());
+        assert!(board.try_drop(PieceType::Knight, [20, 3]).is_none());
+        // like take_turn, try_drop leaves switching players to the caller
+        assert!(board.try_drop(PieceType::Knight, [3, 3]).is_some());
+        assert!(board.get_player());
+        assert_eq!(board.get_reserve(true)[PieceType::Knight.index()], 0);
+        board.set_player(true);
+        assert!(board.next_drop(PieceType::Pawn, [3, 4]).is_some());
+        assert!(!board.get_player());
+        assert_eq!(board.get_num_turns(), 2);
+    }
+
+    #[test]
+    fn drops_cant_leave_the_king_in_check() {
+        // the rook gives check down the e file, so only blocking drops work
+        let mut board = crazyhouse("4r2k/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert!(board.try_drop(PieceType::Knight, [0, 3]).is_none());
+        assert!(board.next_drop(PieceType::Knight, [4, 3]).is_some());
+    }
+
+    #[test]
+    fn chess960_castles_land_on_the_usual_squares() {
+        // the kings start on the f file with rooks on b and g, and castle by
+        // moving onto their own rook
+        let mut board = board("1r3kr1/6p1/8/8/8/8/6P1/1R3KR1 w BGbg - 0 1");
+        let king = |is_white| Some(Piece::new(PieceType::King, is_white));
+        let rook = |is_white| Some(Piece::new(PieceType::Rook, is_white));
+        assert_eq!(board.find_castle([5, 0], [1, 0], true), Some(([2, 0], [1, 0], [3, 0])));
+        board.next_turn([5, 0], [1, 0]).unwrap();
+        let squares = board.get_board();
+        assert_eq!(squares[2][0], king(true));
+        assert_eq!(squares[3][0], rook(true));
+        assert_eq!(squares[1][0], None);
+        assert_eq!(squares[5][0], None);
+        assert_eq!(squares[6][0], rook(true));
+        assert_eq!(board.get_castle_rooks(true), [None; 2]);
+        // on the other side the king and rook swap places
+        board.next_turn([5, 7], [6, 7]).unwrap();
+        let squares = board.get_board();
+        assert_eq!(squares[6][7], king(false));
+        assert_eq!(squares[5][7], rook(false));
+        assert_eq!(squares[1][7], rook(false));
+        assert_eq!(board.get_castle_rooks(false), [None; 2]);
+    }
+
+    #[test]
+    fn odds_take_away_the_givers_piece() {
+        let mut board = ChessBoard::new();
+        board.set_odds(Odds::Queen, false);
+        board.new_game();
+        assert_eq!(board.get_board()[3][7], None);
+        assert_eq!(board.get_board()[3][0], Some(Piece::new(PieceType::Queen, true)));
+        board.set_odds(Odds::Rook, true);
+        board.new_game();
+        assert_eq!(board.get_board()[0][0], None);
+        assert_eq!(board.get_castle_rooks(true), [None, Some(7)]);
+        // pawn and move takes the f pawn and lets black go first
+        board.set_odds(Odds::PawnAndMove, true);
+        board.new_game();
+        assert_eq!(board.get_board()[5][1], None);
+        assert!(!board.get_player());
+    }
+
+    #[test]
+    fn clocks_run_for_the_player_to_move() {
+        let mut board = ChessBoard::new();
+        board.set_time_control(Some([1_000, 5_000]));
+        board.new_game();
+        board.tick(400);
+        assert_eq!(board.get_clock(true), Some(600));
+        assert_eq!(board.get_clock(false), Some(5_000));
+        board.next_turn([4, 1], [4, 3]).unwrap();
+        board.tick(4_000);
+        assert_eq!(board.get_clock(false), Some(1_000));
+        board.next_turn([4, 6], [4, 4]).unwrap();
+        let events = board.tick(600);
+        assert!(events.contains(&GameEvent::GameOver { winner: -1, reason: GameOverReason::OutOfTime }));
+        assert!(board.tick(600).is_empty());
+    }
+
+    #[test]
+    fn new_rules_wait_for_the_next_game() {
+        let mut board = ChessBoard::new();
+        board.next_turn([4, 1], [4, 3]).unwrap();
+        board.set_variant(Variant::Capablanca);
+        assert_eq!(board.get_variant(), Variant::Standard);
+        assert_eq!(board.get_width(), 8);
+        assert!(!board.can_promote_to(PieceType::Archbishop));
+        board.new_game();
+        assert_eq!(board.get_variant(), Variant::Capablanca);
+        assert_eq!(board.get_width(), 10);
+        assert!(board.can_promote_to(PieceType::Archbishop));
+    }
+}
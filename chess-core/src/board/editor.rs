@@ -0,0 +1,86 @@
+// setting up a position by hand. Edits change the board straight away and
+// nothing is checked until start_from_position, since a position is often
+// wrong halfway through setting it up
+use super::{ChessBoard, MAX_SIZE};
+use crate::{chesspiece::*, event::square_name};
+
+impl ChessBoard {
+    // put a piece on a square, or empty it with None
+    pub fn place_piece(&mut self, square: [usize; 2], piece: Option<Piece>) -> Result<(), String> {
+        if !self.on_board(square) {
+            return Err(format!("{} isn't on the board", square_name(square)));
+        }
+        self.board[square[0]][square[1]] = piece;
+        self.promoted[square[0]][square[1]] = false;
+        // a king might have been put down or taken away
+        self.white_king_pos = self.find_king(true);
+        self.black_king_pos = self.find_king(false);
+        Ok(())
+    }
+    // take every piece off the board, along with anything that depends on them
+    pub fn clear_board(&mut self) {
+        self.board = [[None; MAX_SIZE]; MAX_SIZE];
+        self.promoted = [[false; MAX_SIZE]; MAX_SIZE];
+        self.white_castle_rooks = [None; 2];
+        self.black_castle_rooks = [None; 2];
+        self.white_en_passant = None;
+        self.black_en_passant = None;
+        self.white_king_pos = None;
+        self.black_king_pos = None;
+    }
+    // the square behind a pawn that just moved two squares, or None. The
+    // player to move when the game starts is the one who can take it
+    pub fn set_en_passant(&mut self, square: Option<[usize; 2]>) -> Result<(), String> {
+        if let Some(square) = square {
+            if !self.on_board(square) {
+                return Err(format!("{} isn't on the board", square_name(square)));
+            }
+        }
+        self.white_en_passant = square;
+        self.black_en_passant = square;
+        Ok(())
+    }
+    // start a new game from the position that was set up. Nothing changes
+    // if the position can't be played from
+    pub fn start_from_position(&mut self) -> Result<(), String> {
+        // shredder FEN keeps the castling rooks exactly as they were set
+        let fen = self.to_fen(true);
+        self.load_fen(&fen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::PositionProblem;
+
+    fn piece(kind: PieceType, is_white: bool) -> Option<Piece> {
+        Some(Piece::new(kind, is_white))
+    }
+
+    #[test]
+    fn edited_positions_are_checked() {
+        let mut board = ChessBoard::new();
+        board.clear_board();
+        board.place_piece([4, 0], piece(PieceType::King, true)).unwrap();
+        board.place_piece([4, 7], piece(PieceType::King, false)).unwrap();
+        assert_eq!(board.get_white_king_pos(), Some([4, 0]));
+        board.place_piece([4, 7], None).unwrap();
+        assert_eq!(board.get_black_king_pos(), None);
+        assert_eq!(board.validate_position(), vec![PositionProblem::NoKing { is_white: false }]);
+        assert!(board.start_from_position().is_err());
+        board.place_piece([0, 7], piece(PieceType::King, false)).unwrap();
+        board.place_piece([3, 7], piece(PieceType::Pawn, true)).unwrap();
+        assert_eq!(board.validate_position(), vec![PositionProblem::PawnOnBackRank { square: [3, 7] }]);
+        board.place_piece([3, 7], None).unwrap();
+        // files off the side of the board are turned down straight away,
+        // and ones without a rook when the game starts
+        assert!(board.set_castle_rooks(true, [None, Some(8)]).is_err());
+        assert!(board.place_piece([8, 0], piece(PieceType::Rook, true)).is_err());
+        board.set_castle_rooks(true, [None, Some(7)]).unwrap();
+        assert_eq!(board.validate_position(), vec![PositionProblem::BadCastlingRights { is_white: true, file: 7 }]);
+        board.place_piece([7, 0], piece(PieceType::Rook, true)).unwrap();
+        board.start_from_position().unwrap();
+        assert_eq!(board.to_fen(false), "k7/8/8/8/8/8/8/4K2R w K - 0 1");
+    }
+}
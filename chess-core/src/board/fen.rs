@@ -0,0 +1,369 @@
+// reading and writing positions as FEN. Castling is written as X-FEN, which
+// is normal FEN unless a chess960 game has a rook that isn't the outermost
+// one, or as Shredder-FEN, which always uses the files of the rooks
+use super::{validate::problems_message, ChessBoard, Squares, MAX_SIZE};
+use crate::chesspiece::*;
+
+impl ChessBoard {
+    // the current position as a FEN string
+    pub fn to_fen(&self, shredder: bool) -> String {
+        let mut fen = placement(&self.board, [self.width, self.height]);
+        fen.push_str(if self.player {
+            " w "
+        } else {
+            " b "
+        });
+        // castling
+        let mut castling = String::new();
+        for is_white in [true, false].iter() {
+            let rooks = self.get_castle_rooks(*is_white);
+            // kingside comes first
+            for side in (0..2).rev() {
+                if let Some(col) = rooks[side].filter(|col| *col < self.width) {
+                    let letter = if !shredder && self.is_outer_rook(col, side, *is_white) {
+                        ['Q', 'K'][side]
+                    } else {
+                        (b'A' + col as u8) as char
+                    };
+                    if *is_white {
+                        castling.push(letter);
+                    } else {
+                        castling.push(letter.to_ascii_lowercase());
+                    }
+                }
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        fen.push_str(&castling);
+        // en passant, which is the square behind the pawn that just moved
+        let en_passant = if self.player {
+            self.black_en_passant
+        } else {
+            self.white_en_passant
+        };
+        match en_passant {
+            Some(square) => fen.push_str(&format!(" {}", crate::event::square_name(square))),
+            None => fen.push_str(" -"),
+        }
+        let full_moves = (self.start_ply + self.turn_num)/2 + 1;
+        fen.push_str(&format!(" {} {}", self.halfmove_clock, full_moves));
+        fen
+    }
+    // set up the position from a FEN string as the start of a new game.
+    // Nothing changes if the string can't be read
+    pub fn load_fen(&mut self, fen: &str) -> Result<(), String> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(format!("a FEN needs 4 to 6 fields, this one has {}", fields.len()));
+        }
+        // the pieces
+        // the variant decides how big the board is
+        let [width, height] = self.variant.size();
+        let board = parse_placement(fields[0], [width, height])?;
+        let mut king_pos = [None; 2];
+        for (col, column) in board.iter().enumerate().take(width) {
+            for (row, piece) in column.iter().enumerate().take(height) {
+                if let Some(piece) = piece.filter(|piece| piece.get_piece_type() == PieceType::King) {
+                    let index = if piece.is_white() {
+                        0
+                    } else {
+                        1
+                    };
+                    king_pos[index] = Some([col, row]);
+                }
+            }
+        }
+        let [white_king_pos, black_king_pos] = king_pos;
+        // the player
+        let player = match fields[1] {
+            "w" => true,
+            "b" => false,
+            other => return Err(format!("{} isn't a player, it should be w or b", other)),
+        };
+        // castling
+        let mut white_castle_rooks = [None; 2];
+        let mut black_castle_rooks = [None; 2];
+        if fields[2] != "-" {
+            for letter in fields[2].chars() {
+                let is_white = letter.is_ascii_uppercase();
+                let (rooks, king_pos) = if is_white {
+                    (&mut white_castle_rooks, white_king_pos)
+                } else {
+                    (&mut black_castle_rooks, black_king_pos)
+                };
+                let row = if is_white {
+                    0
+                } else {
+                    height - 1
+                };
+                let king_pos = king_pos.ok_or_else(|| format!("there's no king to castle with for {}", letter))?;
+                if king_pos[1] != row {
+                    return Err(format!("the king has to be on its first rank to castle for {}", letter));
+                }
+                let is_rook = |col: usize| board[col][row] == Some(Piece::new(PieceType::Rook, is_white));
+                let col = match letter.to_ascii_uppercase() {
+                    // the outermost rook on that side of the king
+                    'K' => (king_pos[0]..width).rev().find(|col| is_rook(*col)),
+                    'Q' => (0..king_pos[0]).find(|col| is_rook(*col)),
+                    file @ 'A'..='Z' if (file as usize - 'A' as usize) < width => {
+                        Some(file as usize - 'A' as usize).filter(|col| is_rook(*col))
+                    },
+                    _ => return Err(format!("{} isn't a castling right", letter)),
+                };
+                match col {
+                    Some(col) => {
+                        let side = if col < king_pos[0] {
+                            0
+                        } else {
+                            1
+                        };
+                        rooks[side] = Some(col);
+                    },
+                    _ => return Err(format!("there's no rook to castle with for {}", letter)),
+                }
+            }
+        }
+        // en passant
+        let en_passant = if fields[3] == "-" {
+            None
+        } else {
+            let square = parse_square(fields[3])
+                .filter(|square| square[0] < width && square[1] < height)
+                .ok_or_else(|| format!("{} isn't a square", fields[3]))?;
+            Some(square)
+        };
+        let halfmove_clock = match fields.get(4) {
+            Some(field) => field.parse().map_err(|_| format!("{} isn't a number", field))?,
+            None => 0,
+        };
+        let full_moves: usize = match fields.get(5) {
+            Some(field) => field.parse().map_err(|_| format!("{} isn't a number", field))?,
+            None => 1,
+        };
+        // everything was read, but the position still has to be checked
+        // before the game is really changed
+        let previous = self.clone();
+        self.board = board;
+        self.width = width;
+        self.height = height;
+        self.player = player;
+        self.score = [0; 2];
+        self.winner = None;
+        self.termination = None;
+        self.date = String::new();
+        self.white_captured = String::new();
+        self.black_captured = String::new();
+        self.white_en_passant = en_passant.filter(|_| !player);
+        self.black_en_passant = en_passant.filter(|_| player);
+        self.white_castle_rooks = white_castle_rooks;
+        self.black_castle_rooks = black_castle_rooks;
+        self.white_king_pos = white_king_pos;
+        self.black_king_pos = black_king_pos;
+        self.turn_num = 0;
+        self.halfmove_clock = halfmove_clock;
+        self.checks = [0; 2];
+        self.white_reserve = [0; 5];
+        self.black_reserve = [0; 5];
+        self.promoted = [[false; MAX_SIZE]; MAX_SIZE];
+        self.pending_promotion = None;
+        self.start_ply = ply(full_moves, player);
+        let problems = self.validate_position();
+        if !problems.is_empty() {
+            *self = previous;
+            return Err(problems_message(&problems));
+        }
+        self.start_fen = self.to_fen(false);
+        Ok(())
+    }
+    // a rook is the outer one if there isn't another rook of the same colour
+    // between it and the edge of the board, so K or Q can stand for it
+    fn is_outer_rook(&self, col: usize, side: usize, is_white: bool) -> bool {
+        if col >= self.width {
+            return false;
+        }
+        let row = if is_white {
+            0
+        } else {
+            self.height - 1
+        };
+        let rook = Some(Piece::new(PieceType::Rook, is_white));
+        let mut outside = if side == 0 {
+            0..col
+        } else {
+            col+1..self.width
+        };
+        !outside.any(|col| self.board[col][row] == rook)
+    }
+    // the FEN the current game started from
+    pub fn get_start_fen(&self) -> &str {
+        &self.start_fen
+    }
+}
+
+// the pieces as the first field of a FEN, from black's back rank down
+pub(super) fn placement(board: &Squares, size: [usize; 2]) -> String {
+    let [width, height] = size;
+    let mut fen = String::new();
+    for row in (0..height).rev() {
+        let mut empty = 0;
+        for column in board.iter().take(width) {
+            match column[row] {
+                Some(piece) => {
+                    if empty > 0 {
+                        fen.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    fen.push(piece_letter(piece));
+                },
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            fen.push_str(&empty.to_string());
+        }
+        if row > 0 {
+            fen.push('/');
+        }
+    }
+    fen
+}
+// read the first field of a FEN onto a board of the given size
+pub(super) fn parse_placement(field: &str, size: [usize; 2]) -> Result<Squares, String> {
+    let [width, height] = size;
+    let rows: Vec<&str> = field.split('/').collect();
+    if rows.len() != height {
+        return Err(format!("a FEN needs {} ranks, this one has {}", height, rows.len()));
+    }
+    let mut board = [[None; MAX_SIZE]; MAX_SIZE];
+    for (i, rank) in rows.iter().enumerate() {
+        let row = height - 1 - i;
+        let mut col = 0;
+        // wide boards can have ten or more empty squares in a row
+        let mut empty: usize = 0;
+        let too_long = || format!("rank {} has more than {} squares", row + 1, width);
+        for letter in rank.chars() {
+            if let Some(digit) = letter.to_digit(10) {
+                // long runs of digits can't be allowed to overflow
+                empty = empty.checked_mul(10)
+                    .and_then(|empty| empty.checked_add(digit as usize))
+                    .filter(|empty| col + empty <= width)
+                    .ok_or_else(too_long)?;
+                continue;
+            }
+            col += empty;
+            empty = 0;
+            let piece = piece_from_letter(letter)
+                .ok_or_else(|| format!("{} isn't a piece", letter))?;
+            if col >= width {
+                return Err(too_long());
+            }
+            board[col][row] = Some(piece);
+            col += 1;
+        }
+        col += empty;
+        if col != width {
+            return Err(format!("rank {} doesn't have {} squares", row + 1, width));
+        }
+    }
+    Ok(board)
+}
+// the first letter in the pieces field of a FEN that isn't a piece
+pub(super) fn unknown_letter(field: &str) -> Option<char> {
+    field.chars().find(|letter| {
+        !letter.is_ascii_digit() && *letter != '/' && piece_from_letter(*letter).is_none()
+    })
+}
+// how many moves were played before a FEN's position, from its player and
+// full move number
+pub(super) fn start_ply(fen: &str) -> Option<usize> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    let player = match fields.get(1) {
+        Some(&"w") => true,
+        Some(&"b") => false,
+        _ => return None,
+    };
+    let full_moves = fields.get(5).map_or(Some(1), |field| field.parse().ok())?;
+    Some(ply(full_moves, player))
+}
+fn ply(full_moves: usize, player: bool) -> usize {
+    full_moves.saturating_sub(1)*2 + if player {
+        0
+    } else {
+        1
+    }
+}
+// the letter FEN uses for a piece, upper case for white
+fn piece_letter(piece: Piece) -> char {
+    let letter = piece.get_piece_type().letter();
+    if piece.is_white() {
+        letter
+    } else {
+        letter.to_ascii_lowercase()
+    }
+}
+fn piece_from_letter(letter: char) -> Option<Piece> {
+    let kind = PieceType::from_letter(letter.to_ascii_uppercase())?;
+    Some(Piece::new(kind, letter.is_ascii_uppercase()))
+}
+// read a square like e3 or j10. The caller checks it fits on the board
+pub(super) fn parse_square(square: &str) -> Option<[usize; 2]> {
+    let mut chars = square.chars();
+    let file = chars.next().filter(char::is_ascii_lowercase)?;
+    let rank = chars.as_str();
+    if rank.is_empty() || !rank.chars().all(|digit| digit.is_ascii_digit()) {
+        return None;
+    }
+    let square = [file as usize - 'a' as usize, rank.parse::<usize>().ok()?.checked_sub(1)?];
+    if square[0] < MAX_SIZE && square[1] < MAX_SIZE {
+        return Some(square);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::Game, variant::Variant};
+
+    #[test]
+    fn fens_round_trip() {
+        let fens = [
+            (Variant::Standard, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            (Variant::Standard, "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3"),
+            (Variant::Standard, "4k3/8/8/8/8/8/8/R3K3 w Q - 12 40"),
+            // a chess960 rook that isn't the outer one is written by its file
+            (Variant::Standard, "1r2k1r1/8/8/8/8/8/8/RR2K3 w Bk - 0 1"),
+            (Variant::Capablanca, "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1"),
+        ];
+        for (variant, fen) in fens.iter() {
+            let mut board = ChessBoard::new();
+            board.set_variant(*variant);
+            board.new_game();
+            board.load_fen(fen).unwrap();
+            assert_eq!(board.to_fen(false), *fen);
+            // and the same position comes back from the shredder version
+            let shredder = board.to_fen(true);
+            board.load_fen(&shredder).unwrap();
+            assert_eq!(board.to_fen(false), *fen);
+        }
+    }
+
+    #[test]
+    fn long_runs_of_empty_squares() {
+        let mut board = ChessBoard::new();
+        board.new_game();
+        let fen = board.to_fen(false);
+        for placement in ["99999999999999999999999/8/8/8/8/8/8/8", "9/8/8/8/8/8/8/8", "44k/8/8/8/8/8/8/K7"].iter() {
+            let error = board.load_fen(&format!("{} w - - 0 1", placement)).unwrap_err();
+            assert!(error.contains("more than 8 squares"), "{}", error);
+        }
+        // the board doesn't change when the FEN is bad
+        assert_eq!(board.to_fen(false), fen);
+        // and ten is fine on a board that's ten wide
+        board.set_variant(Variant::Capablanca);
+        board.new_game();
+        board.load_fen("4k5/10/10/10/10/10/10/4K5 w - - 0 1").unwrap();
+    }
+}
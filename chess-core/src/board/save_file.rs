@@ -0,0 +1,496 @@
+// turning the game's history into a save file and back. While a game is
+// being played its history is kept as text, with a block of lines for
+// every turn, which is also what saves were before they had a version.
+// Save files only keep the moves, and the game is played back from them
+use super::{fen, parse_castle_save, ChessBoard, SAVE_HEADER, TURN_INFO_LINES, MAX_SIZE};
+use crate::{
+    chesspiece::*,
+    event::{square_name, GameEvent, GameOverReason},
+    game::Game,
+    odds::Odds,
+    rules::{self, RuleSet},
+    save::{self, LoadError, SaveFile, SaveHeader, SaveMetadata, SavedMove, SavedOdds, SavedTurn},
+    variant::Variant,
+};
+
+impl ChessBoard {
+    // what the game being played is played with
+    pub(super) fn metadata(&self) -> SaveMetadata {
+        let odds = match self.odds {
+            Odds::None => None,
+            odds => Some(SavedOdds {
+                odds: String::from(odds.as_str()),
+                giver_is_white: self.odds_giver,
+            }),
+        };
+        let [white, black] = self.players.clone();
+        SaveMetadata {
+            white,
+            black,
+            event: self.event.clone(),
+            date: self.date.clone(),
+            result: String::from(save::result_string(self.winner)),
+            termination: self.termination.map(|reason| String::from(reason.as_str())),
+            move_count: self.get_num_turns(),
+            variant: String::from(self.variant.as_str()),
+            rules: String::from(self.rules.name()),
+            odds,
+            time_control: self.time_control,
+            start_fen: Some(self.start_fen.clone()).filter(|fen| !fen.is_empty()),
+        }
+    }
+    // what the game in the history was played with, which comes from its
+    // header since the board might have been set up for the next game already
+    fn save_metadata(&self) -> SaveMetadata {
+        let header = |key: &str| String::from(self.header_value(key).unwrap_or_default());
+        let (odds, giver_is_white) = self.save_odds();
+        let odds = Some(SavedOdds {
+            odds: String::from(odds.as_str()),
+            giver_is_white,
+        }).filter(|_| odds != Odds::None);
+        let result = self.save_result();
+        SaveMetadata {
+            white: header("white"),
+            black: header("black"),
+            event: header("event"),
+            date: header("date"),
+            result: String::from(save::result_string(result.map(|(winner, _)| winner))),
+            termination: result.map(|(_, reason)| String::from(reason.as_str())),
+            move_count: self.get_num_turns(),
+            variant: String::from(self.save_variant().as_str()),
+            rules: String::from(self.save_rules().name()),
+            odds,
+            time_control: self.save_time_control(),
+            start_fen: self.header_value("start_fen").map(String::from),
+        }
+    }
+    // the history as a save file
+    pub fn to_save_file(&self) -> Result<SaveFile, LoadError> {
+        let metadata = self.save_metadata();
+        if self.moves.len() != self.get_num_turns() {
+            return Err(LoadError::InconsistentState(format!(
+                "there are {} moves for {} turns", self.moves.len(), self.get_num_turns())));
+        }
+        Ok(SaveFile {
+            header: SaveHeader::default(),
+            metadata,
+            moves: self.moves.clone(),
+        })
+    }
+    // a history that stops partway through a turn was cut off
+    pub(super) fn check_complete(&self) -> Result<(), LoadError> {
+        let lines = self.save_turns().count();
+        if lines % self.turn_lines() != 0 {
+            return Err(LoadError::TruncatedTurn { turn: lines/self.turn_lines() });
+        }
+        Ok(())
+    }
+    // play the game in a save file back from the start, which gives the
+    // history for every turn
+    pub(super) fn read_save_file(&mut self, file: &SaveFile) -> Result<(), LoadError> {
+        let metadata = &file.metadata;
+        let variant = Variant::from_name(&metadata.variant).ok_or_else(|| {
+            LoadError::InconsistentState(format!("{} isn't a variant", metadata.variant))
+        })?;
+        let rules = rules::rule_set(&metadata.rules)
+            .filter(|rules| rules.variant() == variant)
+            .unwrap_or_else(|| rules::variant_rules(variant));
+        let odds = match &metadata.odds {
+            Some(odds) => (Odds::from_name(&odds.odds).unwrap_or_default(), odds.giver_is_white),
+            None => (Odds::None, true),
+        };
+        self.set_time_control(metadata.time_control);
+        self.set_up_saved_start(rules, odds, metadata.start_fen.as_deref().unwrap_or_default())?;
+        for (number, saved_move) in file.moves.iter().enumerate() {
+            self.play_saved_move(saved_move)
+                .map_err(|e| LoadError::InconsistentState(format!("move {}: {}", number + 1, e)))?;
+        }
+        // endings that aren't a move, like resigning, are only in the metadata
+        if self.winner.is_none() {
+            let reason = metadata.termination.as_deref().and_then(|termination| {
+                GameOverReason::ALL.iter().copied().find(|reason| reason.as_str() == termination)
+            });
+            if let (Some(winner), Some(reason)) = (save::parse_result(&metadata.result), reason) {
+                self.winner = Some(winner);
+                self.termination = Some(reason);
+            }
+        }
+        self.players = [metadata.white.clone(), metadata.black.clone()];
+        self.event = metadata.event.clone();
+        self.date = metadata.date.clone();
+        self.rewrite_header();
+        Ok(())
+    }
+    // set up the position a saved game started from
+    fn set_up_saved_start(&mut self, rules: &'static dyn RuleSet, odds: (Odds, bool), start_fen: &str) -> Result<(), LoadError> {
+        self.set_rules(rules);
+        self.set_odds(odds.0, odds.1);
+        self.new_game();
+        if !start_fen.is_empty() {
+            self.load_fen(start_fen).map_err(LoadError::InconsistentState)?;
+        }
+        Ok(())
+    }
+    // play a saved move and switch players, the same way it was played
+    fn play_saved_move(&mut self, saved_move: &SavedMove) -> Result<(), String> {
+        let square = |name: &str, board: &ChessBoard| {
+            fen::parse_square(name)
+                .filter(|square| board.on_board(*square))
+                .ok_or_else(|| format!("{} isn't a square on the board", name))
+        };
+        let kind = |name: &str| PieceType::from_name(name).ok_or_else(|| format!("{} isn't a piece", name));
+        let to = square(&saved_move.to, self)?;
+        let events = match (&saved_move.from, &saved_move.drop) {
+            (Some(from), None) => {
+                let from = square(from, self)?;
+                self.next_turn(from, to)
+            },
+            (None, Some(piece)) => {
+                let kind = kind(piece)?;
+                // in bughouse the piece came from the other board, which isn't in the save
+                if self.rules.passes_captures() && self.get_reserve(self.player).get(kind.index()) == Some(&0) {
+                    self.add_to_reserve(self.player, kind);
+                }
+                self.next_drop(kind, to)
+            },
+            _ => return Err(String::from("a move needs a square it's from or a piece to drop")),
+        };
+        let events = events.ok_or_else(|| String::from("it isn't legal"))?;
+        let pending = events.iter().find_map(|event| match event {
+            GameEvent::PromotionPending { square } => Some(*square),
+            _ => None,
+        });
+        match (pending, &saved_move.promotion) {
+            (Some(square), Some(piece)) => {
+                self.upgrade_pawn(square, kind(piece)?)?;
+            },
+            (None, None) => {},
+            (Some(_), None) => return Err(String::from("it doesn't say what the pawn was promoted to")),
+            (None, Some(_)) => return Err(String::from("there's no pawn to promote")),
+        }
+        Ok(())
+    }
+    // work out the moves of a history that only has them in the log, by
+    // finding the move that gets from each position to the next
+    pub(super) fn moves_from_history(&self) -> Result<Vec<SavedMove>, LoadError> {
+        let mut board = self.clone();
+        let mut moves = Vec::new();
+        for turn in 0..self.get_num_turns() {
+            if turn == 0 {
+                board.set_up_saved_start(self.save_rules(), self.save_odds(), &self.start_fen)?;
+            } else {
+                board.load_turn(turn - 1)?;
+            }
+            let after = self.saved_turn(turn)?.board;
+            let found = board.candidate_moves().into_iter().find(|saved_move| {
+                let mut trial = board.without_history();
+                trial.play_saved_move(saved_move).is_ok() && trial.placement() == after
+            });
+            let found = found.ok_or_else(|| {
+                LoadError::InconsistentState(format!("the move in turn {} can't be worked out", turn + 1))
+            })?;
+            moves.push(found);
+        }
+        Ok(moves)
+    }
+    // every move the player to move could make, the way it would be saved
+    fn candidate_moves(&mut self) -> Vec<SavedMove> {
+        let promotions = self.get_promotion_options();
+        let mut moves = Vec::new();
+        for (start, dest) in self.legal_moves() {
+            let saved_move = SavedMove {
+                from: Some(square_name(start)),
+                to: square_name(dest),
+                ..SavedMove::default()
+            };
+            // a pawn capturing onto the last rank in atomic chess blows up
+            // instead of being promoted, so the move is tried both ways
+            let is_pawn = self.board[start[0]][start[1]].is_some_and(|piece| piece.get_piece_type() == PieceType::Pawn);
+            if is_pawn && (dest[1] == 0 || dest[1] == self.height - 1) {
+                for kind in promotions.iter() {
+                    moves.push(SavedMove {
+                        promotion: Some(String::from(kind.as_str())),
+                        ..saved_move.clone()
+                    });
+                }
+            }
+            moves.push(saved_move);
+        }
+        for (kind, square) in self.legal_drops(self.player) {
+            moves.push(SavedMove {
+                to: square_name(square),
+                drop: Some(String::from(kind.as_str())),
+                ..SavedMove::default()
+            });
+        }
+        moves
+    }
+    // the pieces on the board as the first field of a FEN
+    pub(crate) fn placement(&self) -> String {
+        fen::placement(&self.board, [self.width, self.height])
+    }
+    // write the header of the history again, for when what it says about
+    // the game has changed. Before the first move of a new game the history
+    // is still the last game's, which keeps its own header
+    pub(super) fn rewrite_header(&mut self) {
+        if self.turn_num == 0 && self.get_num_turns() > 0 {
+            return;
+        }
+        // the turns are kept as they are, since the last one might only be
+        // part written
+        let header_len: usize = self.game_save.split_inclusive('\n')
+            .take_while(|line| line.starts_with(SAVE_HEADER))
+            .map(str::len)
+            .sum();
+        let mut metadata = self.metadata();
+        // looking back at an earlier turn doesn't change how the game ended
+        if self.turn_num < self.get_num_turns() {
+            let result = self.save_result();
+            metadata.result = String::from(save::result_string(result.map(|(winner, _)| winner)));
+            metadata.termination = result.map(|(_, reason)| String::from(reason.as_str()));
+        }
+        self.game_save = header_text(&metadata) + &self.game_save[header_len..];
+    }
+    // read a turn out of the history
+    pub(super) fn saved_turn(&self, turn: usize) -> Result<SavedTurn, LoadError> {
+        let variant = self.save_variant();
+        let [width, height] = variant.size();
+        let lines: Vec<&str> = self.save_turns()
+            .skip(self.turn_lines()*turn)
+            .take(self.turn_lines())
+            .collect();
+        if lines.is_empty() {
+            return Err(LoadError::InconsistentState(format!("there's no turn {}", turn + 1)));
+        }
+        if lines.len() < self.turn_lines() {
+            return Err(LoadError::TruncatedTurn { turn });
+        }
+        // score lines look like "White Score: 3 "
+        let score = |line: &str| {
+            line.split_whitespace()
+                .last()
+                .and_then(|score| score.parse().ok())
+                .ok_or_else(|| LoadError::BadScoreLine { turn, line: String::from(line) })
+        };
+        let mut board = [[None; MAX_SIZE]; MAX_SIZE];
+        for row in 0..height {
+            for (col, symbol) in lines[TURN_INFO_LINES + row].chars().take(width).enumerate() {
+                if symbol != ' ' {
+                    let piece = Piece::from_symbol(symbol)
+                        .ok_or(LoadError::UnknownPiece { turn, glyph: symbol })?;
+                    board[col][row] = Some(piece);
+                }
+            }
+        }
+        // the lines the rules add come after the board, and are a name
+        // followed by numbers or squares
+        let extra_lines = &lines[TURN_INFO_LINES + height..];
+        let counts = |line: &str| -> Vec<u8> {
+            line.split(' ').skip(1).filter_map(|count| count.parse().ok()).collect()
+        };
+        let bad_line = |line: &str, what: &str| {
+            LoadError::InconsistentState(format!("\"{}\" in turn {} isn't {}", line, turn + 1, what))
+        };
+        let mut checks = None;
+        let mut reserves = None;
+        let mut promoted = Vec::new();
+        for line in extra_lines.iter() {
+            match line.split(' ').next() {
+                Some("checks") => match counts(line)[..] {
+                    [white, black] => checks = Some([white, black]),
+                    _ => return Err(bad_line(line, "a count of checks")),
+                },
+                Some("reserve") => {
+                    let counts = counts(line);
+                    if counts.len() != 10 {
+                        return Err(bad_line(line, "a reserve"));
+                    }
+                    let mut both = [[0; 5]; 2];
+                    both[0].copy_from_slice(&counts[..5]);
+                    both[1].copy_from_slice(&counts[5..]);
+                    reserves = Some(both);
+                },
+                Some("promoted") => promoted = line.split(' ').skip(1).map(String::from).collect(),
+                // lines for rules outside the variants
+                _ => {},
+            }
+        }
+        Ok(SavedTurn {
+            log: String::from(lines[8]),
+            score: [score(lines[0])?, score(lines[2])?],
+            captured: [String::from(lines[1]), String::from(lines[3])],
+            castle_rooks: [parse_castle_save(lines[4]), parse_castle_save(lines[5])],
+            en_passant: [lines[6].parse().ok(), lines[7].parse().ok()],
+            board: fen::placement(&board, [width, height]),
+            checks,
+            reserves,
+            promoted,
+        })
+    }
+}
+
+// the header lines at the top of the history
+pub(super) fn header_text(metadata: &SaveMetadata) -> String {
+    let mut header = String::new();
+    // names go on one line each, and empty ones are left out
+    let mut text_line = |key: &str, value: &str| {
+        if !value.is_empty() {
+            header.push_str(&format!("{}{} {}\n", SAVE_HEADER, key, value.replace('\n', " ")));
+        }
+    };
+    text_line("white", &metadata.white);
+    text_line("black", &metadata.black);
+    text_line("event", &metadata.event);
+    text_line("date", &metadata.date);
+    if let Some(start_fen) = &metadata.start_fen {
+        text_line("start_fen", start_fen);
+    }
+    if metadata.variant != Variant::Standard.as_str() {
+        header.push_str(&format!("{}variant {}\n", SAVE_HEADER, metadata.variant));
+    }
+    // rule sets that aren't just a variant are saved by name too
+    if metadata.rules != metadata.variant {
+        header.push_str(&format!("{}rules {}\n", SAVE_HEADER, metadata.rules));
+    }
+    if let Some(odds) = &metadata.odds {
+        let giver = if odds.giver_is_white {
+            "white"
+        } else {
+            "black"
+        };
+        header.push_str(&format!("{}odds {} {}\n", SAVE_HEADER, odds.odds, giver));
+    }
+    if let Some([white, black]) = metadata.time_control {
+        header.push_str(&format!("{}time_control {} {}\n", SAVE_HEADER, white, black));
+    }
+    // how the game ended, for endings that aren't written after the last move
+    if let Some(termination) = &metadata.termination {
+        header.push_str(&format!("{}result {} {}\n", SAVE_HEADER, metadata.result, termination));
+    }
+    header
+}
+// what's wrong with the board of a saved turn that couldn't be read
+pub(super) fn placement_error(turn: usize, placement: &str, message: String) -> LoadError {
+    match fen::unknown_letter(placement) {
+        Some(glyph) => LoadError::UnknownPiece { turn, glyph },
+        None => LoadError::InconsistentState(format!("turn {}: {}", turn + 1, message)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // play moves written like e2e4 or e7e8q
+    fn play(board: &mut ChessBoard, moves: &[&str]) {
+        for text in moves {
+            let from = fen::parse_square(&text[..2]).unwrap();
+            let to = fen::parse_square(&text[2..4]).unwrap();
+            let events = board.next_turn(from, to).unwrap();
+            if let Some(letter) = text.chars().nth(4) {
+                assert!(events.iter().any(|event| matches!(event, GameEvent::PromotionPending { .. })));
+                let kind = PieceType::from_letter(letter.to_ascii_uppercase()).unwrap();
+                board.upgrade_pawn(to, kind).unwrap();
+            }
+        }
+    }
+    // the position after every turn of a game
+    fn positions(board: &ChessBoard) -> Vec<String> {
+        (0..board.get_num_turns()).map(|turn| board.saved_turn(turn).unwrap().board).collect()
+    }
+    // a game read back from a save has to be the same as the one saved
+    fn same_game(loaded: &ChessBoard, board: &ChessBoard, file: &SaveFile) {
+        assert_eq!(positions(loaded), positions(board));
+        assert_eq!(loaded.moves, board.moves);
+        assert_eq!(loaded.winner, board.winner);
+        assert_eq!(loaded.termination, board.termination);
+        assert_eq!(loaded.to_save_file().unwrap(), *file);
+    }
+    // the game has to come back the same from a save
+    fn round_trips(board: &ChessBoard) {
+        let file = board.to_save_file().unwrap();
+        same_game(&ChessBoard::from_save(file.to_json()).unwrap(), board, &file);
+    }
+    // saves from before the format had a version are only the log, so they
+    // always start from the usual position and don't know where it was
+    fn legacy_round_trips(board: &ChessBoard) {
+        let mut file = board.to_save_file().unwrap();
+        file.metadata.start_fen = None;
+        same_game(&ChessBoard::from_save(board.game_save.clone()).unwrap(), board, &file);
+    }
+
+    #[test]
+    fn castling_round_trips() {
+        let mut board = ChessBoard::new();
+        play(&mut board, &["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "f8c5", "e1g1"]);
+        round_trips(&board);
+        legacy_round_trips(&board);
+        // resigning isn't a move, so it comes back from the metadata, which
+        // the oldest saves didn't have
+        board.resign(false);
+        round_trips(&board);
+    }
+
+    #[test]
+    fn promotions_round_trip() {
+        let mut board = ChessBoard::new();
+        board.load_fen("8/P7/8/8/8/8/8/k6K w - - 0 1").unwrap();
+        play(&mut board, &["a7a8n", "a1b2", "a8c7"]);
+        round_trips(&board);
+    }
+
+    #[test]
+    fn crazyhouse_round_trips() {
+        let mut board = ChessBoard::new();
+        board.set_variant(Variant::Crazyhouse);
+        board.new_game();
+        play(&mut board, &["e2e4", "d7d5", "e4d5", "d8d5"]);
+        board.next_drop(PieceType::Pawn, [4, 3]).unwrap();
+        round_trips(&board);
+        legacy_round_trips(&board);
+    }
+
+    #[test]
+    fn three_check_round_trips() {
+        let mut board = ChessBoard::new();
+        board.set_variant(Variant::ThreeCheck);
+        board.new_game();
+        play(&mut board, &["e2e4", "e7e5", "f1c4", "b8c6", "c4f7", "e8f7", "d1h5", "f7e7", "h5e5"]);
+        assert_eq!(board.termination, Some(GameOverReason::ThreeCheck));
+        round_trips(&board);
+        legacy_round_trips(&board);
+    }
+
+    #[test]
+    fn saves_are_of_the_game_in_the_history() {
+        let mut board = ChessBoard::new();
+        board.set_players("Anna", "Ben");
+        play(&mut board, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+        // names given during the game count for it
+        board.set_event("Club night");
+        // a new game that hasn't had a move yet doesn't change the last one
+        board.new_game();
+        board.set_players("Cleo", "Dan");
+        board.set_event("");
+        let metadata = board.to_save_file().unwrap().metadata;
+        assert_eq!([metadata.white.as_str(), metadata.black.as_str()], ["Anna", "Ben"]);
+        assert_eq!(metadata.event, "Club night");
+        assert_eq!(metadata.result, "0-1");
+        assert_eq!(metadata.termination.as_deref(), Some("checkmate"));
+        // until it does
+        play(&mut board, &["e2e4"]);
+        let metadata = board.to_save_file().unwrap().metadata;
+        assert_eq!([metadata.white.as_str(), metadata.black.as_str()], ["Cleo", "Dan"]);
+        assert_eq!(metadata.result, "*");
+        assert_eq!(metadata.move_count, 1);
+    }
+
+    #[test]
+    fn moves_have_to_be_legal() {
+        let mut board = ChessBoard::new();
+        play(&mut board, &["e2e4"]);
+        let mut file = board.to_save_file().unwrap();
+        file.moves[0].to = String::from("e5");
+        assert!(ChessBoard::from_save(file.to_json()).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+// checking a position can be played from. Positions from FENs, the board
+// editor and hand edited saves can be things the rest of the rules code
+// was never meant to see, like a side with no king
+use super::ChessBoard;
+use crate::{chesspiece::*, event::square_name};
+use std::fmt;
+
+// something wrong with a position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionProblem {
+    NoKing { is_white: bool },
+    TooManyKings { is_white: bool },
+    // a pawn on the first or last rank, where it could never have got to
+    PawnOnBackRank { square: [usize; 2] },
+    // the player who just moved left their own king in check
+    OpponentInCheck { is_white: bool },
+    // a castling rook on a file without the player's rook, or with the king
+    // not on its first rank
+    BadCastlingRights { is_white: bool, file: usize },
+    // an en passant square that no pawn could have just moved past
+    BadEnPassant { square: [usize; 2] },
+}
+impl fmt::Display for PositionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            PositionProblem::NoKing { is_white } => write!(f, "there's no {} king", colour(is_white)),
+            PositionProblem::TooManyKings { is_white } => {
+                write!(f, "{} has more than one king", colour(is_white))
+            },
+            PositionProblem::PawnOnBackRank { square } => {
+                write!(f, "there's a pawn on {}, which is a back rank", square_name(square))
+            },
+            PositionProblem::OpponentInCheck { is_white } => {
+                write!(f, "{} is in check but it's {}'s move", colour(is_white), colour(!is_white))
+            },
+            PositionProblem::BadCastlingRights { is_white, file } => {
+                write!(f, "{} can't castle with a rook on the {} file", colour(is_white), (b'a' + file as u8) as char)
+            },
+            PositionProblem::BadEnPassant { square } => {
+                write!(f, "{} can't be an en passant square", square_name(square))
+            },
+        }
+    }
+}
+// all the problems in one message, for places that only report a string
+pub(super) fn problems_message(problems: &[PositionProblem]) -> String {
+    let messages: Vec<String> = problems.iter().map(PositionProblem::to_string).collect();
+    messages.join(", ")
+}
+fn colour(is_white: bool) -> &'static str {
+    if is_white {
+        "white"
+    } else {
+        "black"
+    }
+}
+
+impl ChessBoard {
+    // everything wrong with the current position, which is empty if it can
+    // be played from
+    pub fn validate_position(&mut self) -> Vec<PositionProblem> {
+        let mut problems = Vec::new();
+        // kings aren't special without check, like in antichess, and the
+        // horde doesn't have one
+        let royal = self.rules.has_check();
+        for is_white in [true, false].iter().copied() {
+            let king = Some(Piece::new(PieceType::King, is_white));
+            let kings = self.board.iter().flatten().filter(|piece| **piece == king).count();
+            if kings == 0 && royal && self.rules.has_king(is_white) {
+                problems.push(PositionProblem::NoKing { is_white });
+            } else if kings > 1 && royal {
+                problems.push(PositionProblem::TooManyKings { is_white });
+            }
+        }
+        // the horde starts with pawns on white's first rank
+        for col in 0..self.width {
+            for row in [0, self.height - 1].iter().copied() {
+                if let Some(piece) = self.board[col][row] {
+                    let own_rank = if piece.is_white() {
+                        0
+                    } else {
+                        self.height - 1
+                    };
+                    let first_rank = row == own_rank && self.rules.first_rank_pawns(piece.is_white());
+                    if piece.get_piece_type() == PieceType::Pawn && !first_rank {
+                        problems.push(PositionProblem::PawnOnBackRank { square: [col, row] });
+                    }
+                }
+            }
+        }
+        // the player who isn't moving can't be in check, since that means
+        // they could only have moved into it. The move code only gets asked
+        // about positions that don't already have problems
+        let waiting = !self.player;
+        if self.rules.has_check() && problems.is_empty() {
+            if let Some(king) = self.find_king(waiting) {
+                if self.is_king_attacked(king, waiting) {
+                    problems.push(PositionProblem::OpponentInCheck { is_white: waiting });
+                }
+            }
+        }
+        for is_white in [true, false].iter().copied() {
+            let row = if is_white {
+                0
+            } else {
+                self.height - 1
+            };
+            let king = self.find_king(is_white).filter(|king| king[1] == row);
+            let rooks = self.get_castle_rooks(is_white);
+            for (side, file) in rooks.iter().enumerate() {
+                if let Some(file) = *file {
+                    let rook = file < self.width
+                        && self.board[file][row] == Some(Piece::new(PieceType::Rook, is_white));
+                    // the left rook has to be left of the king
+                    let king_between = king.is_some_and(|king| (file < king[0]) == (side == 0));
+                    if !rook || !king_between {
+                        problems.push(PositionProblem::BadCastlingRights { is_white, file });
+                    }
+                }
+            }
+        }
+        // the square has to be right behind a pawn of the player who just
+        // moved, with the square it came from empty
+        let en_passant = if self.player {
+            self.black_en_passant
+        } else {
+            self.white_en_passant
+        };
+        if let Some(square) = en_passant {
+            let (row, pawn_row, from_row) = if self.player {
+                (self.height - 3, self.height - 4, self.height - 2)
+            } else {
+                (2, 3, 1)
+            };
+            let pawn = Some(Piece::new(PieceType::Pawn, waiting));
+            if !self.on_board(square)
+            || square[1] != row
+            || self.board[square[0]][row].is_some()
+            || self.board[square[0]][from_row].is_some()
+            || self.board[square[0]][pawn_row] != pawn {
+                problems.push(PositionProblem::BadEnPassant { square });
+            }
+        }
+        problems
+    }
+}
@@ -0,0 +1,590 @@
+// the rules of each variant. They live with the board so the parts of the
+// board only one variant cares about can stay private to its rules
+use super::{neighbours, ChessBoard};
+use crate::{chesspiece::*, event::{square_name, GameEvent, GameOverReason}, rules::{standard_game_over, standard_promotions, RuleSet, StandardRules}, variant::Variant};
+
+// the squares a king has to reach in king of the hill
+const HILL: [[usize; 2]; 4] = [[3, 3], [4, 3], [3, 4], [4, 4]];
+// capablanca chess and gothic chess add an archbishop and a chancellor
+const CAPABLANCA_BACK_RANK: [PieceType; 10] = [
+    PieceType::Rook,
+    PieceType::Knight,
+    PieceType::Archbishop,
+    PieceType::Bishop,
+    PieceType::Queen,
+    PieceType::King,
+    PieceType::Bishop,
+    PieceType::Chancellor,
+    PieceType::Knight,
+    PieceType::Rook,
+];
+const GOTHIC_BACK_RANK: [PieceType; 10] = [
+    PieceType::Rook,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Queen,
+    PieceType::Chancellor,
+    PieceType::King,
+    PieceType::Archbishop,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Rook,
+];
+
+// the rules for a variant
+pub fn variant_rules(variant: Variant) -> &'static dyn RuleSet {
+    match variant {
+        Variant::Standard => &StandardRules,
+        Variant::KingOfTheHill => &KingOfTheHillRules,
+        Variant::ThreeCheck => &ThreeCheckRules,
+        Variant::Atomic => &AtomicRules,
+        Variant::Crazyhouse => &CrazyhouseRules,
+        Variant::Antichess => &AntichessRules,
+        Variant::Horde => &HordeRules,
+        Variant::RacingKings => &RacingKingsRules,
+        Variant::Bughouse => &BughouseRules,
+        Variant::Capablanca => &CapablancaRules,
+        Variant::Gothic => &GothicRules,
+    }
+}
+
+// getting your king to the middle wins
+struct KingOfTheHillRules;
+impl RuleSet for KingOfTheHillRules {
+    fn name(&self) -> &str {
+        Variant::KingOfTheHill.as_str()
+    }
+    fn variant(&self) -> Variant {
+        Variant::KingOfTheHill
+    }
+    fn game_over(&self, board: &mut ChessBoard, is_white: bool) -> Option<(i8, GameOverReason)> {
+        if board.king_on_hill(is_white) {
+            return Some((winner(is_white), GameOverReason::KingOfTheHill));
+        }
+        standard_game_over(board, is_white)
+    }
+}
+impl ChessBoard {
+    // test if the player's king has made it to the middle
+    fn king_on_hill(&self, is_white: bool) -> bool {
+        let king_pos = if is_white {
+            self.white_king_pos
+        } else {
+            self.black_king_pos
+        };
+        king_pos.is_some_and(|pos| HILL.contains(&pos))
+    }
+}
+
+// giving check three times wins
+struct ThreeCheckRules;
+impl RuleSet for ThreeCheckRules {
+    fn name(&self) -> &str {
+        Variant::ThreeCheck.as_str()
+    }
+    fn variant(&self) -> Variant {
+        Variant::ThreeCheck
+    }
+    fn game_over(&self, board: &mut ChessBoard, is_white: bool) -> Option<(i8, GameOverReason)> {
+        // the check is counted even if it's also mate
+        let checks = board.count_check(is_white);
+        if checks.is_some_and(|checks| checks >= 3) {
+            return Some((winner(is_white), GameOverReason::ThreeCheck));
+        }
+        standard_game_over(board, is_white)
+    }
+    // the check counters
+    fn extra_save_lines(&self) -> usize {
+        1
+    }
+    fn save_lines(&self, board: &ChessBoard) -> String {
+        let [white, black] = board.get_checks();
+        format!("checks {} {}\n", white, black)
+    }
+}
+impl ChessBoard {
+    // count a check given by the player, and return how many they've given
+    // if they gave one
+    fn count_check(&mut self, is_white: bool) -> Option<u8> {
+        if !self.test_checkmate(!is_white) {
+            return None;
+        }
+        let index = if is_white {
+            0
+        } else {
+            1
+        };
+        self.checks[index] += 1;
+        self.events.push(GameEvent::ScoreChanged { score: self.score });
+        Some(self.checks[index])
+    }
+}
+
+// every capture blows up the pieces around it, and blowing up the other
+// king wins
+struct AtomicRules;
+impl RuleSet for AtomicRules {
+    fn name(&self) -> &str {
+        Variant::Atomic.as_str()
+    }
+    fn variant(&self) -> Variant {
+        Variant::Atomic
+    }
+    fn is_legal(&self, board: &mut ChessBoard, start: [usize; 2], dest: [usize; 2], piece: Piece) -> bool {
+        !board.test_atomic_check(start, dest, piece)
+    }
+    // a king next to the other king is safe, since taking it would blow up
+    // the king that took it, and there's no check once a king has been
+    // blown up
+    fn is_king_attacked(&self, board: &mut ChessBoard, space: [usize; 2], is_white: bool) -> bool {
+        match (board.find_king(is_white), board.find_king(!is_white)) {
+            (Some(_), Some(other)) if !kings_touching(space, other) => board.is_threatened(space, is_white),
+            _ => false,
+        }
+    }
+    fn kings_can_capture(&self) -> bool {
+        false
+    }
+    // the capturing piece goes up with the one it took
+    fn after_capture(&self, board: &mut ChessBoard, start: [usize; 2], dest: [usize; 2]) -> Option<String> {
+        Some(board.explode(start, dest))
+    }
+    fn game_over(&self, board: &mut ChessBoard, is_white: bool) -> Option<(i8, GameOverReason)> {
+        if board.find_king(!is_white).is_none() {
+            return Some((winner(is_white), GameOverReason::KingExploded));
+        }
+        standard_game_over(board, is_white)
+    }
+}
+impl ChessBoard {
+    // test a move and see if it blows up the player's own king or leaves
+    // it in check. Kings can't capture, and blowing up the other king is
+    // always allowed since it wins straight away
+    fn test_atomic_check(&mut self, start: [usize; 2], dest: [usize; 2], piece: Piece) -> bool {
+        let is_white = piece.is_white();
+        let mut capture_space = dest;
+        // en passant takes a pawn that isn't on dest
+        if piece.get_piece_type() == PieceType::Pawn && start[0] != dest[0]
+        && self.board[dest[0]][dest[1]].is_none() {
+            capture_space = [dest[0], start[1]];
+        }
+        let is_capture = self.board[capture_space[0]][capture_space[1]].is_some();
+        if is_capture && piece.get_piece_type() == PieceType::King {
+            return true;
+        }
+        // play the move out on the real board and put it back afterwards
+        let board = self.board;
+        self.board[start[0]][start[1]] = None;
+        if is_capture {
+            self.board[capture_space[0]][capture_space[1]] = None;
+            for space in neighbours(dest, self.get_size()) {
+                if let Some(other) = self.board[space[0]][space[1]] {
+                    if other.get_piece_type() != PieceType::Pawn {
+                        self.board[space[0]][space[1]] = None;
+                    }
+                }
+            }
+        } else {
+            self.board[dest[0]][dest[1]] = Some(piece);
+        }
+        let in_check = match (self.find_king(is_white), self.find_king(!is_white)) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            // kings next to each other can't be in check since taking
+            // one would blow up the other
+            (Some(own), Some(_)) => self.is_king_attacked(own, is_white),
+        };
+        self.board = board;
+        in_check
+    }
+    // blow up the piece that moved from start to capture on dest and every
+    // piece around dest that isn't a pawn
+    fn explode(&mut self, start: [usize; 2], dest: [usize; 2]) -> String {
+        self.take_piece(start);
+        let mut squares = Vec::new();
+        for space in neighbours(dest, self.get_size()) {
+            if let Some(piece) = self.board[space[0]][space[1]] {
+                if piece.get_piece_type() != PieceType::Pawn {
+                    self.take_piece(space);
+                    squares.push(space);
+                }
+            }
+        }
+        let exploded = GameEvent::Exploded { centre: dest, squares };
+        let explode_string = exploded.log_entry().unwrap_or_default();
+        self.events.push(exploded);
+        self.events.push(GameEvent::ScoreChanged { score: self.score });
+        explode_string
+    }
+}
+
+// captured pieces go in the capturing player's reserve to be dropped later
+struct CrazyhouseRules;
+impl RuleSet for CrazyhouseRules {
+    fn name(&self) -> &str {
+        Variant::Crazyhouse.as_str()
+    }
+    fn variant(&self) -> Variant {
+        Variant::Crazyhouse
+    }
+    fn has_drops(&self) -> bool {
+        true
+    }
+    // the reserves and which pieces were promoted
+    fn extra_save_lines(&self) -> usize {
+        2
+    }
+    fn save_lines(&self, board: &ChessBoard) -> String {
+        reserve_lines(board)
+    }
+}
+
+// crazyhouse for two boards, where captures go to the partner
+struct BughouseRules;
+impl RuleSet for BughouseRules {
+    fn name(&self) -> &str {
+        Variant::Bughouse.as_str()
+    }
+    fn variant(&self) -> Variant {
+        Variant::Bughouse
+    }
+    fn has_drops(&self) -> bool {
+        true
+    }
+    fn passes_captures(&self) -> bool {
+        true
+    }
+    fn extra_save_lines(&self) -> usize {
+        2
+    }
+    fn save_lines(&self, board: &ChessBoard) -> String {
+        reserve_lines(board)
+    }
+}
+// the reserves of both players and the squares of promoted pieces, which
+// turn back into pawns when they're taken
+fn reserve_lines(board: &ChessBoard) -> String {
+    let reserves: Vec<String> = board.get_reserve(true)
+        .iter()
+        .chain(board.get_reserve(false).iter())
+        .map(|count| count.to_string())
+        .collect();
+    let mut promoted = String::from("promoted");
+    for col in 0..board.get_width() {
+        for row in 0..board.get_height() {
+            if board.is_promoted([col, row]) {
+                promoted.push_str(&format!(" {}", square_name([col, row])));
+            }
+        }
+    }
+    format!("reserve {}\n{}\n", reserves.join(" "), promoted)
+}
+
+// taking is compulsory and losing everything wins
+struct AntichessRules;
+impl RuleSet for AntichessRules {
+    fn name(&self) -> &str {
+        Variant::Antichess.as_str()
+    }
+    fn variant(&self) -> Variant {
+        Variant::Antichess
+    }
+    fn set_up(&self, board: &mut ChessBoard) {
+        board.set_castle_rooks(true, [None; 2]).ok();
+        board.set_castle_rooks(false, [None; 2]).ok();
+    }
+    // the king is just another piece
+    fn is_legal(&self, _board: &mut ChessBoard, _start: [usize; 2], _dest: [usize; 2], _piece: Piece) -> bool {
+        true
+    }
+    fn must_capture(&self) -> bool {
+        true
+    }
+    fn has_check(&self) -> bool {
+        false
+    }
+    fn can_castle(&self) -> bool {
+        false
+    }
+    fn stalemate_wins(&self) -> bool {
+        true
+    }
+    // running out of pieces or moves wins
+    fn game_over(&self, board: &mut ChessBoard, is_white: bool) -> Option<(i8, GameOverReason)> {
+        if !board.has_pieces(!is_white) {
+            Some((-winner(is_white), GameOverReason::NoPiecesLeft))
+        } else if board.test_stalemate(!is_white) {
+            Some((-winner(is_white), GameOverReason::Stalemate))
+        } else {
+            None
+        }
+    }
+    // pawns can become kings
+    fn promotion_options(&self) -> Vec<PieceType> {
+        let mut options = standard_promotions();
+        options.push(PieceType::King);
+        options
+    }
+}
+
+// white has a horde of pawns and no king, and black has to take all of them
+struct HordeRules;
+impl RuleSet for HordeRules {
+    fn name(&self) -> &str {
+        Variant::Horde.as_str()
+    }
+    fn variant(&self) -> Variant {
+        Variant::Horde
+    }
+    // the 36 pawns fill the first four ranks and four squares of the fifth
+    fn set_up(&self, board: &mut ChessBoard) {
+        for col in 0..board.get_width() {
+            for row in 0..4 {
+                board.set([col, row], Some(Piece::new(PieceType::Pawn, true)));
+            }
+        }
+        for col in [1, 2, 5, 6].iter() {
+            board.set([*col, 4], Some(Piece::new(PieceType::Pawn, true)));
+        }
+        board.set_castle_rooks(true, [None; 2]).ok();
+        board.set_white_king_pos(None);
+    }
+    fn has_king(&self, is_white: bool) -> bool {
+        !is_white
+    }
+    fn first_rank_pawns(&self, is_white: bool) -> bool {
+        is_white
+    }
+    // double steps from the first rank can't be taken en passant
+    fn en_passant_after(&self, start: [usize; 2], is_white: bool) -> bool {
+        !(is_white && start[1] == 0)
+    }
+    fn game_over(&self, board: &mut ChessBoard, is_white: bool) -> Option<(i8, GameOverReason)> {
+        if !board.has_pieces(!is_white) {
+            return Some((winner(is_white), GameOverReason::HordeCaptured));
+        }
+        standard_game_over(board, is_white)
+    }
+}
+
+// no pawns and no checks, and the first king to the eighth rank wins
+struct RacingKingsRules;
+impl RuleSet for RacingKingsRules {
+    fn name(&self) -> &str {
+        Variant::RacingKings.as_str()
+    }
+    fn variant(&self) -> Variant {
+        Variant::RacingKings
+    }
+    // both sides start on the first two ranks, white on the right and
+    // black on the left
+    fn set_up(&self, board: &mut ChessBoard) {
+        for col in 0..board.get_width() {
+            for row in 0..board.get_height() {
+                board.set([col, row], None);
+            }
+        }
+        let first_rank = [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight];
+        let second_rank = [PieceType::King, PieceType::Rook, PieceType::Bishop, PieceType::Knight];
+        for (col, (first, second)) in first_rank.iter().zip(second_rank.iter()).enumerate() {
+            board.set([col, 0], Some(Piece::new(*first, false)));
+            board.set([col, 1], Some(Piece::new(*second, false)));
+            board.set([7 - col, 0], Some(Piece::new(*first, true)));
+            board.set([7 - col, 1], Some(Piece::new(*second, true)));
+        }
+        board.set_castle_rooks(true, [None; 2]).ok();
+        board.set_castle_rooks(false, [None; 2]).ok();
+        board.set_white_king_pos(Some([7, 1]));
+        board.set_black_king_pos(Some([0, 1]));
+    }
+    // nobody's allowed to give check
+    fn is_legal(&self, board: &mut ChessBoard, start: [usize; 2], dest: [usize; 2], piece: Piece) -> bool {
+        !board.test_check(start, dest, !piece.is_white()) && !board.test_check(start, dest, piece.is_white())
+    }
+    // black gets one more move to tie when white gets there first
+    fn game_over(&self, board: &mut ChessBoard, is_white: bool) -> Option<(i8, GameOverReason)> {
+        match board.race_winner() {
+            Some(_) if is_white && board.can_finish_race(false) => None,
+            Some(winner) => Some((winner, GameOverReason::RacingKings)),
+            None => standard_game_over(board, is_white),
+        }
+    }
+}
+impl ChessBoard {
+    // who won the race going by which kings are on the eighth rank
+    pub(super) fn race_winner(&self) -> Option<i8> {
+        let last_rank = self.height - 1;
+        let finished = |king_pos: Option<[usize; 2]>| king_pos.is_some_and(|pos| pos[1] == last_rank);
+        match (finished(self.white_king_pos), finished(self.black_king_pos)) {
+            (true, true) => Some(0),
+            (true, false) => Some(1),
+            (false, true) => Some(-1),
+            (false, false) => None,
+        }
+    }
+    // test if the player's king can get to the eighth rank this move
+    fn can_finish_race(&mut self, is_white: bool) -> bool {
+        let (king_pos, king) = match self.find_king(is_white) {
+            Some(king_pos) => (king_pos, Piece::new(PieceType::King, is_white)),
+            None => return false,
+        };
+        for col in 0..self.width {
+            if self.test_legal(king_pos, [col, self.height - 1], king) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// a ten file board with the archbishop and chancellor
+struct CapablancaRules;
+impl RuleSet for CapablancaRules {
+    fn name(&self) -> &str {
+        Variant::Capablanca.as_str()
+    }
+    fn variant(&self) -> Variant {
+        Variant::Capablanca
+    }
+    fn back_rank(&self) -> Vec<PieceType> {
+        CAPABLANCA_BACK_RANK.to_vec()
+    }
+    fn promotion_options(&self) -> Vec<PieceType> {
+        big_board_promotions()
+    }
+}
+
+// capablanca chess with the pieces in a different order
+struct GothicRules;
+impl RuleSet for GothicRules {
+    fn name(&self) -> &str {
+        Variant::Gothic.as_str()
+    }
+    fn variant(&self) -> Variant {
+        Variant::Gothic
+    }
+    fn back_rank(&self) -> Vec<PieceType> {
+        GOTHIC_BACK_RANK.to_vec()
+    }
+    fn promotion_options(&self) -> Vec<PieceType> {
+        big_board_promotions()
+    }
+}
+// pawns can become the new pieces as well
+fn big_board_promotions() -> Vec<PieceType> {
+    let mut options = standard_promotions();
+    options.push(PieceType::Archbishop);
+    options.push(PieceType::Chancellor);
+    options
+}
+
+// test if two kings are right next to each other
+fn kings_touching(white: [usize; 2], black: [usize; 2]) -> bool {
+    let x_dist = (white[0] as i32 - black[0] as i32).abs();
+    let y_dist = (white[1] as i32 - black[1] as i32).abs();
+    x_dist <= 1 && y_dist <= 1
+}
+fn winner(is_white: bool) -> i8 {
+    if is_white {
+        1
+    } else {
+        -1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    // a new game of a variant, from a FEN if there is one
+    fn game(variant: Variant, fen: Option<&str>) -> ChessBoard {
+        let mut board = ChessBoard::new();
+        board.set_variant(variant);
+        board.new_game();
+        if let Some(fen) = fen {
+            board.load_fen(fen).unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn explosions_spare_pawns() {
+        // the knight takes the queen with a pawn, a bishop and a rook around it
+        let mut board = game(Variant::Atomic, Some("k7/8/2p1b3/3q4/2R1P3/4N3/8/7K w - - 0 1"));
+        let events = board.next_turn([4, 2], [3, 4]).unwrap();
+        let squares = board.get_board();
+        // the pawns on either side survive
+        assert_eq!(squares[2][5], Some(Piece::new(PieceType::Pawn, false)));
+        assert_eq!(squares[4][3], Some(Piece::new(PieceType::Pawn, true)));
+        // everything else around it goes, whoever it belongs to
+        assert_eq!(squares[4][5], None);
+        assert_eq!(squares[2][3], None);
+        // and so do the pieces that took and were taken
+        assert_eq!(squares[3][4], None);
+        assert_eq!(squares[4][2], None);
+        let mut exploded = events.iter().filter_map(|event| match event {
+            GameEvent::Exploded { centre, squares } => Some((*centre, squares.clone())),
+            _ => None,
+        });
+        let (centre, mut squares) = exploded.next().unwrap();
+        squares.sort();
+        assert_eq!(centre, [3, 4]);
+        assert_eq!(squares, vec![[2, 3], [4, 5]]);
+        // the points for everything that went up go to the other player
+        assert_eq!(board.score, [12, 8]);
+    }
+
+    #[test]
+    fn the_horde_is_36_pawns() {
+        let board = game(Variant::Horde, None);
+        let mut horde = Vec::new();
+        for (col, file) in board.get_board().iter().enumerate() {
+            for (row, square) in file.iter().enumerate() {
+                match square {
+                    Some(piece) if piece.is_white() => {
+                        assert_eq!(piece.get_piece_type(), PieceType::Pawn);
+                        horde.push([col, row]);
+                    },
+                    _ => {},
+                }
+            }
+        }
+        assert_eq!(horde.len(), 36);
+        for col in 0..8 {
+            for row in 0..4 {
+                assert!(horde.contains(&[col, row]));
+            }
+        }
+        for col in [1, 2, 5, 6].iter() {
+            assert!(horde.contains(&[*col, 4]));
+        }
+        // black is set up as usual, white has no king and can't castle
+        assert_eq!(board.find_king(true), None);
+        assert_eq!(board.find_king(false), Some([4, 7]));
+        assert_eq!(board.get_castle_rooks(true), [None; 2]);
+        assert_eq!(board.get_castle_rooks(false), [Some(0), Some(7)]);
+    }
+
+    #[test]
+    fn taking_the_whole_horde_wins() {
+        let game_over = |events: &[GameEvent]| events.iter().any(|event| matches!(event, GameEvent::GameOver { .. }));
+        let mut board = game(Variant::Horde, Some("k7/8/8/8/8/8/P7/r6P b - - 0 1"));
+        // one pawn left isn't enough
+        assert!(!game_over(&board.next_turn([0, 0], [0, 1]).unwrap()));
+        board.next_turn([7, 0], [7, 1]).unwrap();
+        let events = board.next_turn([0, 1], [7, 1]).unwrap();
+        assert!(events.contains(&GameEvent::GameOver { winner: -1, reason: GameOverReason::HordeCaptured }));
+        assert_eq!(board.get_winner(), Some(-1));
+    }
+
+    #[test]
+    fn every_variant_has_its_rules() {
+        for variant in Variant::ALL.iter() {
+            let rules = variant_rules(*variant);
+            assert_eq!(rules.variant(), *variant);
+            assert_eq!(rules.name(), variant.as_str());
+            assert_eq!(rules.back_rank().len(), variant.size()[0]);
+        }
+    }
+}
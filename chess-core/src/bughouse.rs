@@ -0,0 +1,174 @@
+// bughouse, two games played side by side by two teams of two. The player
+// with white on one board is partnered with black on the other, and
+// everything one of them captures goes to the other to drop
+use crate::{board::ChessBoard, chesspiece::*, event::GameEvent, game::Game, variant::Variant};
+
+// an event and the board it happened on, 0 or 1
+pub type BoardEvent = (usize, GameEvent);
+
+pub struct Bughouse {
+    // each board runs its own clocks
+    boards: [ChessBoard; 2],
+    // 1 if the team with white on the first board won, -1 if the other
+    // team did and 0 for a draw
+    winner: Option<i8>,
+}
+
+impl Bughouse {
+    // a new match where every player has time_ms milliseconds
+    pub fn new(time_ms: u64) -> Self {
+        let mut bughouse = Bughouse {
+            boards: [ChessBoard::new(), ChessBoard::new()],
+            winner: None,
+        };
+        bughouse.new_match(time_ms);
+        bughouse
+    }
+    // reset both boards and the clocks
+    pub fn new_match(&mut self, time_ms: u64) {
+        for board in self.boards.iter_mut() {
+            board.set_variant(Variant::Bughouse);
+            board.set_time_control(Some([time_ms; 2]));
+            board.new_game();
+        }
+        self.winner = None;
+    }
+    // move a piece on one of the boards. None if the move isn't legal or the
+    // match is over
+    pub fn take_turn(&mut self, board: usize, start: [usize; 2], dest: [usize; 2]) -> Option<Vec<BoardEvent>> {
+        if self.winner.is_some() {
+            return None;
+        }
+        let events = self.boards[board].next_turn(start, dest)?;
+        Some(self.pass_on(board, events))
+    }
+    // drop a piece from the reserve on one of the boards
+    pub fn try_drop(&mut self, board: usize, kind: PieceType, square: [usize; 2]) -> Option<Vec<BoardEvent>> {
+        if self.winner.is_some() {
+            return None;
+        }
+        let events = self.boards[board].next_drop(kind, square)?;
+        Some(self.pass_on(board, events))
+    }
+    // finish a promotion on one of the boards
+    pub fn upgrade_pawn(&mut self, board: usize, square: [usize; 2], kind: PieceType) -> Result<Vec<BoardEvent>, String> {
+        if self.winner.is_some() {
+            return Err(String::from("the match is over"));
+        }
+        let events = self.boards[board].upgrade_pawn(square, kind)?;
+        Ok(self.pass_on(board, events))
+    }
+    // the player gives up, which loses the match for their team
+    pub fn resign(&mut self, board: usize, is_white: bool) -> Vec<BoardEvent> {
+        if self.winner.is_some() {
+            return Vec::new();
+        }
+        let events = self.boards[board].resign(is_white);
+        self.pass_on(board, events)
+    }
+    // run the clocks of the players to move on both boards. Running out of
+    // time loses the match
+    pub fn tick(&mut self, elapsed_ms: u64) -> Vec<BoardEvent> {
+        let mut events = Vec::new();
+        for board in 0..2 {
+            if self.winner.is_some() {
+                break;
+            }
+            let flagged = self.boards[board].tick(elapsed_ms);
+            events.extend(self.pass_on(board, flagged));
+        }
+        events
+    }
+    // send captured pieces over to the other board, and end the match if
+    // the game on this board ended
+    fn pass_on(&mut self, board: usize, events: Vec<GameEvent>) -> Vec<BoardEvent> {
+        let other = 1 - board;
+        let mut board_events = Vec::new();
+        for event in events {
+            match event {
+                GameEvent::Passed { piece } => {
+                    let added = self.boards[other].add_to_reserve(piece.is_white(), piece.get_piece_type());
+                    board_events.extend(added.into_iter().map(|event| (other, event)));
+                },
+                // the teams are the other way round on the second board
+                GameEvent::GameOver { winner, .. } => {
+                    self.winner = Some(if board == 0 {
+                        winner
+                    } else {
+                        -winner
+                    });
+                },
+                _ => {},
+            }
+            board_events.push((board, event));
+        }
+        board_events
+    }
+    // getters
+    pub fn get_board(&self, board: usize) -> &ChessBoard {
+        &self.boards[board]
+    }
+    // milliseconds the player has left on the given board
+    pub fn get_clock(&self, board: usize, is_white: bool) -> u64 {
+        self.boards[board].get_clock(is_white).unwrap_or_default()
+    }
+    pub fn get_winner(&self) -> Option<i8> {
+        self.winner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_go_to_the_partner() {
+        let mut bughouse = Bughouse::new(60_000);
+        bughouse.take_turn(0, [4, 1], [4, 3]).unwrap();
+        bughouse.take_turn(0, [3, 6], [3, 4]).unwrap();
+        let events = bughouse.take_turn(0, [4, 3], [3, 4]).unwrap();
+        assert!(events.contains(&(1, GameEvent::ReserveChanged { is_white: false })));
+        // white's partner plays black on the other board
+        assert_eq!(bughouse.get_board(1).get_reserve(false)[PieceType::Pawn.index()], 1);
+        assert_eq!(bughouse.get_board(0).get_reserve(true), [0; 5]);
+    }
+
+    #[test]
+    fn either_board_ends_the_match() {
+        let mut bughouse = Bughouse::new(60_000);
+        bughouse.resign(1, true);
+        // black on the second board is on the team with white on the first
+        assert_eq!(bughouse.get_winner(), Some(1));
+        assert!(bughouse.take_turn(0, [4, 1], [4, 3]).is_none());
+    }
+
+    #[test]
+    fn running_out_of_time_loses() {
+        let mut bughouse = Bughouse::new(1_000);
+        bughouse.tick(400);
+        assert_eq!(bughouse.get_clock(0, true), 600);
+        bughouse.tick(600);
+        assert_eq!(bughouse.get_winner(), Some(-1));
+    }
+
+    #[test]
+    fn promoting_uses_the_promoting_players_time() {
+        let mut bughouse = Bughouse::new(10_000);
+        bughouse.boards[0].load_fen("8/P7/8/8/8/8/8/k6K w - - 0 1").unwrap();
+        bughouse.take_turn(0, [0, 6], [0, 7]).unwrap();
+        // white still has to pick a piece
+        bughouse.tick(1_000);
+        assert_eq!(bughouse.get_clock(0, true), 9_000);
+        assert_eq!(bughouse.get_clock(0, false), 10_000);
+    }
+
+    #[test]
+    fn nothing_is_promoted_once_the_match_is_over() {
+        let mut bughouse = Bughouse::new(60_000);
+        bughouse.boards[0].load_fen("8/P7/8/8/8/8/8/k6K w - - 0 1").unwrap();
+        bughouse.take_turn(0, [0, 6], [0, 7]).unwrap();
+        bughouse.resign(1, false);
+        assert!(bughouse.get_winner().is_some());
+        assert!(bughouse.upgrade_pawn(0, [0, 7], PieceType::Queen).is_err());
+    }
+}
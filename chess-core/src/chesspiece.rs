@@ -0,0 +1,398 @@
+use std::fmt;
+use std::num::NonZeroU8;
+use std::iter::Zip;
+use std::vec::IntoIter;
+use crate::board::ChessBoard;
+use crate::fairy::{self, BetzaPiece};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PieceType {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+    // moves like a bishop or a knight
+    Archbishop,
+    // moves like a rook or a knight
+    Chancellor,
+    // a piece from the fairy pieces file, by where it is in the file
+    Fairy(u8),
+}
+impl PieceType {
+    // every piece type that's built in, in the same order as their indexes
+    pub const ALL: [PieceType; 8] = [
+        PieceType::Pawn,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::Queen,
+        PieceType::King,
+        PieceType::Archbishop,
+        PieceType::Chancellor,
+    ];
+    // a number for each piece type, which is also where the reserve
+    // counts of the pieces that can be dropped are kept
+    pub fn index(self) -> usize {
+        match self {
+            PieceType::Pawn => 0,
+            PieceType::Knight => 1,
+            PieceType::Bishop => 2,
+            PieceType::Rook => 3,
+            PieceType::Queen => 4,
+            PieceType::King => 5,
+            PieceType::Archbishop => 6,
+            PieceType::Chancellor => 7,
+            PieceType::Fairy(id) => PieceType::ALL.len() + id as usize,
+        }
+    }
+    // white and black symbols for the piece
+    pub fn symbols(self) -> [&'static str; 2] {
+        match self {
+            PieceType::Pawn => ["♙", "♟"],
+            PieceType::Knight => ["♘", "♞"],
+            PieceType::Bishop => ["♗", "♝"],
+            PieceType::Rook => ["♖", "♜"],
+            PieceType::Queen => ["♕", "♛"],
+            PieceType::King => ["♔", "♚"],
+            PieceType::Archbishop => ["🩐", "🩓"],
+            PieceType::Chancellor => ["🩏", "🩒"],
+            PieceType::Fairy(id) => fairy::fairy_piece(id).map_or(["?", "?"], |piece| piece.get_symbols()),
+        }
+    }
+    // the letter for the piece in FEN and algebraic notation
+    pub fn letter(self) -> char {
+        match self {
+            PieceType::Pawn => 'P',
+            PieceType::Knight => 'N',
+            PieceType::Bishop => 'B',
+            PieceType::Rook => 'R',
+            PieceType::Queen => 'Q',
+            PieceType::King => 'K',
+            PieceType::Archbishop => 'A',
+            PieceType::Chancellor => 'C',
+            PieceType::Fairy(id) => fairy::fairy_piece(id).map_or('?', |piece| piece.get_letter()),
+        }
+    }
+    pub fn from_letter(letter: char) -> Option<Self> {
+        PieceType::ALL.iter().copied()
+            .chain(fairy::fairy_piece_types())
+            .find(|kind| kind.letter() == letter)
+    }
+    // the piece type godot calls name
+    pub fn from_name(name: &str) -> Option<Self> {
+        PieceType::ALL.iter().copied()
+            .chain(fairy::fairy_piece_types())
+            .find(|kind| kind.as_str() == name)
+    }
+    // the name godot uses for the piece
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PieceType::Pawn => "pawn",
+            PieceType::Knight => "knight",
+            PieceType::Bishop => "bishop",
+            PieceType::Rook => "rook",
+            PieceType::Queen => "queen",
+            PieceType::King => "king",
+            PieceType::Archbishop => "archbishop",
+            PieceType::Chancellor => "chancellor",
+            PieceType::Fairy(id) => fairy::fairy_piece(id).map_or("fairy", |piece| piece.get_name()),
+        }
+    }
+}
+
+// Chess Piece trait
+pub trait ChessPiece {
+    fn test_move(
+        &self,
+        start: [usize; 2],
+        dest: [usize; 2],
+        board: &mut ChessBoard) -> bool {
+        let dest_player = board.get_board()[dest[0]][dest[1]].map(|piece| piece.is_white());
+        // kings are let through onto their own pieces since moving onto
+        // your own rook is how castling is entered in chess960
+        if start != dest && (!check_occupied(self.is_white(), dest_player)
+        || self.get_piece_type() == PieceType::King) {
+            return self.test_space(start, dest, dest_player, board);
+        }
+        false
+    }
+    fn test_space(
+        &self,
+        start: [usize; 2],
+        dest: [usize; 2],
+        dest_player: Option<bool>,
+        board: &mut ChessBoard) -> bool;
+    fn get_points(&self) -> u8;
+    fn get_piece_type(&self) -> PieceType;
+    fn is_white(&self) -> bool;
+    fn get_symbol(&self, index: usize) -> &'static str;
+    fn as_str(&self) -> &'static str {
+        if self.is_white() {
+            return self.get_symbol(0);
+        }
+        self.get_symbol(1)
+    }
+}
+// Diagonal Move
+pub trait DiagonalMove : ChessPiece {
+    fn test_diagonal(
+        &self,
+        start: [usize; 2],
+        dest: [usize; 2],
+        board: &mut ChessBoard) -> bool {
+        let (x_dist, y_dist) = find_dist(start, dest);
+        if x_dist.abs() == y_dist.abs() {
+            for (x, y) in path_iter(start, dest) {
+                if board.get_board()[x][y].is_some() {
+                    return false;
+                }
+            }
+            return true;
+        }
+        false
+    }
+}
+// Straight Move
+pub trait StraightMove : ChessPiece {
+    fn test_straight(
+        &self,
+        start: [usize; 2],
+        dest: [usize; 2],
+        board: &mut ChessBoard) -> bool {
+        if start[0] == dest[0] || start[1] == dest[1] {
+            for (x, y) in path_iter(start, dest) {
+                if board.get_board()[x][y].is_some() {
+                    return false;
+                }
+            }
+            return true;
+        }
+        false
+    }
+}
+// Knight Move
+pub trait KnightMove : ChessPiece {
+    fn test_knight(
+        &self,
+        start: [usize; 2],
+        dest: [usize; 2]) -> bool {
+        let (x_dist, y_dist) = find_dist(start, dest);
+        if x_dist.abs() == 2 {
+            if y_dist.abs() == 1 {
+                return true;
+            }
+        } else if y_dist.abs() == 2 && x_dist.abs() == 1 {
+            return true;
+        }
+        false
+    }
+}
+// King Move
+pub trait KingMove : ChessPiece {
+    fn test_king(
+        &self,
+        start: [usize; 2],
+        dest: [usize; 2],
+        dest_player: Option<bool>,
+        board: &mut ChessBoard) -> bool {
+        // if player is trying to castle
+        if board.find_castle(start, dest, self.is_white()).is_some() {
+            return board.test_castle(start, dest, self.is_white());
+        }
+        let (x_dist, y_dist) = find_dist(start, dest);
+        !check_occupied(self.is_white(), dest_player) && x_dist.abs() <= 1 && y_dist.abs() <= 1
+    }
+}
+// Pawn Move
+pub trait PawnMove : ChessPiece {
+    fn test_pawn(
+        &self,
+        start: [usize; 2],
+        dest: [usize; 2],
+        dest_player: Option<bool>,
+        board: &mut ChessBoard) -> bool {
+        if start[0] == dest[0] {
+            if let Some(_player) = dest_player {
+                return false;
+            }
+        }
+        let direction = if self.is_white() {
+            -1
+        } else {
+            1
+        };
+        let x_dist = find_dist(start, dest).0;
+        let y_diff = start[1] as i32 - dest[1] as i32;
+        if y_diff/direction == 1 {
+            if x_dist.abs() == 1 {
+                // if the square is occupied
+                if dest_player.is_some() {
+                    return true;
+                // if the square is empty
+                } else {
+                    let en_passant_space = if self.is_white() {
+                        board.get_black_en_passant()
+                    } else {
+                        board.get_white_en_passant()
+                    };
+                    if let Some(space) = en_passant_space {
+                        if dest == space {
+                            return true;
+                        }
+                    }
+                }
+            } else if x_dist == 0 {
+                return true;
+            }
+        } else if y_diff/direction == 2 && (start[1] == 1 || start[1] == board.get_height() - 2
+        // the horde's pawns can also double step from the first rank
+        || (start[1] == 0 && self.is_white() && board.get_rules().first_rank_pawns(true))) {
+            let capture_point = [dest[0], (dest[1] as i32 + direction) as usize];
+            let x = capture_point[0];
+            let y = capture_point[1];
+            if dest_player.is_none() && x_dist == 0 
+            && board.get_board()[x][y].is_none() {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+fn check_occupied(player: bool, dest_player: Option<bool>) -> bool {
+    if let Some(dest_player) = dest_player {
+        if player == dest_player {
+            return true;
+        }
+    }
+    false
+}
+
+// Piece struct. A piece is a single byte: the kind lives in the low bits and
+// the colour in the high bit, so squares are cheap to copy around. The encoding
+// is offset by one so that Option<Piece> still fits in one byte.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Piece(NonZeroU8);
+impl Piece {
+    pub fn new(kind: PieceType, is_white: bool) -> Self {
+        let colour = if is_white { 0x80 } else { 0 };
+        Piece(NonZeroU8::new((kind.index() as u8 + 1) | colour).unwrap())
+    }
+    pub fn kind(&self) -> PieceType {
+        let index = ((self.0.get() & 0x7f) - 1) as usize;
+        match PieceType::ALL.get(index) {
+            Some(kind) => *kind,
+            None => PieceType::Fairy((index - PieceType::ALL.len()) as u8),
+        }
+    }
+    // read a piece back from the symbol used in saves
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        for kind in PieceType::ALL.iter().copied().chain(fairy::fairy_piece_types()) {
+            let symbols = kind.symbols();
+            if symbols[0].starts_with(symbol) {
+                return Some(Piece::new(kind, true));
+            } else if symbols[1].starts_with(symbol) {
+                return Some(Piece::new(kind, false));
+            }
+        }
+        None
+    }
+}
+impl PawnMove for Piece {}
+impl DiagonalMove for Piece {}
+impl StraightMove for Piece {}
+impl KnightMove for Piece {}
+impl KingMove for Piece {}
+impl ChessPiece for Piece {
+    fn is_white(&self) -> bool {
+        self.0.get() & 0x80 != 0
+    }
+    // each kind of piece moves differently
+    fn test_space(
+        &self,
+        start: [usize; 2],
+        dest: [usize; 2],
+        dest_player: Option<bool>,
+        board: &mut ChessBoard) -> bool {
+        match self.kind() {
+            PieceType::Pawn => self.test_pawn(start, dest, dest_player, board),
+            PieceType::Knight => self.test_knight(start, dest),
+            PieceType::Bishop => self.test_diagonal(start, dest, board),
+            PieceType::Rook => self.test_straight(start, dest, board),
+            PieceType::Queen => {
+                self.test_straight(start, dest, board) || self.test_diagonal(start, dest, board)
+            },
+            PieceType::King => self.test_king(start, dest, dest_player, board),
+            PieceType::Archbishop => {
+                self.test_diagonal(start, dest, board) || self.test_knight(start, dest)
+            },
+            PieceType::Chancellor => {
+                self.test_straight(start, dest, board) || self.test_knight(start, dest)
+            },
+            PieceType::Fairy(id) => BetzaPiece::new(id, self.is_white())
+                .is_some_and(|piece| piece.test_space(start, dest, dest_player, board)),
+        }
+    }
+    fn get_piece_type(&self) -> PieceType {
+        self.kind()
+    }
+    fn get_points(&self) -> u8 {
+        match self.kind() {
+            PieceType::Pawn => 1,
+            PieceType::Knight | PieceType::Bishop => 3,
+            PieceType::Rook => 5,
+            PieceType::Queen => 9,
+            PieceType::King => 0,
+            PieceType::Archbishop => 7,
+            PieceType::Chancellor => 8,
+            PieceType::Fairy(id) => fairy::fairy_piece(id).map_or(0, |piece| piece.get_points()),
+        }
+    }
+    fn get_symbol(&self, index: usize) -> &'static str {
+        self.kind().symbols()[index]
+    }
+}
+// show the kind and colour rather than the packed byte
+impl fmt::Debug for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Piece")
+            .field("kind", &self.kind())
+            .field("is_white", &self.is_white())
+            .finish()
+    }
+}
+// Display trait for pieces
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+// calculate distance
+fn find_dist(start: [usize; 2], dest: [usize; 2]) -> (i32, i32) {
+    let x_dist = start[0] as i32 - dest[0] as i32;
+    let y_dist = start[1] as i32 - dest[1] as i32;
+    (x_dist, y_dist)
+}
+// make an iterator for traversing a piece's path
+fn path_iter(start: [usize; 2], dest: [usize; 2]) -> Zip<IntoIter<usize>, IntoIter<usize>> {
+    let (mut x_iter, x_forwards) = if start[0] < dest[0] {
+        ((start[0]+1..dest[0]).collect(), true)
+    } else if dest[0] < start[0] {
+        ((dest[0]+1..start[0]).collect(), false)
+    } else {
+        (vec![start[0]; find_dist(start, dest).1.unsigned_abs() as usize], true)
+    };
+    let (y_iter, y_forwards) = if start[1] < dest[1] {
+        ((start[1]+1..dest[1]).collect(), true)
+    } else if dest[1] < start[1] {
+        ((dest[1]+1..start[1]).collect(), false)
+    } else {
+        (vec![start[1]; find_dist(start, dest).0.unsigned_abs() as usize], true)
+    };
+    if x_forwards != y_forwards {
+       x_iter.reverse(); 
+    }
+    x_iter.into_iter().zip(y_iter)
+}
@@ -0,0 +1,332 @@
+// an index of the saved games so they can be searched, kept in the save
+// directory next to them. Writing or deleting a save updates its entry
+// straight away, and opening the database catches up with saves that were
+// changed some other way, like being copied into the directory
+use crate::{
+    board::ChessBoard,
+    chesspiece::PieceType,
+    save::{self, LoadError, SaveMetadata, SavedMove},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, io};
+
+// the index is written again from scratch when this changes
+const INDEX_VERSION: u32 = 1;
+// how many moves from the start of a game count as its opening
+pub const OPENING_PLIES: usize = 10;
+
+// what's known about a saved game
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub name: String,
+    pub metadata: SaveMetadata,
+    // the first moves from square to square, like "e2e4 e7e5 g1f3", with
+    // drops like N@e4 and the letter of the piece after a promotion
+    pub opening: String,
+    // the pieces left at the end, white's then black's, like KRPPvKR
+    pub material: String,
+    // when the save was last written, in seconds since 1970
+    pub modified: u64,
+}
+#[derive(Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    games: Vec<GameRecord>,
+}
+
+// what to look for in the saved games. Anything left as None matches every game
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameQuery {
+    // part of either player's name, in any case
+    pub player: Option<String>,
+    // the first and last days to look between, like 2020-05-17
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    // 1-0, 0-1, 1/2-1/2 or *
+    pub result: Option<String>,
+    pub variant: Option<String>,
+    // the moves the game starts with, written like GameRecord::opening
+    pub opening: Option<String>,
+    pub min_moves: Option<usize>,
+    pub max_moves: Option<usize>,
+    // the pieces left at the end, written like GameRecord::material
+    pub material: Option<String>,
+}
+impl GameQuery {
+    pub fn matches(&self, record: &GameRecord) -> bool {
+        let metadata = &record.metadata;
+        if let Some(player) = &self.player {
+            let player = player.to_lowercase();
+            if !metadata.white.to_lowercase().contains(&player)
+            && !metadata.black.to_lowercase().contains(&player) {
+                return false;
+            }
+        }
+        // games without a date can't be in a range of them
+        if self.date_from.is_some() || self.date_to.is_some() {
+            if metadata.date.is_empty() {
+                return false;
+            }
+            // dates written like 2020-05-17 sort the same as strings
+            if self.date_from.as_ref().is_some_and(|from| metadata.date < *from)
+            || self.date_to.as_ref().is_some_and(|to| metadata.date > *to) {
+                return false;
+            }
+        }
+        if self.result.as_ref().is_some_and(|result| metadata.result != *result)
+        || self.variant.as_ref().is_some_and(|variant| metadata.variant != *variant) {
+            return false;
+        }
+        // whole moves have to match, so e2e doesn't find e2e4
+        if let Some(opening) = &self.opening {
+            let opening: Vec<&str> = opening.split_whitespace().collect();
+            let moves: Vec<&str> = record.opening.split_whitespace().collect();
+            if !moves.starts_with(&opening) {
+                return false;
+            }
+        }
+        if self.min_moves.is_some_and(|min| metadata.move_count < min)
+        || self.max_moves.is_some_and(|max| metadata.move_count > max) {
+            return false;
+        }
+        if let Some(material) = &self.material {
+            if !record.material.eq_ignore_ascii_case(material.trim()) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// the saved games by name
+#[derive(Clone, Debug, Default)]
+pub struct GameDatabase {
+    games: BTreeMap<String, GameRecord>,
+}
+impl GameDatabase {
+    // the index of the saves in the save directory, brought up to date with
+    // them. Saves that can't be read are left out
+    pub fn open() -> Self {
+        let mut database = GameDatabase::read_index();
+        let names = save::save_names();
+        let before = database.games.len();
+        database.games.retain(|name, _| names.contains(name));
+        let mut changed = database.games.len() != before;
+        for name in names {
+            let modified = save::save_modified(&name).unwrap_or(0);
+            let current = database.games.get(&name).is_some_and(|record| record.modified == modified);
+            if !current {
+                database.index_save(&name);
+                changed = true;
+            }
+        }
+        // it gets caught up again next time if this fails
+        if changed {
+            database.write_index().ok();
+        }
+        database
+    }
+    // the games that match, newest first. Games without a date come last
+    pub fn query(&self, query: &GameQuery) -> Vec<&GameRecord> {
+        let mut games: Vec<&GameRecord> = self.games.values().filter(|record| query.matches(record)).collect();
+        games.sort_by(|a, b| b.metadata.date.cmp(&a.metadata.date).then_with(|| a.name.cmp(&b.name)));
+        games
+    }
+    pub fn get(&self, save_name: &str) -> Option<&GameRecord> {
+        self.games.get(save_name)
+    }
+    pub fn games(&self) -> impl Iterator<Item = &GameRecord> {
+        self.games.values()
+    }
+    // bring the database up to date after a save is written, without
+    // reading the others again
+    pub fn save_written(&mut self, save_name: &str) -> io::Result<()> {
+        self.index_save(save_name);
+        self.write_index()
+    }
+    // and after one is deleted
+    pub fn save_deleted(&mut self, save_name: &str) -> io::Result<()> {
+        if self.games.remove(save_name).is_some() {
+            self.write_index()?;
+        }
+        Ok(())
+    }
+    // the index as it was last written, without checking it against the saves
+    fn read_index() -> Self {
+        let index = save::read_index()
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Index>(&contents).ok())
+            .filter(|index| index.version == INDEX_VERSION);
+        let games = match index {
+            Some(index) => index.games.into_iter().map(|record| (record.name.clone(), record)).collect(),
+            None => BTreeMap::new(),
+        };
+        GameDatabase { games }
+    }
+    fn write_index(&self) -> io::Result<()> {
+        let index = Index {
+            version: INDEX_VERSION,
+            games: self.games.values().cloned().collect(),
+        };
+        // nothing in the index can fail to be written as JSON
+        save::write_index(&serde_json::to_string(&index).unwrap_or_default())
+    }
+    // read a save into the index, or take it out if it can't be read
+    fn index_save(&mut self, save_name: &str) {
+        match record(save_name) {
+            Ok(record) => self.games.insert(String::from(save_name), record),
+            Err(_) => self.games.remove(save_name),
+        };
+    }
+}
+
+// keep the index up to date after a save is written
+pub(crate) fn save_written(save_name: &str) -> io::Result<()> {
+    GameDatabase::read_index().save_written(save_name)
+}
+// and after one is deleted
+pub(crate) fn save_deleted(save_name: &str) -> io::Result<()> {
+    GameDatabase::read_index().save_deleted(save_name)
+}
+
+// everything the index keeps about a save
+fn record(save_name: &str) -> Result<GameRecord, LoadError> {
+    let contents = save::read_save(save_name)?;
+    game_record(save_name, contents, save::save_modified(save_name)?)
+}
+fn game_record(save_name: &str, contents: String, modified: u64) -> Result<GameRecord, LoadError> {
+    // reading it into a board works for saves in any format
+    let board = ChessBoard::from_save(contents)?;
+    let file = board.to_save_file()?;
+    let opening: Vec<String> = file.moves
+        .iter()
+        .take(OPENING_PLIES)
+        .filter_map(move_key)
+        .collect();
+    let material = if file.moves.is_empty() { String::new() } else { material_signature(&board.placement()) };
+    Ok(GameRecord {
+        name: String::from(save_name),
+        metadata: file.metadata,
+        opening: opening.join(" "),
+        material,
+        modified,
+    })
+}
+// a saved move as g1f3, N@e4 for a drop, or e7e8q for a promotion
+fn move_key(saved_move: &SavedMove) -> Option<String> {
+    let mut key = match (&saved_move.from, &saved_move.drop) {
+        (Some(from), _) => format!("{}{}", from, saved_move.to),
+        (None, Some(piece)) => format!("{}@{}", PieceType::from_name(piece)?.letter(), saved_move.to),
+        (None, None) => return None,
+    };
+    if let Some(piece) = &saved_move.promotion {
+        key.push(PieceType::from_name(piece)?.letter().to_ascii_lowercase());
+    }
+    Some(key)
+}
+// the pieces in the first field of a FEN, as the letters of white's pieces
+// then v then black's, biggest pieces first like KQRRBNPPPvKQRBNNPP
+pub fn material_signature(placement: &str) -> String {
+    const ORDER: &str = "KQCARBN";
+    let side = |is_white: bool| {
+        let mut letters: Vec<char> = placement.chars()
+            .filter(|letter| letter.is_alphabetic() && letter.is_uppercase() == is_white)
+            .map(|letter| letter.to_ascii_uppercase())
+            .collect();
+        // fairy pieces go after the usual ones and pawns go last
+        letters.sort_by_key(|letter| match ORDER.find(*letter) {
+            Some(index) => (index, *letter),
+            None if *letter == 'P' => (ORDER.len() + 1, *letter),
+            None => (ORDER.len(), *letter),
+        });
+        letters.into_iter().collect::<String>()
+    };
+    format!("{}v{}", side(true), side(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    // the record of a game played from the start with moves like e2e4
+    fn played(moves: &[&str]) -> GameRecord {
+        let mut board = ChessBoard::new();
+        board.set_players("Anna", "Ben");
+        for text in moves {
+            let square = |text: &str| {
+                let text = text.as_bytes();
+                [(text[0] - b'a') as usize, (text[1] - b'1') as usize]
+            };
+            board.next_turn(square(&text[..2]), square(&text[2..4])).unwrap();
+        }
+        let contents = board.to_save_file().unwrap().to_json();
+        game_record("game", contents, 0).unwrap()
+    }
+
+    #[test]
+    fn records_have_the_moves() {
+        let record = played(&["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert_eq!(record.opening, "f2f3 e7e5 g2g4 d8h4");
+        assert_eq!(record.material, "KQRRBBNNPPPPPPPPvKQRRBBNNPPPPPPPP");
+        assert_eq!(record.metadata.move_count, 4);
+        assert_eq!(record.metadata.result, "0-1");
+        let promotion = SavedMove {
+            from: Some(String::from("a7")),
+            to: String::from("a8"),
+            promotion: Some(String::from("knight")),
+            drop: None,
+        };
+        assert_eq!(move_key(&promotion).as_deref(), Some("a7a8n"));
+        let drop = SavedMove {
+            to: String::from("e4"),
+            drop: Some(String::from("knight")),
+            ..SavedMove::default()
+        };
+        assert_eq!(move_key(&drop).as_deref(), Some("N@e4"));
+    }
+
+    #[test]
+    fn queries_match_whole_moves() {
+        let record = played(&["e2e4", "e7e5"]);
+        let opening = |opening: &str| GameQuery {
+            opening: Some(String::from(opening)),
+            ..GameQuery::default()
+        };
+        assert!(opening("e2e4").matches(&record));
+        assert!(opening(" e2e4  e7e5 ").matches(&record));
+        assert!(!opening("e2e").matches(&record));
+        assert!(!opening("e2e4 e7e").matches(&record));
+        assert!(!opening("e2e4 e7e5 g1f3").matches(&record));
+    }
+
+    #[test]
+    fn queries_match_the_metadata() {
+        let mut record = played(&["e2e4"]);
+        record.metadata.date = String::from("2020-05-17");
+        let query = |query: GameQuery| query.matches(&record);
+        assert!(query(GameQuery::default()));
+        assert!(query(GameQuery { player: Some(String::from("ann")), ..GameQuery::default() }));
+        assert!(!query(GameQuery { player: Some(String::from("cleo")), ..GameQuery::default() }));
+        assert!(query(GameQuery {
+            date_from: Some(String::from("2020-01-01")),
+            date_to: Some(String::from("2020-12-31")),
+            ..GameQuery::default()
+        }));
+        assert!(!query(GameQuery { date_from: Some(String::from("2021-01-01")), ..GameQuery::default() }));
+        assert!(query(GameQuery { result: Some(String::from("*")), ..GameQuery::default() }));
+        assert!(!query(GameQuery { variant: Some(String::from("atomic")), ..GameQuery::default() }));
+        assert!(query(GameQuery { min_moves: Some(1), max_moves: Some(1), ..GameQuery::default() }));
+        assert!(!query(GameQuery { min_moves: Some(2), ..GameQuery::default() }));
+        assert!(query(GameQuery {
+            material: Some(String::from("kqrrbbnnppppppppvkqrrbbnnpppppppp")),
+            ..GameQuery::default()
+        }));
+    }
+
+    #[test]
+    fn material_puts_pawns_last() {
+        assert_eq!(material_signature("r3k2r/8/8/8/8/8/PP6/R3KQ2"), "KQRPPvKRR");
+        assert_eq!(material_signature("8/8/8/8/8/8/8/8"), "v");
+    }
+}
@@ -0,0 +1,170 @@
+use crate::chesspiece::Piece;
+use std::sync::Arc;
+
+// why a game ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameOverReason {
+    Checkmate,
+    Stalemate,
+    Resignation,
+    DrawAgreed,
+    // a king reached the middle of the board in king of the hill
+    KingOfTheHill,
+    // a player gave their third check in three-check
+    ThreeCheck,
+    // a king was caught in an explosion in atomic chess
+    KingExploded,
+    // a player got rid of all their pieces in antichess
+    NoPiecesLeft,
+    // black took every pawn in the horde
+    HordeCaptured,
+    // a king made it to the eighth rank in racing kings, or both did for a tie
+    RacingKings,
+    // a player's clock ran out
+    OutOfTime,
+}
+impl GameOverReason {
+    pub const ALL: [GameOverReason; 11] = [
+        GameOverReason::Checkmate,
+        GameOverReason::Stalemate,
+        GameOverReason::Resignation,
+        GameOverReason::DrawAgreed,
+        GameOverReason::KingOfTheHill,
+        GameOverReason::ThreeCheck,
+        GameOverReason::KingExploded,
+        GameOverReason::NoPiecesLeft,
+        GameOverReason::HordeCaptured,
+        GameOverReason::RacingKings,
+        GameOverReason::OutOfTime,
+    ];
+    // the name godot gets for the reason
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GameOverReason::Checkmate => "checkmate",
+            GameOverReason::Stalemate => "stalemate",
+            GameOverReason::Resignation => "resignation",
+            GameOverReason::DrawAgreed => "draw_agreed",
+            GameOverReason::KingOfTheHill => "king_of_the_hill",
+            GameOverReason::ThreeCheck => "three_check",
+            GameOverReason::KingExploded => "king_exploded",
+            GameOverReason::NoPiecesLeft => "no_pieces_left",
+            GameOverReason::HordeCaptured => "horde_captured",
+            GameOverReason::RacingKings => "racing_kings",
+            GameOverReason::OutOfTime => "out_of_time",
+        }
+    }
+    // what gets written after the move in the save when the move ended the
+    // game. Resigning, draws and running out of time aren't moves so they
+    // don't get one
+    pub fn save_note(self) -> Option<&'static str> {
+        match self {
+            GameOverReason::Checkmate => Some(" checkmate"),
+            GameOverReason::Stalemate => Some(" stalemate"),
+            GameOverReason::KingOfTheHill => Some(" king of the hill"),
+            GameOverReason::ThreeCheck => Some(" third check"),
+            GameOverReason::KingExploded => Some(" king exploded"),
+            GameOverReason::NoPiecesLeft => Some(" no pieces left"),
+            GameOverReason::HordeCaptured => Some(" horde captured"),
+            GameOverReason::RacingKings => Some(" race finished"),
+            GameOverReason::Resignation | GameOverReason::DrawAgreed | GameOverReason::OutOfTime => None,
+        }
+    }
+}
+
+// things that happen during a game. Every state change of the board returns
+// the events it caused, and they are also sent to any subscribed observers,
+// so front ends can react to the game without the core knowing about them
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    // a piece moved. turn is the number of the move in the game, starting at 1
+    MoveMade { turn: usize, piece: Piece, start: [usize; 2], dest: [usize; 2] },
+    // a piece was dropped from the reserve onto square in crazyhouse
+    Dropped { turn: usize, piece: Piece, square: [usize; 2] },
+    // the pieces in the player's crazyhouse reserve changed
+    ReserveChanged { is_white: bool },
+    // a captured piece in bughouse that goes to the reserve of the player
+    // of the same colour on the other board
+    Passed { piece: Piece },
+    // a piece was taken off the given square
+    Captured { piece: Piece, square: [usize; 2] },
+    // a capture in atomic chess blew up the capturing piece at centre
+    // along with the pieces on squares
+    Exploded { centre: [usize; 2], squares: Vec<[usize; 2]> },
+    // the rook in a castle moved from rook_start to rook_dest and the
+    // king ended up on king_dest, which in chess960 isn't always where it was dropped
+    Castled { rook_start: [usize; 2], rook_dest: [usize; 2], king_dest: [usize; 2] },
+    // a pawn reached the last rank and is waiting for upgrade_pawn
+    PromotionPending { square: [usize; 2] },
+    // a pawn was turned into piece
+    Promoted { square: [usize; 2], piece: Piece },
+    // the given player's king is in check
+    Check { is_white: bool },
+    // 1 if white won, -1 if black won and 0 for a draw
+    GameOver { winner: i8, reason: GameOverReason },
+    // the points each player has from captures, white first
+    ScoreChanged { score: [u8; 2] },
+    // the number of turns that can be loaded changed
+    MovesUpdated { turns: usize },
+    // a turn from the save was loaded onto the board
+    TurnLoaded { turn: usize },
+    // everything for the current turn has been reported
+    TurnEnded,
+}
+impl GameEvent {
+    // the text the event adds to the move log, if any. This is the same
+    // text that gets written to save files
+    pub fn log_entry(&self) -> Option<String> {
+        match self {
+            GameEvent::MoveMade { turn, piece, start, dest } => {
+                Some(format!("{}: {} {} → {}", turn, piece, square_name(*start), square_name(*dest)))
+            },
+            GameEvent::Dropped { turn, piece, square } => {
+                Some(format!("{}: {} @ {}", turn, piece, square_name(*square)))
+            },
+            GameEvent::Captured { piece, .. } => Some(format!(" captures {}", piece)),
+            GameEvent::Promoted { piece, .. } => Some(format!(" promoted to {}", piece)),
+            GameEvent::Exploded { squares, .. } if !squares.is_empty() => {
+                Some(format!(" and blows up {} more", squares.len()))
+            },
+            GameEvent::TurnEnded => Some(String::from("\n")),
+            _ => None,
+        }
+    }
+}
+// name a square the way players do, like e4
+pub fn square_name(square: [usize; 2]) -> String {
+    format!("{}{}", (square[0] + 97) as u8 as char, square[1] + 1)
+}
+
+// something that wants to hear about every event in a game
+pub trait GameObserver: Send + Sync {
+    fn notify(&self, event: &GameEvent);
+}
+impl<F: Fn(&GameEvent) + Send + Sync> GameObserver for F {
+    fn notify(&self, event: &GameEvent) {
+        self(event)
+    }
+}
+
+// the observers subscribed to a board. Copies of a board (for searching
+// positions and the like) start without any observers so that moves tried
+// on them aren't reported as if they happened in the real game
+#[derive(Default)]
+pub struct Observers(Vec<Arc<dyn GameObserver>>);
+impl Observers {
+    pub fn subscribe(&mut self, observer: Arc<dyn GameObserver>) {
+        self.0.push(observer);
+    }
+    pub fn notify(&self, events: &[GameEvent]) {
+        for observer in &self.0 {
+            for event in events {
+                observer.notify(event);
+            }
+        }
+    }
+}
+impl Clone for Observers {
+    fn clone(&self) -> Self {
+        Observers::default()
+    }
+}
@@ -0,0 +1,549 @@
+// fairy pieces, which are defined in a data file instead of in code. Their
+// moves are written in Betza notation (Betza's funny notation on wikipedia),
+// which builds a piece out of the steps it can take:
+//   leapers jump straight to a square, W F D N A H C Z G
+//   riders keep going in a line, R B Q or a doubled leaper like NN
+//   K and Q are the king and queen, a number after a letter limits the range
+// and lower case letters in front change the move:
+//   m only moves, c only captures
+//   p hops over exactly one piece on the way, like a cannon
+//   g hops over the piece right before the square it lands on, like a grasshopper
+//   f b l r s v and pairs like fl or ff pick the directions it goes in
+use std::{io, sync::RwLock};
+use crate::{board::ChessBoard, chesspiece::*, save};
+
+// fairy pieces share the byte a piece is stored in with the usual ones,
+// so there's only room for so many of them
+pub const MAX_FAIRY_PIECES: usize = 100;
+
+// every fairy piece that's been added. A piece on the board only has the
+// place of its definition in here, so definitions are never removed or
+// changed
+static FAIRY_PIECES: RwLock<Vec<&'static FairyPiece>> = RwLock::new(Vec::new());
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Hop {
+    None,
+    // jumps one piece anywhere along the line
+    Cannon,
+    // jumps the piece right before where it lands
+    Grasshopper,
+}
+
+// one way a piece can move, like the N in a knight or the R in a rook
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Atom {
+    // the squares one step can go to, as files right and ranks forward
+    steps: Vec<[i32; 2]>,
+    // how many steps it can take in a line, 0 for as many as it likes
+    range: i32,
+    can_move: bool,
+    can_capture: bool,
+    hop: Hop,
+}
+impl Atom {
+    fn test(
+        &self,
+        start: [usize; 2],
+        dest: [usize; 2],
+        dest_player: Option<bool>,
+        is_white: bool,
+        board: &ChessBoard) -> bool {
+        if (dest_player.is_some() && !self.can_capture) || (dest_player.is_none() && !self.can_move) {
+            return false;
+        }
+        // forward is down the board for black
+        let forward = if is_white {
+            1
+        } else {
+            -1
+        };
+        let dist = [
+            dest[0] as i32 - start[0] as i32,
+            (dest[1] as i32 - start[1] as i32)*forward,
+        ];
+        self.steps.iter().any(|step| {
+            let count = match steps_to(dist, *step) {
+                Some(count) if self.range == 0 || count <= self.range => count,
+                _ => return false,
+            };
+            // the steps on the way that have a piece on them
+            let blocked: Vec<i32> = (1..count)
+                .filter(|i| {
+                    let col = start[0] as i32 + step[0]*i;
+                    let row = start[1] as i32 + step[1]*i*forward;
+                    board.get_board()[col as usize][row as usize].is_some()
+                })
+                .collect();
+            match self.hop {
+                Hop::None => blocked.is_empty(),
+                Hop::Cannon => blocked.len() == 1,
+                Hop::Grasshopper => blocked == [count - 1],
+            }
+        })
+    }
+}
+// how many of the step it takes to go the distance, if it's on the line at all
+fn steps_to(dist: [i32; 2], step: [i32; 2]) -> Option<i32> {
+    let count = if step[0] != 0 {
+        dist[0] / step[0]
+    } else {
+        dist[1] / step[1]
+    };
+    if count >= 1 && step[0]*count == dist[0] && step[1]*count == dist[1] {
+        return Some(count);
+    }
+    None
+}
+
+// a piece read from the fairy pieces file
+#[derive(Debug, PartialEq)]
+pub struct FairyPiece {
+    name: String,
+    // the letter for FEN and the terminal
+    letter: char,
+    // white and black symbols, each one character so it fits in a save
+    symbols: [String; 2],
+    points: u8,
+    betza: String,
+    atoms: Vec<Atom>,
+}
+impl FairyPiece {
+    pub fn new(name: &str, letter: char, symbols: [&str; 2], points: u8, betza: &str) -> Result<Self, String> {
+        if !letter.is_ascii_uppercase() {
+            return Err(format!("{} needs an upper case letter, not {}", name, letter));
+        }
+        if symbols.iter().any(|symbol| symbol.chars().count() != 1) {
+            return Err(format!("each of {}'s symbols has to be one character", name));
+        }
+        Ok(FairyPiece {
+            name: String::from(name),
+            letter,
+            symbols: [String::from(symbols[0]), String::from(symbols[1])],
+            points,
+            betza: String::from(betza),
+            atoms: parse_betza(betza)?,
+        })
+    }
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    pub fn get_letter(&self) -> char {
+        self.letter
+    }
+    pub fn get_symbols(&self) -> [&str; 2] {
+        [&self.symbols[0], &self.symbols[1]]
+    }
+    pub fn get_points(&self) -> u8 {
+        self.points
+    }
+    pub fn get_betza(&self) -> &str {
+        &self.betza
+    }
+}
+
+// a fairy piece of one colour. Its moves come from its definition instead
+// of being written out like the usual pieces
+#[derive(Clone, Copy)]
+pub struct BetzaPiece {
+    id: u8,
+    definition: &'static FairyPiece,
+    is_white: bool,
+}
+impl BetzaPiece {
+    pub fn new(id: u8, is_white: bool) -> Option<Self> {
+        Some(BetzaPiece {
+            id,
+            definition: fairy_piece(id)?,
+            is_white,
+        })
+    }
+}
+impl ChessPiece for BetzaPiece {
+    fn test_space(
+        &self,
+        start: [usize; 2],
+        dest: [usize; 2],
+        dest_player: Option<bool>,
+        board: &mut ChessBoard) -> bool {
+        self.definition.atoms.iter().any(|atom| atom.test(start, dest, dest_player, self.is_white, board))
+    }
+    fn get_points(&self) -> u8 {
+        self.definition.points
+    }
+    fn get_piece_type(&self) -> PieceType {
+        PieceType::Fairy(self.id)
+    }
+    fn is_white(&self) -> bool {
+        self.is_white
+    }
+    fn get_symbol(&self, index: usize) -> &'static str {
+        &self.definition.symbols[index]
+    }
+}
+
+// the definition of a fairy piece from where it is in the list
+pub fn fairy_piece(id: u8) -> Option<&'static FairyPiece> {
+    FAIRY_PIECES.read().ok()?.get(id as usize).copied()
+}
+// the piece types of every fairy piece that's been added
+pub fn fairy_piece_types() -> Vec<PieceType> {
+    let count = FAIRY_PIECES.read().map_or(0, |pieces| pieces.len());
+    (0..count).map(|id| PieceType::Fairy(id as u8)).collect()
+}
+// add a fairy piece so it can be put on the board. Adding the same piece
+// again is fine, but a name that's taken can't be given different moves,
+// since pieces of that type might already be on a board
+pub fn add_fairy_piece(piece: FairyPiece) -> Result<PieceType, String> {
+    let mut pieces = FAIRY_PIECES.write().map_err(|_| String::from("the fairy pieces can't be changed"))?;
+    for kind in PieceType::ALL.iter() {
+        if kind.as_str() == piece.name {
+            return Err(format!("{} is already a piece", piece.name));
+        }
+        if kind.letter() == piece.letter {
+            return Err(format!("{} already stands for the {}", piece.letter, kind.as_str()));
+        }
+        if kind.symbols().iter().any(|symbol| piece.symbols.contains(&String::from(*symbol))) {
+            return Err(format!("{} uses the symbol of the {}", piece.name, kind.as_str()));
+        }
+    }
+    if let Some(id) = pieces.iter().position(|other| other.name == piece.name) {
+        if *pieces[id] != piece {
+            return Err(format!("{} is already a fairy piece with a different definition", piece.name));
+        }
+        return Ok(PieceType::Fairy(id as u8));
+    }
+    for other in pieces.iter() {
+        if other.letter == piece.letter {
+            return Err(format!("{} already stands for the {}", piece.letter, other.name));
+        }
+        if other.symbols.iter().any(|symbol| piece.symbols.contains(symbol)) {
+            return Err(format!("{} uses the symbol of the {}", piece.name, other.name));
+        }
+    }
+    if pieces.len() >= MAX_FAIRY_PIECES {
+        return Err(format!("there can't be more than {} fairy pieces", MAX_FAIRY_PIECES));
+    }
+    // definitions are never removed, so each one only has to be made once
+    pieces.push(Box::leak(Box::new(piece)));
+    Ok(PieceType::Fairy((pieces.len() - 1) as u8))
+}
+// read the fairy pieces file in the save directory and add everything in
+// it. Not having the file is fine, there just aren't any fairy pieces
+pub fn load_fairy_pieces() -> Result<Vec<PieceType>, String> {
+    let contents = match save::read_fairy_pieces() {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.to_string()),
+    };
+    parse_fairy_pieces(&contents)?.into_iter().map(add_fairy_piece).collect()
+}
+// each line of the file is a piece's name, letter, white and black symbols,
+// points and moves, like
+//   amazon M 🩎 🩑 12 QN
+// and blank lines or lines starting with # are skipped
+pub fn parse_fairy_pieces(contents: &str) -> Result<Vec<FairyPiece>, String> {
+    let mut pieces = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let piece = match fields[..] {
+            [name, letter, white, black, points, betza] => {
+                let mut letters = letter.chars();
+                let letter = match (letters.next(), letters.next()) {
+                    (Some(letter), None) => letter,
+                    _ => return Err(format!("line {}: {} isn't a single letter", number + 1, letter)),
+                };
+                let points = points.parse()
+                    .map_err(|_| format!("line {}: {} isn't a number of points", number + 1, points))?;
+                FairyPiece::new(name, letter, [white, black], points, betza)
+                    .map_err(|error| format!("line {}: {}", number + 1, error))?
+            },
+            _ => return Err(format!("line {}: a piece needs a name, letter, two symbols, points and moves", number + 1)),
+        };
+        pieces.push(piece);
+    }
+    Ok(pieces)
+}
+
+// read a piece's moves, like mRcpR for a cannon
+fn parse_betza(betza: &str) -> Result<Vec<Atom>, String> {
+    let mut atoms = Vec::new();
+    let mut chars = betza.chars().peekable();
+    while chars.peek().is_some() {
+        // the modifiers before the letter
+        let mut modifiers = String::new();
+        while let Some(modifier) = chars.next_if(char::is_ascii_lowercase) {
+            modifiers.push(modifier);
+        }
+        let letter = chars.next().ok_or_else(|| format!("{} is missing a piece letter at the end", betza))?;
+        // the leaps that make up the letter and if it's a rider to begin with
+        let (leaps, rider): (&[[i32; 2]], bool) = match letter {
+            'W' => (&[[1, 0]], false),
+            'F' => (&[[1, 1]], false),
+            'D' => (&[[2, 0]], false),
+            'N' => (&[[2, 1]], false),
+            'A' => (&[[2, 2]], false),
+            'H' => (&[[3, 0]], false),
+            'C' => (&[[3, 1]], false),
+            'Z' => (&[[3, 2]], false),
+            'G' => (&[[3, 3]], false),
+            'K' => (&[[1, 0], [1, 1]], false),
+            'R' => (&[[1, 0]], true),
+            'B' => (&[[1, 1]], true),
+            'Q' => (&[[1, 0], [1, 1]], true),
+            other => return Err(format!("{} isn't a Betza piece letter", other)),
+        };
+        // a doubled leaper rides, and a number sets how far it can go
+        let mut range = if rider {
+            0
+        } else {
+            1
+        };
+        if !rider && chars.next_if_eq(&letter).is_some() {
+            range = 0;
+        }
+        let mut digits = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            digits.push(digit);
+        }
+        if !digits.is_empty() {
+            range = digits.parse().map_err(|_| format!("{} is too far to move", digits))?;
+        }
+        let mut can_move = modifiers.contains('m');
+        let mut can_capture = modifiers.contains('c');
+        if !can_move && !can_capture {
+            can_move = true;
+            can_capture = true;
+        }
+        let hop = if modifiers.contains('g') {
+            Hop::Grasshopper
+        } else if modifiers.contains('p') {
+            Hop::Cannon
+        } else {
+            Hop::None
+        };
+        let directions = parse_directions(&modifiers)?;
+        for leap in leaps {
+            let steps: Vec<[i32; 2]> = symmetries(*leap)
+                .into_iter()
+                .filter(|step| directions.is_empty() || directions.iter().any(|direction| allows(direction, *step)))
+                .collect();
+            if !steps.is_empty() {
+                atoms.push(Atom {
+                    steps,
+                    range,
+                    can_move,
+                    can_capture,
+                    hop,
+                });
+            }
+        }
+    }
+    if atoms.is_empty() {
+        return Err(format!("{} doesn't give the piece any moves", betza));
+    }
+    Ok(atoms)
+}
+// split the direction letters into the single ones and the pairs
+fn parse_directions(modifiers: &str) -> Result<Vec<String>, String> {
+    let letters: Vec<char> = modifiers.chars().filter(|modifier| !"mcpg".contains(*modifier)).collect();
+    let mut directions = Vec::new();
+    let mut i = 0;
+    while i < letters.len() {
+        if !"fblrsv".contains(letters[i]) {
+            return Err(format!("{} isn't a Betza modifier", letters[i]));
+        }
+        let pair: String = letters[i..].iter().take(2).collect();
+        if pair.len() == 2 && allows_pair(&pair).is_some() {
+            directions.push(pair);
+            i += 2;
+        } else {
+            directions.push(letters[i].to_string());
+            i += 1;
+        }
+    }
+    Ok(directions)
+}
+// if a step goes in the direction, where forward is up the board
+fn allows(direction: &str, step: [i32; 2]) -> bool {
+    if let Some(allowed) = allows_pair(direction) {
+        return allowed(step);
+    }
+    let [x, y] = step;
+    match direction {
+        "f" => y > 0,
+        "b" => y < 0,
+        "l" => x < 0,
+        "r" => x > 0,
+        // more sideways than up or down
+        "s" => x.abs() > y.abs(),
+        // more up or down than sideways
+        "v" => y.abs() > x.abs(),
+        _ => false,
+    }
+}
+// the two letter directions
+fn allows_pair(pair: &str) -> Option<fn([i32; 2]) -> bool> {
+    let allowed: fn([i32; 2]) -> bool = match pair {
+        "fl" | "lf" => |[x, y]| y > 0 && x < 0,
+        "fr" | "rf" => |[x, y]| y > 0 && x > 0,
+        "bl" | "lb" => |[x, y]| y < 0 && x < 0,
+        "br" | "rb" => |[x, y]| y < 0 && x > 0,
+        // the narrow and wide halves of a direction, for oblique leapers
+        "ff" => |[x, y]| y > 0 && y.abs() > x.abs(),
+        "bb" => |[x, y]| y < 0 && y.abs() > x.abs(),
+        "ll" => |[x, y]| x < 0 && x.abs() > y.abs(),
+        "rr" => |[x, y]| x > 0 && x.abs() > y.abs(),
+        "fs" => |[x, y]| y > 0 && x.abs() > y.abs(),
+        "bs" => |[x, y]| y < 0 && x.abs() > y.abs(),
+        _ => return None,
+    };
+    Some(allowed)
+}
+// every way a leap can be turned and flipped
+fn symmetries(leap: [i32; 2]) -> Vec<[i32; 2]> {
+    let [a, b] = leap;
+    let mut steps = Vec::new();
+    for step in [[a, b], [b, a]].iter() {
+        for x_sign in [1, -1].iter() {
+            for y_sign in [1, -1].iter() {
+                let step = [step[0]*x_sign, step[1]*y_sign];
+                if !steps.contains(&step) {
+                    steps.push(step);
+                }
+            }
+        }
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::square_name;
+
+    fn square(name: &str) -> [usize; 2] {
+        let name = name.as_bytes();
+        [(name[0] - b'a') as usize, (name[1] - b'1') as usize]
+    }
+    // the squares a piece with the moves can go to from start, in order,
+    // with the pieces of a FEN placement on the board
+    fn targets(betza: &str, placement: &str, start: &str, is_white: bool) -> Vec<String> {
+        let atoms = parse_betza(betza).unwrap();
+        let mut board = ChessBoard::new();
+        board.load_fen(&format!("{} w - - 0 1", placement)).unwrap();
+        let start = square(start);
+        let mut squares = Vec::new();
+        for col in 0..8 {
+            for row in 0..8 {
+                let dest_player = board.get_board()[col][row].map(|piece| piece.is_white());
+                if [col, row] == start || dest_player == Some(is_white) {
+                    continue;
+                }
+                if atoms.iter().any(|atom| atom.test(start, [col, row], dest_player, is_white, &board)) {
+                    squares.push(square_name([col, row]));
+                }
+            }
+        }
+        squares.sort();
+        squares
+    }
+    // just the kings, out of the way in the corners
+    const KINGS: &str = "k7/8/8/8/8/8/8/7K";
+
+    #[test]
+    fn leapers() {
+        assert_eq!(targets("W", KINGS, "d4", true), ["c4", "d3", "d5", "e4"]);
+        assert_eq!(targets("F", KINGS, "d4", true), ["c3", "c5", "e3", "e5"]);
+        assert_eq!(targets("N", KINGS, "d4", true), ["b3", "b5", "c2", "c6", "e2", "e6", "f3", "f5"]);
+        // leapers jump over anything in the way
+        assert_eq!(targets("N", "k7/8/8/2ppp3/2pPp3/2ppp3/8/7K", "d4", true).len(), 8);
+    }
+
+    #[test]
+    fn riders() {
+        assert_eq!(targets("R", KINGS, "d4", true).len(), 14);
+        assert_eq!(targets("B", KINGS, "d4", true).len(), 13);
+        // a rider stops at the first piece, and can take it if it's the other side's
+        assert_eq!(targets("R", "k2r4/8/3P4/8/8/8/8/7K", "d1", true), ["a1", "b1", "c1", "d2", "d3", "d4", "d5", "e1", "f1", "g1"]);
+        // the nightrider keeps going in knight leaps
+        let nightrider = targets("NN", "k7/8/8/2p5/8/8/8/7K", "a1", true);
+        assert_eq!(nightrider, ["b3", "c2", "c5", "e3", "g4"]);
+    }
+
+    #[test]
+    fn range_limits() {
+        assert_eq!(targets("R2", KINGS, "d4", true), ["b4", "c4", "d2", "d3", "d5", "d6", "e4", "f4"]);
+        assert_eq!(targets("B1", KINGS, "d4", true), targets("F", KINGS, "d4", true));
+        assert_eq!(targets("NN2", KINGS, "a1", true), ["b3", "c2", "c5", "e3"]);
+    }
+
+    #[test]
+    fn move_and_capture_only() {
+        let placement = "k7/8/3p4/8/8/8/8/7K";
+        assert_eq!(targets("fmR", placement, "d4", true), ["d5"]);
+        assert_eq!(targets("fcR", placement, "d4", true), ["d6"]);
+        // pawns move forward and take diagonally
+        assert_eq!(targets("fmWfcF", "k7/8/8/4p3/8/8/8/7K", "d4", true), ["d5", "e5"]);
+    }
+
+    #[test]
+    fn hoppers() {
+        // a cannon needs exactly one piece to jump
+        let placement = "k2r4/8/3P4/8/8/8/8/7K";
+        assert_eq!(targets("pR", placement, "d4", true), ["d7", "d8"]);
+        assert_eq!(targets("mRcpR", placement, "d4", true), ["a4", "b4", "c4", "d1", "d2", "d3", "d5", "d8", "e4", "f4", "g4", "h4"]);
+        // a grasshopper lands right behind the piece it jumps
+        assert_eq!(targets("gQ", placement, "d4", true), ["d7"]);
+        assert!(targets("gQ", KINGS, "d4", true).is_empty());
+    }
+
+    #[test]
+    fn directions() {
+        assert_eq!(targets("fW", KINGS, "d4", true), ["d5"]);
+        // forward is down the board for black
+        assert_eq!(targets("fW", KINGS, "d4", false), ["d3"]);
+        assert_eq!(targets("bW", KINGS, "d4", true), ["d3"]);
+        assert_eq!(targets("lW", KINGS, "d4", true), ["c4"]);
+        assert_eq!(targets("rW", KINGS, "d4", true), ["e4"]);
+        assert_eq!(targets("vW", KINGS, "d4", true), ["d3", "d5"]);
+        assert_eq!(targets("sW", KINGS, "d4", true), ["c4", "e4"]);
+        assert_eq!(targets("flF", KINGS, "d4", true), ["c5"]);
+        assert_eq!(targets("ffN", KINGS, "d4", true), ["c6", "e6"]);
+        assert_eq!(targets("fsN", KINGS, "d4", true), ["b5", "f5"]);
+        assert_eq!(targets("bbN", KINGS, "d4", false), ["c6", "e6"]);
+    }
+
+    #[test]
+    fn bad_pieces_say_where() {
+        let error = |contents: &str| parse_fairy_pieces(contents).unwrap_err();
+        assert_eq!(error("# pieces\n\nbad M 🩎 🩑 12 QX"), "line 3: X isn't a Betza piece letter");
+        assert_eq!(error("bad M 🩎 🩑 12 xR"), "line 1: x isn't a Betza modifier");
+        assert_eq!(error("bad M 🩎 🩑 12 fm"), "line 1: fm is missing a piece letter at the end");
+        assert_eq!(error("ok M 🩎 🩑 12 Q\nbad MM 🩎 🩑 12 Q"), "line 2: MM isn't a single letter");
+        assert_eq!(error("ok M 🩎 🩑 12 Q\n\nbad M 🩎 🩑 lots Q"), "line 3: lots isn't a number of points");
+        assert_eq!(error("bad M 🩎 🩑 Q"), "line 1: a piece needs a name, letter, two symbols, points and moves");
+        assert_eq!(error("bad m 🩎 🩑 12 Q"), "line 1: bad needs an upper case letter, not m");
+        // diagonal steps are never more sideways than forward
+        assert_eq!(error("bad M 🩎 🩑 12 sF"), "line 1: sF doesn't give the piece any moves");
+    }
+
+    #[test]
+    fn pieces_keep_their_moves() {
+        let piece = |betza: &str| FairyPiece::new("wazir_test", 'Y', ["⍟", "⍣"], 1, betza).unwrap();
+        let wazir = add_fairy_piece(piece("W")).unwrap();
+        // adding it again is fine, changing it isn't
+        assert_eq!(add_fairy_piece(piece("W")), Ok(wazir));
+        assert!(add_fairy_piece(piece("F")).is_err());
+        assert_eq!(fairy_piece_types().iter().filter(|kind| **kind == wazir).count(), 1);
+        let wazir_id = match wazir {
+            PieceType::Fairy(id) => id,
+            _ => unreachable!(),
+        };
+        assert_eq!(fairy_piece(wazir_id).unwrap().get_betza(), "W");
+    }
+}
@@ -0,0 +1,28 @@
+use crate::event::GameEvent;
+use std::fmt;
+
+pub trait Game : fmt::Display {
+    // make a new game
+    fn new_game(&mut self);
+    // swap whose turn it is
+    fn switch_player(&mut self) {
+        self.set_player(!self.get_player());
+    }
+    // take the next turn, call switch player if move was valid
+    fn next_turn(&mut self, start: [usize; 2], dest: [usize; 2]) -> Option<Vec<GameEvent>> {
+        let events = self.take_turn(start, dest)?;
+        self.switch_player();
+        Some(events)
+    }
+    // take a turn of the game. returns what happened, or None if the move
+    // wasn't legal
+    fn take_turn(&mut self, start: [usize; 2], dest: [usize; 2]) -> Option<Vec<GameEvent>>;
+    // getter for which player's turn it is
+    fn get_player(&self) -> bool;
+    // setter for which player's turn it is
+    fn set_player(&mut self, player: bool);
+    // return the player who won if there is one
+    fn get_winner(&self) -> Option<i8>;
+    // represent the game score with a string
+    fn get_score(&self) -> String;
+}
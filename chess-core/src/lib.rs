@@ -0,0 +1,26 @@
+// rules of chess without any godot code, so it can be used and tested on its own
+pub mod ai;
+mod board;
+mod bughouse;
+mod chesspiece;
+pub mod database;
+mod event;
+mod fairy;
+mod game;
+mod odds;
+mod rules;
+pub mod save;
+mod variant;
+
+pub use board::{random_chess960_index, ChessBoard, PositionProblem, Squares};
+pub use bughouse::{BoardEvent, Bughouse};
+pub use chesspiece::{ChessPiece, Piece, PieceType};
+pub use event::{square_name, GameEvent, GameObserver, GameOverReason};
+pub use fairy::{add_fairy_piece, load_fairy_pieces, parse_fairy_pieces, BetzaPiece, FairyPiece};
+pub use game::Game;
+pub use odds::Odds;
+pub use rules::{
+    add_rule_set, rule_set, rule_set_names, standard_game_over, standard_promotions, variant_rules,
+    RuleSet, StandardRules,
+};
+pub use variant::Variant;
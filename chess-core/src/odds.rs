@@ -0,0 +1,35 @@
+// handicaps a stronger player can give by starting the game without something
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Odds {
+    #[default]
+    None,
+    // the f pawn, and the other player gets the first move
+    PawnAndMove,
+    // the knight nearest the a file
+    Knight,
+    // the rook nearest the a file, along with castling on that side
+    Rook,
+    Queen,
+}
+impl Odds {
+    pub const ALL: [Odds; 5] = [
+        Odds::None,
+        Odds::PawnAndMove,
+        Odds::Knight,
+        Odds::Rook,
+        Odds::Queen,
+    ];
+    // the name used for the odds in saves and by godot
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Odds::None => "none",
+            Odds::PawnAndMove => "pawn_and_move",
+            Odds::Knight => "knight",
+            Odds::Rook => "rook",
+            Odds::Queen => "queen",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Odds::ALL.iter().copied().find(|odds| odds.as_str() == name)
+    }
+}
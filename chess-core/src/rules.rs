@@ -0,0 +1,281 @@
+// the rules a game is played by. ChessBoard asks its rule set about
+// everything that changes from one variant to another, and every method
+// has the standard rules as its default, so a rule set only has to write
+// out what's different about it
+use std::sync::RwLock;
+use crate::{board::ChessBoard, chesspiece::*, event::GameOverReason, fairy, variant::Variant};
+
+pub use crate::board::variant_rules;
+
+// the back rank of a normal game, which is chess960 position 518
+pub const STANDARD_BACK_RANK: [PieceType; 8] = [
+    PieceType::Rook,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Queen,
+    PieceType::King,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Rook,
+];
+// rule sets added on top of the variants, by name. Boards keep a reference
+// to the rule set they're using, so they're never removed
+static RULE_SETS: RwLock<Vec<&'static dyn RuleSet>> = RwLock::new(Vec::new());
+
+pub trait RuleSet: Send + Sync {
+    // the name the rules are saved under and picked by in godot
+    fn name(&self) -> &str;
+    // the variant the rules build on, which decides the size of the board,
+    // what goes in the save and things like drops that are part of the board
+    fn variant(&self) -> Variant {
+        Variant::Standard
+    }
+    // the pieces on each player's first rank at the start, which is also
+    // how wide the board is
+    fn back_rank(&self) -> Vec<PieceType> {
+        STANDARD_BACK_RANK.to_vec()
+    }
+    // change the starting position once the back ranks and pawns are out
+    fn set_up(&self, _board: &mut ChessBoard) {}
+    // test a move the piece is able to make against everything else, which
+    // is usually just not leaving its own king in check
+    fn is_legal(&self, board: &mut ChessBoard, start: [usize; 2], dest: [usize; 2], piece: Piece) -> bool {
+        !board.test_check(start, dest, piece.is_white())
+    }
+    // if a player has to take something whenever they can
+    fn must_capture(&self) -> bool {
+        false
+    }
+    // if a player can be told they're in check
+    fn has_check(&self) -> bool {
+        true
+    }
+    // if the player's king would be in check on space, which is whenever
+    // the other player could take it there
+    fn is_king_attacked(&self, board: &mut ChessBoard, space: [usize; 2], is_white: bool) -> bool {
+        board.is_threatened(space, is_white)
+    }
+    // if kings can take things
+    fn kings_can_capture(&self) -> bool {
+        true
+    }
+    // if the player starts with a king
+    fn has_king(&self, _is_white: bool) -> bool {
+        true
+    }
+    // if kings can castle
+    fn can_castle(&self) -> bool {
+        true
+    }
+    // if the player's pawns can be on their first rank, where they can
+    // move two squares the same as from the second
+    fn first_rank_pawns(&self, _is_white: bool) -> bool {
+        false
+    }
+    // if a pawn that moved two squares from start can be taken en passant
+    fn en_passant_after(&self, _start: [usize; 2], _is_white: bool) -> bool {
+        true
+    }
+    // if the player who's stalemated wins instead of it being a draw
+    fn stalemate_wins(&self) -> bool {
+        false
+    }
+    // if captured pieces go to the partner on the other board instead of
+    // the capturing player's reserve, so a piece to block check with can
+    // still turn up
+    fn passes_captures(&self) -> bool {
+        false
+    }
+    // anything else that happens when a piece takes something. Returning
+    // the log of what else was taken removes the capturing piece as well,
+    // None leaves it where it landed
+    fn after_capture(&self, _board: &mut ChessBoard, _start: [usize; 2], _dest: [usize; 2]) -> Option<String> {
+        None
+    }
+    // the winner and the reason if the game is over after is_white's move
+    fn game_over(&self, board: &mut ChessBoard, is_white: bool) -> Option<(i8, GameOverReason)> {
+        standard_game_over(board, is_white)
+    }
+    // if pawns can turn into the pieces from the fairy pieces file. Fairy
+    // pieces aren't part of any variant, so a rule set has to ask for them
+    fn fairy_promotions(&self) -> bool {
+        false
+    }
+    // the pieces a pawn can turn into
+    fn promotion_options(&self) -> Vec<PieceType> {
+        let mut options = standard_promotions();
+        if self.fairy_promotions() {
+            options.extend(fairy::fairy_piece_types());
+        }
+        options
+    }
+    // if players have a reserve of pieces they can drop onto the board
+    fn has_drops(&self) -> bool {
+        false
+    }
+    // how many lines save_lines adds to the end of each turn in the save
+    fn extra_save_lines(&self) -> usize {
+        0
+    }
+    // the lines that go after the board for every turn in the save, each
+    // starting with a name. The save reads back "checks", "reserve" and
+    // "promoted" lines, and skips anything else
+    fn save_lines(&self, _board: &ChessBoard) -> String {
+        String::new()
+    }
+}
+
+// normal chess, which is what everything does unless its rules say otherwise
+pub struct StandardRules;
+impl RuleSet for StandardRules {
+    fn name(&self) -> &str {
+        Variant::Standard.as_str()
+    }
+}
+
+// checkmate wins and having no moves is a draw
+pub fn standard_game_over(board: &mut ChessBoard, is_white: bool) -> Option<(i8, GameOverReason)> {
+    if !board.test_stalemate(!is_white) {
+        return None;
+    }
+    if board.test_checkmate(!is_white) {
+        Some((winner(is_white), GameOverReason::Checkmate))
+    } else {
+        Some((0, GameOverReason::Stalemate))
+    }
+}
+// the usual pieces to promote to
+pub fn standard_promotions() -> Vec<PieceType> {
+    vec![PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight]
+}
+fn winner(is_white: bool) -> i8 {
+    if is_white {
+        1
+    } else {
+        -1
+    }
+}
+
+// find a rule set by name, out of the variants and anything that's been added
+pub fn rule_set(name: &str) -> Option<&'static dyn RuleSet> {
+    if let Some(variant) = Variant::from_name(name) {
+        return Some(variant_rules(variant));
+    }
+    RULE_SETS.read().ok()?.iter().copied().find(|rules| rules.name() == name)
+}
+// the names of every rule set that can be picked
+pub fn rule_set_names() -> Vec<String> {
+    let mut names: Vec<String> = Variant::ALL.iter().map(|variant| String::from(variant.as_str())).collect();
+    if let Ok(rule_sets) = RULE_SETS.read() {
+        names.extend(rule_sets.iter().map(|rules| String::from(rules.name())));
+    }
+    names
+}
+// add a rule set so it can be picked by name. One with the same name as
+// a rule set that was added before replaces it
+pub fn add_rule_set(rules: Box<dyn RuleSet>) -> Result<&'static dyn RuleSet, String> {
+    if Variant::from_name(rules.name()).is_some() {
+        return Err(format!("{} is already a variant", rules.name()));
+    }
+    let mut rule_sets = RULE_SETS.write().map_err(|_| String::from("the rule sets can't be changed"))?;
+    let rules: &'static dyn RuleSet = Box::leak(rules);
+    match rule_sets.iter().position(|other| other.name() == rules.name()) {
+        Some(index) => rule_sets[index] = rules,
+        None => rule_sets.push(rules),
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{add_fairy_piece, game::Game, parse_fairy_pieces, GameEvent};
+
+    struct FairyRules;
+    impl RuleSet for FairyRules {
+        fn name(&self) -> &str {
+            "fairy promotions"
+        }
+        fn fairy_promotions(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn fairy_promotions_are_opt_in() {
+        let piece = parse_fairy_pieces("grasshopper G 🩔 🩕 2 gQ").unwrap().remove(0);
+        let grasshopper = add_fairy_piece(piece).unwrap();
+        for variant in Variant::ALL.iter() {
+            assert!(!variant_rules(*variant).promotion_options().contains(&grasshopper));
+        }
+        assert!(FairyRules.promotion_options().contains(&grasshopper));
+        assert!(FairyRules.promotion_options().starts_with(&standard_promotions()));
+    }
+
+    // play moves in a variant from a FEN, and see how the game ended
+    fn play(variant: Variant, fen: &str, moves: &[([usize; 2], [usize; 2])]) -> Option<(i8, GameOverReason)> {
+        let mut board = ChessBoard::new();
+        board.set_variant(variant);
+        board.new_game();
+        board.load_fen(fen).unwrap();
+        let mut ending = None;
+        for (start, dest) in moves.iter() {
+            assert!(ending.is_none());
+            for event in board.next_turn(*start, *dest).unwrap() {
+                if let GameEvent::GameOver { winner, reason } = event {
+                    ending = Some((winner, reason));
+                }
+            }
+        }
+        ending
+    }
+
+    #[test]
+    fn every_variant_can_end() {
+        assert_eq!(play(Variant::Standard, "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &[([0, 0], [0, 7])]),
+            Some((1, GameOverReason::Checkmate)));
+        assert_eq!(play(Variant::Standard, "k7/8/8/8/8/8/8/2Q4K w - - 0 1", &[([2, 0], [2, 6])]),
+            Some((0, GameOverReason::Stalemate)));
+        assert_eq!(play(Variant::KingOfTheHill, "k7/8/8/8/8/4K3/8/8 w - - 0 1", &[([4, 2], [4, 3])]),
+            Some((1, GameOverReason::KingOfTheHill)));
+        let checks = [([1, 0], [1, 7]), ([7, 7], [7, 6]), ([1, 7], [1, 6]), ([7, 6], [7, 5]), ([1, 6], [1, 5])];
+        assert_eq!(play(Variant::ThreeCheck, "7k/8/8/8/8/8/8/KR6 w - - 0 1", &checks),
+            Some((1, GameOverReason::ThreeCheck)));
+        assert_eq!(play(Variant::Atomic, "k7/1p6/8/8/8/8/8/1R5K w - - 0 1", &[([1, 0], [1, 6])]),
+            Some((1, GameOverReason::KingExploded)));
+        // losing every piece wins antichess
+        assert_eq!(play(Variant::Antichess, "8/8/8/8/8/8/8/R6r b - - 0 1", &[([7, 0], [0, 0])]),
+            Some((1, GameOverReason::NoPiecesLeft)));
+        assert_eq!(play(Variant::Horde, "k7/8/8/8/8/8/P7/r7 b - - 0 1", &[([0, 0], [0, 1])]),
+            Some((-1, GameOverReason::HordeCaptured)));
+        // black's king is too far back to tie
+        assert_eq!(play(Variant::RacingKings, "8/6K1/8/8/8/8/8/k7 w - - 0 1", &[([6, 6], [6, 7])]),
+            Some((1, GameOverReason::RacingKings)));
+    }
+
+    #[test]
+    fn variant_hooks() {
+        // no castling in antichess
+        let mut board = ChessBoard::new();
+        board.set_variant(Variant::Antichess);
+        board.new_game();
+        board.load_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(board.find_castle([4, 0], [6, 0], true).is_none());
+        assert!(board.next_turn([4, 0], [6, 0]).is_none());
+        // the horde's pawns double step from the first rank, but can't be
+        // taken en passant from there
+        let mut board = ChessBoard::new();
+        board.set_variant(Variant::Horde);
+        board.new_game();
+        board.load_fen("k7/8/8/8/8/8/8/P7 w - - 0 1").unwrap();
+        board.next_turn([0, 0], [0, 2]).unwrap();
+        assert_eq!(board.get_white_en_passant(), None);
+        assert!(board.validate_position().is_empty());
+        // touching kings are safe in atomic chess
+        let mut board = ChessBoard::new();
+        board.set_variant(Variant::Atomic);
+        board.new_game();
+        board.load_fen("8/8/8/8/8/8/1k6/K6r w - - 0 1").unwrap();
+        assert!(!board.is_king_attacked([0, 0], true));
+    }
+}
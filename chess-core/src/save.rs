@@ -0,0 +1,545 @@
+// reading and writing saved games in the data directory
+use crate::database;
+use serde::{Deserialize, Serialize};
+use std::{env, error::Error, fmt, fs::{self, File}, io::{self, prelude::*}, path::{Component, Path, PathBuf}, sync::RwLock, time::{SystemTime, UNIX_EPOCH}};
+
+// fairy pieces are defined in this file in the data dir, it isn't a save
+pub const FAIRY_PIECES_FILE: &str = "fairy_pieces.txt";
+// the game being played is kept in this file in the data dir after every
+// move, so it can be restored if the game crashes. It starts with a dot so
+// it isn't listed with the saves
+pub const RECOVERY_FILE: &str = ".recovery.json";
+// the index the game database searches, which is kept up to date as games
+// are saved and deleted
+pub const INDEX_FILE: &str = ".index.json";
+// every save starts by saying what it is and which version of the format
+// it was written in, so older saves can still be read after it changes
+pub const SAVE_FORMAT: &str = "dexter_chess_save";
+pub const SAVE_VERSION: u32 = 1;
+
+// why a save couldn't be loaded. Turns are counted from 0, like load_turn
+// counts them, but are shown counting from 1
+#[derive(Debug)]
+pub enum LoadError {
+    // the file couldn't be read, or an old save couldn't be written back
+    Io(io::Error),
+    // the save isn't JSON, or is a kind or version of save this game can't read
+    BadFormat(String),
+    // a turn with fewer lines than every turn has
+    TruncatedTurn { turn: usize },
+    BadScoreLine { turn: usize, line: String },
+    UnknownPiece { turn: usize, glyph: char },
+    // the save can be read but doesn't add up, like a variant that doesn't
+    // exist or a last position that can't be played from
+    InconsistentState(String),
+}
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "{}", e),
+            LoadError::BadFormat(message) => write!(f, "the save can't be read: {}", message),
+            LoadError::TruncatedTurn { turn } => write!(f, "turn {} is cut off", turn + 1),
+            LoadError::BadScoreLine { turn, line } => {
+                write!(f, "\"{}\" in turn {} isn't a score", line, turn + 1)
+            },
+            LoadError::UnknownPiece { turn, glyph } => {
+                write!(f, "{} in turn {} isn't a piece", glyph, turn + 1)
+            },
+            LoadError::InconsistentState(message) => write!(f, "the save doesn't add up: {}", message),
+        }
+    }
+}
+impl Error for LoadError {}
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+// a saved game as it's written to a file, as JSON. The game is played
+// back from its moves when it's loaded. Saves from before there was a
+// version are plain text and get turned into this when they're loaded
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub header: SaveHeader,
+    pub metadata: SaveMetadata,
+    pub moves: Vec<SavedMove>,
+}
+impl SaveFile {
+    // if the contents of a save are in this format rather than the old one
+    pub fn is_save_file(contents: &str) -> bool {
+        contents.trim_start().starts_with('{')
+    }
+    // read a save, as long as it's a version this game knows how to read
+    pub fn from_json(contents: &str) -> Result<Self, LoadError> {
+        let bad_format = |e: serde_json::Error| LoadError::BadFormat(e.to_string());
+        let header: VersionCheck = serde_json::from_str(contents).map_err(bad_format)?;
+        if header.header.format != SAVE_FORMAT {
+            return Err(LoadError::BadFormat(format!("{} isn't a kind of save", header.header.format)));
+        }
+        if header.header.version > SAVE_VERSION {
+            return Err(LoadError::BadFormat(format!(
+                "it's version {}, which is newer than this game", header.header.version)));
+        }
+        serde_json::from_str(contents).map_err(bad_format)
+    }
+    pub fn to_json(&self) -> String {
+        // nothing in a save can fail to be written as JSON
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+// just the header, so the version can be checked before reading the rest
+#[derive(Deserialize)]
+struct VersionCheck {
+    header: SaveHeader,
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveHeader {
+    pub format: String,
+    pub version: u32,
+}
+impl Default for SaveHeader {
+    fn default() -> Self {
+        SaveHeader {
+            format: String::from(SAVE_FORMAT),
+            version: SAVE_VERSION,
+        }
+    }
+}
+// who played the game, when, what it was played with and how it went
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SaveMetadata {
+    // the players' names, which are empty if nobody gave them
+    #[serde(default)]
+    pub white: String,
+    #[serde(default)]
+    pub black: String,
+    #[serde(default)]
+    pub event: String,
+    // the day the game was first saved, like 2020-05-17
+    #[serde(default)]
+    pub date: String,
+    // 1-0, 0-1, 1/2-1/2 or * for a game that isn't over, like in PGN
+    #[serde(default = "unfinished")]
+    pub result: String,
+    // the reason the game ended, as godot gets it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub termination: Option<String>,
+    // how many moves both players made between them
+    #[serde(default)]
+    pub move_count: usize,
+    pub variant: String,
+    pub rules: String,
+    // the odds given and who gave them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub odds: Option<SavedOdds>,
+    // the milliseconds white and black started with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_control: Option<[u64; 2]>,
+    // the position the game started from, which old saves don't have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_fen: Option<String>,
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedOdds {
+    pub odds: String,
+    pub giver_is_white: bool,
+}
+fn unfinished() -> String {
+    String::from(result_string(None))
+}
+// the result of a game the way PGN writes it, from the winner
+pub fn result_string(winner: Option<i8>) -> &'static str {
+    match winner {
+        Some(1) => "1-0",
+        Some(-1) => "0-1",
+        Some(_) => "1/2-1/2",
+        None => "*",
+    }
+}
+pub fn parse_result(result: &str) -> Option<i8> {
+    match result {
+        "1-0" => Some(1),
+        "0-1" => Some(-1),
+        "1/2-1/2" => Some(0),
+        _ => None,
+    }
+}
+// a move as the player made it, with squares written like e4 and pieces by
+// name. Drops don't have a square they came from
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedMove {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    pub to: String,
+    // what a pawn that reached the last rank turned into
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub promotion: Option<String>,
+    // the piece dropped from the reserve
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drop: Option<String>,
+}
+// a move and the position it left behind, so any turn can be loaded on its
+// own. This is how a game's turns are kept while it's played
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SavedTurn {
+    // the move the way it's shown in the log, with anything it captured
+    // and how it ended the game
+    pub log: String,
+    // white first for everything that's for both players
+    pub score: [u8; 2],
+    pub captured: [String; 2],
+    // the files of the rooks each player can still castle with
+    pub castle_rooks: [[Option<usize>; 2]; 2],
+    // the file of the pawn each player just moved two squares, if they did
+    pub en_passant: [Option<usize>; 2],
+    // the pieces as the first field of a FEN
+    pub board: String,
+    // checks given in three-check
+    pub checks: Option<[u8; 2]>,
+    // the reserves in crazyhouse and bughouse
+    pub reserves: Option<[[u8; 5]; 2]>,
+    // the squares of pieces that got there by promotion, for crazyhouse
+    pub promoted: Vec<String>,
+}
+
+// set this environment variable to keep saves somewhere other than the
+// platform's data dir
+pub const SAVE_DIR_VAR: &str = "DEXTER_CHESS_SAVE_DIR";
+// each profile has its own directory in here, with its own saves, recovery
+// file and fairy pieces
+const PROFILES_DIR: &str = "profiles";
+// the longest a save or profile name can be, which keeps file names well
+// under what file systems allow even once they're escaped
+pub const MAX_SAVE_NAME_LEN: usize = 100;
+// the profile whose saves are being used, if any
+static PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+// get the path to where saves are stored (platform dependant), which is
+// the profile's own directory when there is one
+pub fn get_data_dir() -> Option<PathBuf> {
+    let base = match env::var_os(SAVE_DIR_VAR).filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::data_dir()?.join("dexter_chess_saves/"),
+    };
+    match get_profile() {
+        Some(profile) => Some(base.join(PROFILES_DIR).join(escape_name(&profile))),
+        None => Some(base),
+    }
+}
+// the data dir, or an error if the platform doesn't have one
+fn data_dir() -> io::Result<PathBuf> {
+    get_data_dir().ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound, "no data directory on this platform"))
+}
+// use the saves of a profile from now on, or the ones outside of any
+// profile with None
+pub fn set_profile(profile: Option<&str>) -> Result<(), String> {
+    if let Some(profile) = profile {
+        check_name(profile, "profile")?;
+    }
+    let mut current = PROFILE.write().unwrap_or_else(|e| e.into_inner());
+    *current = profile.map(String::from);
+    Ok(())
+}
+pub fn get_profile() -> Option<String> {
+    PROFILE.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+// check a name the player typed in can be used for a save
+pub fn check_save_name(save_name: &str) -> Result<(), String> {
+    check_name(save_name, "save")?;
+    // the file with the fairy pieces is in the same directory
+    if save_name == FAIRY_PIECES_FILE {
+        return Err(format!("{} is used for the fairy pieces", FAIRY_PIECES_FILE));
+    }
+    // and so are the profiles
+    if save_name == PROFILES_DIR {
+        return Err(format!("{} is used for the profiles", PROFILES_DIR));
+    }
+    Ok(())
+}
+fn check_name(name: &str, what: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err(format!("a {} needs a name", what));
+    }
+    if name.chars().count() > MAX_SAVE_NAME_LEN {
+        return Err(format!("{} names can't be longer than {} characters", what, MAX_SAVE_NAME_LEN));
+    }
+    if name.chars().any(char::is_control) {
+        return Err(format!("{} names can't have control characters", what));
+    }
+    Ok(())
+}
+// the file name a save is kept under. Anything that could lead out of the
+// save directory or that some platforms don't allow in file names is
+// written as % and its code, like %2F for /
+fn escape_name(name: &str) -> String {
+    let last = name.chars().count().saturating_sub(1);
+    // windows keeps these names for devices, even with an extension
+    let stem = name.split('.').next().unwrap_or_default().to_ascii_uppercase();
+    let device = ["CON", "PRN", "AUX", "NUL"].contains(&stem.as_str())
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem.ends_with(|c: char| c.is_ascii_digit()));
+    let mut escaped = String::new();
+    for (i, c) in name.chars().enumerate() {
+        let escape = "/\\:*?\"<>|%".contains(c)
+            // hidden files, . and ..
+            || (i == 0 && c == '.')
+            || (i == 0 && device)
+            // windows drops these from the end of a name
+            || (i == last && (c == '.' || c == ' '));
+        if escape {
+            escaped.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+// the save name a file name was escaped from
+fn unescape_name(file_name: &str) -> String {
+    let mut name = String::new();
+    let mut chars = file_name.chars();
+    while let Some(c) = chars.next() {
+        let code = chars.as_str()
+            .get(..2)
+            .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match code {
+            Some(code) if c == '%' => {
+                name.push(code as char);
+                chars.nth(1);
+            },
+            _ => name.push(c),
+        }
+    }
+    name
+}
+// the path of a save, which is always a file right in the save directory
+fn save_path(save_name: &str) -> io::Result<PathBuf> {
+    check_save_name(save_name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let file_name = escape_name(save_name);
+    // escaping should already make sure of this
+    let mut components = Path::new(&file_name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(data_dir()?.join(file_name)),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} can't be a save name", save_name))),
+    }
+}
+// write a file by writing a temporary file next to it and renaming that
+// over it, so a crash partway through never leaves half a file
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let data_dir = data_dir()?;
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir)?;
+    }
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+    let written = File::create(&temp_path).and_then(|mut temp_file| {
+        temp_file.write_all(contents.as_bytes())?;
+        temp_file.sync_all()
+    });
+    if let Err(e) = written {
+        fs::remove_file(&temp_path).ok();
+        return Err(e);
+    }
+    fs::rename(&temp_path, path)
+}
+fn read_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+// write a save to a file in the data dir
+pub fn write_save(save_name: &str, contents: &str) -> io::Result<()> {
+    write_atomic(&save_path(save_name)?, contents)?;
+    // the database catches up the next time it's opened if this fails
+    database::save_written(save_name).ok();
+    Ok(())
+}
+// read the contents of a save
+pub fn read_save(save_name: &str) -> io::Result<String> {
+    read_file(&save_path(save_name)?)
+}
+// delete a saved game
+pub fn delete_save(save_name: &str) -> io::Result<()> {
+    fs::remove_file(save_path(save_name)?)?;
+    database::save_deleted(save_name).ok();
+    Ok(())
+}
+// when a save was last written, in seconds since 1970
+pub fn save_modified(save_name: &str) -> io::Result<u64> {
+    let modified = fs::metadata(save_path(save_name)?)?.modified()?;
+    Ok(modified.duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0))
+}
+// the game database's index
+pub(crate) fn write_index(contents: &str) -> io::Result<()> {
+    write_atomic(&data_dir()?.join(INDEX_FILE), contents)
+}
+pub(crate) fn read_index() -> io::Result<String> {
+    read_file(&data_dir()?.join(INDEX_FILE))
+}
+// read the fairy pieces file, which is kept with the saves
+pub fn read_fairy_pieces() -> io::Result<String> {
+    read_file(&data_dir()?.join(FAIRY_PIECES_FILE))
+}
+// keep the game being played in the recovery file
+pub fn write_recovery(contents: &str) -> io::Result<()> {
+    write_atomic(&data_dir()?.join(RECOVERY_FILE), contents)
+}
+pub fn read_recovery() -> io::Result<String> {
+    read_file(&data_dir()?.join(RECOVERY_FILE))
+}
+// check if there's an unfinished game to offer to restore
+pub fn recovery_exists() -> bool {
+    get_data_dir().is_some_and(|dir| dir.join(RECOVERY_FILE).exists())
+}
+// there's nothing to clear if no game has been kept
+pub fn clear_recovery() -> io::Result<()> {
+    match fs::remove_file(data_dir()?.join(RECOVERY_FILE)) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+// what's known about every saved game, by name. Old saves that haven't
+// been loaded since the format changed only have their variant
+pub fn save_summaries() -> Vec<(String, SaveMetadata)> {
+    save_names()
+        .into_iter()
+        .filter_map(|name| {
+            let contents = read_save(&name).ok()?;
+            let metadata = if SaveFile::is_save_file(&contents) {
+                SaveFile::from_json(&contents).ok()?.metadata
+            } else {
+                let variant = contents.lines()
+                    .find_map(|line| line.strip_prefix("# variant "))
+                    .unwrap_or("standard");
+                SaveMetadata {
+                    result: unfinished(),
+                    variant: String::from(variant),
+                    rules: String::from(variant),
+                    ..SaveMetadata::default()
+                }
+            };
+            Some((name, metadata))
+        })
+        .collect()
+}
+// today's date like 2020-05-17, in UTC
+pub fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+    // count days from 0000-03-01 so leap days come at the end of the year
+    let days = seconds/86_400 + 719_468;
+    let era = days/146_097;
+    let day_of_era = days - era*146_097;
+    let year_of_era = (day_of_era - day_of_era/1460 + day_of_era/36_524 - day_of_era/146_096)/365;
+    let day_of_year = day_of_era - (365*year_of_era + year_of_era/4 - year_of_era/100);
+    let month_from_march = (5*day_of_year + 2)/153;
+    let day = day_of_year - (153*month_from_march + 2)/5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era*400 + if month <= 2 {
+        1
+    } else {
+        0
+    };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+// check if a save exists so the user can be asked whether to overwrite it
+pub fn save_exists(save_name: &str) -> bool {
+    save_path(save_name).is_ok_and(|path| path.exists())
+}
+// the names of the available saved games
+pub fn save_names() -> Vec<String> {
+    let mut names = Vec::new();
+    let save_dir = if let Some(dir) = get_data_dir() {
+        dir
+    } else {
+        return names;
+    };
+    if let Ok(entries) = save_dir.read_dir() {
+        for entry in entries.flatten() {
+            let name = match save_entry_name(&entry) {
+                Some((name, file_name)) if escape_name(&name) == file_name => name,
+                _ => continue,
+            };
+            names.push(name);
+        }
+    }
+    names
+}
+// saves from before names were escaped get moved to the file they'd be
+// saved in now, as long as that isn't taken. This only has to happen once
+// for each save directory, when the game starts or a profile is picked
+pub fn migrate_save_names() {
+    let save_dir = match get_data_dir() {
+        Some(dir) => dir,
+        None => return,
+    };
+    if let Ok(entries) = save_dir.read_dir() {
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().into_string().ok();
+            if let Some(new_name) = file_name.as_deref().and_then(migrated_name) {
+                let path = save_dir.join(new_name);
+                if save_entry_name(&entry).is_some() && !path.exists() {
+                    fs::rename(entry.path(), path).ok();
+                }
+            }
+        }
+    }
+}
+// the file an old save should be moved to, if it needs moving. Anything
+// with an escape in it has already been escaped, even if it might be an old
+// name with a % in it, since there's no telling the two apart
+fn migrated_name(file_name: &str) -> Option<String> {
+    if unescape_name(file_name) != file_name {
+        return None;
+    }
+    check_save_name(file_name).ok()?;
+    Some(escape_name(file_name)).filter(|escaped| escaped != file_name)
+}
+// the name of the save in a file in the save directory and the file's name,
+// if it's a save
+fn save_entry_name(entry: &fs::DirEntry) -> Option<(String, String)> {
+    // the profiles directory is in here too
+    if !entry.file_type().is_ok_and(|file_type| file_type.is_file()) {
+        return None;
+    }
+    // files starting with a dot are the recovery file, the index and ones
+    // that are still being written
+    let file_name = entry.file_name().into_string().ok()
+        .filter(|file_name| file_name != FAIRY_PIECES_FILE && !file_name.starts_with('.'))?;
+    let name = unescape_name(&file_name);
+    check_save_name(&name).ok()?;
+    Some((name, file_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_save_names_are_escaped_once() {
+        // names that need escaping now get moved
+        assert_eq!(migrated_name("100%").as_deref(), Some("100%25"));
+        assert_eq!(migrated_name("what?").as_deref(), Some("what%3F"));
+        assert_eq!(migrated_name("done.").as_deref(), Some("done%2E"));
+        // names that don't stay where they are
+        assert_eq!(migrated_name("my game"), None);
+        // and anything that's already escaped is left alone, even if it
+        // could be an old save with a % in its name
+        assert_eq!(migrated_name("100%25"), None);
+        assert_eq!(migrated_name("foo%41"), None);
+        assert_eq!(unescape_name("foo%41"), "fooA");
+        // but a % that isn't an escape gets escaped
+        assert_eq!(migrated_name("50%off").as_deref(), Some("50%25off"));
+    }
+}
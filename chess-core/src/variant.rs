@@ -0,0 +1,67 @@
+// the different sets of rules a game can be played with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Variant {
+    #[default]
+    Standard,
+    // a king reaching one of the four centre squares wins
+    KingOfTheHill,
+    // giving check three times wins
+    ThreeCheck,
+    // captures blow up everything around them except pawns
+    Atomic,
+    // captured pieces can be dropped back on the board by the player who took them
+    Crazyhouse,
+    // captures are forced and the first player to lose everything wins
+    Antichess,
+    // white has a horde of pawns and no king, black has to take them all
+    Horde,
+    // no checks allowed, the first king to the eighth rank wins
+    RacingKings,
+    // one board of a bughouse match, captures go to the partner on the other board
+    Bughouse,
+    // a 10x8 board with an archbishop and a chancellor between the bishops and knights
+    Capablanca,
+    // capablanca chess with the pieces rearranged
+    Gothic,
+}
+impl Variant {
+    pub const ALL: [Variant; 11] = [
+        Variant::Standard,
+        Variant::KingOfTheHill,
+        Variant::ThreeCheck,
+        Variant::Atomic,
+        Variant::Crazyhouse,
+        Variant::Antichess,
+        Variant::Horde,
+        Variant::RacingKings,
+        Variant::Bughouse,
+        Variant::Capablanca,
+        Variant::Gothic,
+    ];
+    // the name used for the variant in saves and by godot
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Variant::Standard => "standard",
+            Variant::KingOfTheHill => "king_of_the_hill",
+            Variant::ThreeCheck => "three_check",
+            Variant::Atomic => "atomic",
+            Variant::Crazyhouse => "crazyhouse",
+            Variant::Antichess => "antichess",
+            Variant::Horde => "horde",
+            Variant::RacingKings => "racing_kings",
+            Variant::Bughouse => "bughouse",
+            Variant::Capablanca => "capablanca",
+            Variant::Gothic => "gothic",
+        }
+    }
+    // how many files and ranks the board has
+    pub fn size(self) -> [usize; 2] {
+        match self {
+            Variant::Capablanca | Variant::Gothic => [10, 8],
+            _ => [8, 8],
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Variant::ALL.iter().copied().find(|variant| variant.as_str() == name)
+    }
+}
@@ -0,0 +1,133 @@
+use chess_core::PieceType;
+
+// everything a player can type on their turn
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    // move a piece, with the piece to promote to if the move needs one
+    Move([usize; 2], [usize; 2], Option<PieceType>),
+    // drop a piece from the reserve in crazyhouse
+    Drop(PieceType, [usize; 2]),
+    Undo,
+    Resign,
+    Draw,
+    Save(String),
+    Load(String),
+    Help,
+    Quit,
+}
+
+// turn a line of input into a command. Moves can be written like
+// "e2 e4", "e2e4" or "e2-e4", with an optional promotion letter at the end,
+// and crazyhouse drops like "N@e4"
+pub fn parse_command(input: &str) -> Option<Command> {
+    let input = input.trim();
+    let mut words = input.splitn(2, ' ');
+    let first = words.next()?.to_lowercase();
+    let rest = words.next().map(|rest| rest.trim().to_string());
+    match (first.as_str(), rest) {
+        ("undo", None) => return Some(Command::Undo),
+        ("resign", None) => return Some(Command::Resign),
+        ("draw", None) => return Some(Command::Draw),
+        ("help", None) => return Some(Command::Help),
+        ("quit", None) | ("exit", None) => return Some(Command::Quit),
+        ("save", Some(name)) => return Some(Command::Save(name)),
+        ("load", Some(name)) => return Some(Command::Load(name)),
+        _ => {},
+    }
+    // drops are written like N@e4
+    let chars: Vec<char> = input.chars().collect();
+    if let [piece, '@', file, rank] = chars[..] {
+        let kind = match piece.to_ascii_lowercase() {
+            'p' => PieceType::Pawn,
+            letter => parse_promotion(letter)?,
+        };
+        return Some(Command::Drop(kind, parse_square(file, rank)?));
+    }
+    let squares: Vec<char> = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect();
+    if squares.len() != 4 && squares.len() != 5 {
+        return None;
+    }
+    let start = parse_square(squares[0], squares[1])?;
+    let dest = parse_square(squares[2], squares[3])?;
+    let promotion = match squares.get(4) {
+        Some(letter) => Some(parse_promotion(*letter)?),
+        None => None,
+    };
+    Some(Command::Move(start, dest, promotion))
+}
+
+// read a square like e4. Files go up to l for the wider boards, the board
+// checks the square is actually on it
+fn parse_square(file: char, rank: char) -> Option<[usize; 2]> {
+    let file = file.to_ascii_lowercase();
+    if ('a'..='l').contains(&file) && ('1'..='9').contains(&rank) {
+        return Some([file as usize - 'a' as usize, rank as usize - '1' as usize]);
+    }
+    None
+}
+
+// read the letter of the piece a pawn should promote to
+pub fn parse_promotion(letter: char) -> Option<PieceType> {
+    match letter.to_ascii_lowercase() {
+        'q' => Some(PieceType::Queen),
+        'r' => Some(PieceType::Rook),
+        'b' => Some(PieceType::Bishop),
+        'n' => Some(PieceType::Knight),
+        'k' => Some(PieceType::King),
+        'a' => Some(PieceType::Archbishop),
+        'c' => Some(PieceType::Chancellor),
+        // anything else has to be one of the fairy pieces
+        letter => PieceType::from_letter(letter.to_ascii_uppercase())
+            .filter(|kind| matches!(kind, PieceType::Fairy(_))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves() {
+        let e2e4 = Some(Command::Move([4, 1], [4, 3], None));
+        assert_eq!(parse_command("e2e4"), e2e4);
+        assert_eq!(parse_command("e2 e4"), e2e4);
+        assert_eq!(parse_command("  E2-E4 "), e2e4);
+        assert_eq!(parse_command("e7e8q"), Some(Command::Move([4, 6], [4, 7], Some(PieceType::Queen))));
+        assert_eq!(parse_command("b7b8N"), Some(Command::Move([1, 6], [1, 7], Some(PieceType::Knight))));
+        // the wide boards go up to the l file
+        assert_eq!(parse_command("j1i3"), Some(Command::Move([9, 0], [8, 2], None)));
+    }
+
+    #[test]
+    fn drops() {
+        assert_eq!(parse_command("N@e4"), Some(Command::Drop(PieceType::Knight, [4, 3])));
+        assert_eq!(parse_command("p@a3"), Some(Command::Drop(PieceType::Pawn, [0, 2])));
+        assert_eq!(parse_command("Q@h8"), Some(Command::Drop(PieceType::Queen, [7, 7])));
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(parse_command("undo"), Some(Command::Undo));
+        assert_eq!(parse_command("resign"), Some(Command::Resign));
+        assert_eq!(parse_command("Draw"), Some(Command::Draw));
+        assert_eq!(parse_command("help"), Some(Command::Help));
+        assert_eq!(parse_command("quit"), Some(Command::Quit));
+        assert_eq!(parse_command("exit"), Some(Command::Quit));
+        assert_eq!(parse_command("save my game"), Some(Command::Save(String::from("my game"))));
+        assert_eq!(parse_command("load  old game "), Some(Command::Load(String::from("old game"))));
+    }
+
+    #[test]
+    fn bad_input() {
+        let bad = [
+            "", "e2", "e2e", "e2e4e5", "z2e4", "e0e4", "e2e4x", "e7e8p",
+            "X@e4", "N@z4", "N@e44", "save", "load", "undo it",
+        ];
+        for input in bad.iter() {
+            assert_eq!(parse_command(input), None, "{}", input);
+        }
+    }
+}
@@ -0,0 +1,420 @@
+// play chess in a terminal with the same rules and saves as the godot game
+mod input;
+mod render;
+
+use chess_core::{ai::{self, AiMove}, save, ChessBoard, Game, GameEvent, Odds, PieceType, Variant};
+use input::{parse_command, parse_promotion, Command};
+use std::{env, io::{self, prelude::*}, process};
+
+const HELP: &str = "\
+moves:   e2 e4, e2e4 or e2-e4 (add q, r, b or n to pick a promotion, or k in antichess,
+         a or c in capablanca and gothic, or the letter of a fairy piece in rules that allow them)
+N@e4     drop a piece from your reserve in crazyhouse (p, n, b, r or q)
+undo     take back the last move
+resign   give up the game
+draw     offer a draw, or accept one that was offered
+save X   save the game as X
+load X   load the saved game X
+help     show this message
+quit     leave the game";
+
+const USAGE: &str = "\
+usage: chess-terminal [--ai white|black] [--depth N] [--profile NAME] [--load NAME] [--chess960 N|random] [--fen FEN]
+                      [--variant standard|king_of_the_hill|three_check|atomic|crazyhouse|antichess|horde|racing_kings
+                                 |capablanca|gothic]
+                      [--odds pawn_and_move|knight|rook|queen] [--odds-by white|black]
+
+in chess960, castle by moving the king onto the rook it castles with.
+odds are given by white unless --odds-by says otherwise.
+fairy pieces from fairy_pieces.txt in the save directory can be used in --fen.
+saves go in DEXTER_CHESS_SAVE_DIR if it's set, and each --profile has its own saves there";
+
+// all the state of a game in the terminal
+struct Terminal {
+    board: ChessBoard,
+    // the player the computer plays as, if any
+    ai_player: Option<bool>,
+    ai_depth: u32,
+    // the player who offered a draw that hasn't been answered yet
+    draw_offer: Option<bool>,
+    colour: bool,
+    message: String,
+}
+
+impl Terminal {
+    fn new() -> Self {
+        Terminal {
+            board: ChessBoard::new(),
+            ai_player: None,
+            ai_depth: 2,
+            draw_offer: None,
+            colour: render::colour_enabled(),
+            message: String::new(),
+        }
+    }
+    // keep taking turns until someone quits
+    fn game_loop(&mut self) {
+        loop {
+            render::print_game(&self.board, self.colour, &self.message);
+            self.message.clear();
+            if let Some(winner) = self.board.get_winner() {
+                println!("{}", render::end_message(winner));
+            } else if self.ai_player == Some(self.board.get_player()) && self.ai_turn() {
+                continue;
+            }
+            let player = self.board.get_player();
+            print!("{} to move > ", player_name(player));
+            io::stdout().flush().ok();
+            let mut line = String::new();
+            match io::stdin().read_line(&mut line) {
+                // end of input counts as quitting
+                Ok(0) | Err(_) => break,
+                Ok(_) => {},
+            }
+            match parse_command(&line) {
+                Some(Command::Move(start, dest, promotion)) => self.player_move(start, dest, promotion),
+                Some(Command::Drop(kind, square)) => self.player_drop(kind, square),
+                Some(Command::Undo) => self.undo(),
+                Some(Command::Resign) => {
+                    if self.board.get_winner().is_none() {
+                        self.board.resign(player);
+                        self.autosave();
+                    }
+                },
+                Some(Command::Draw) => self.draw(player),
+                Some(Command::Save(name)) => {
+                    self.message = match self.board.save_game(&name) {
+                        Ok(()) => format!("saved as {}", name),
+                        Err(e) => format!("couldn't save: {}", e),
+                    };
+                },
+                Some(Command::Load(name)) => self.load(&name),
+                Some(Command::Help) => self.message = String::from(HELP),
+                Some(Command::Quit) => break,
+                None => self.message = String::from("didn't understand that, type help for commands"),
+            }
+        }
+    }
+    // try the move the player typed in
+    fn player_move(&mut self, start: [usize; 2], dest: [usize; 2], promotion: Option<PieceType>) {
+        if self.board.get_winner().is_some() {
+            self.message = String::from("the game is over");
+            return;
+        }
+        let player = self.board.get_player();
+        match self.board.next_turn(start, dest) {
+            Some(events) => {
+                for event in events {
+                    if let GameEvent::PromotionPending { square } = event {
+                        // keep asking until the player picks something the rules allow
+                        let mut kind = promotion;
+                        loop {
+                            let picked = match kind {
+                                Some(kind) => kind,
+                                None => ask_promotion(&self.board.get_promotion_options()),
+                            };
+                            match self.board.upgrade_pawn(square, picked) {
+                                Ok(_) => break,
+                                Err(e) => {
+                                    println!("{}", e);
+                                    kind = None;
+                                },
+                            }
+                        }
+                    }
+                }
+                // a draw offer lapses once the other player moves instead
+                if self.draw_offer == Some(!player) {
+                    self.draw_offer = None;
+                }
+                self.autosave();
+            },
+            None => self.message = String::from("that move isn't legal"),
+        }
+    }
+    // try the drop the player typed in
+    fn player_drop(&mut self, kind: PieceType, square: [usize; 2]) {
+        if self.board.get_winner().is_some() {
+            self.message = String::from("the game is over");
+            return;
+        }
+        let player = self.board.get_player();
+        match self.board.next_drop(kind, square) {
+            Some(_) => {
+                if self.draw_offer == Some(!player) {
+                    self.draw_offer = None;
+                }
+                self.autosave();
+            },
+            None => self.message = String::from("that drop isn't legal"),
+        }
+    }
+    // let the computer pick and play a move or a drop. Returns false if it
+    // couldn't find one, so the player gets the prompt instead
+    fn ai_turn(&mut self) -> bool {
+        println!("thinking...");
+        // the computer never accepts draws
+        self.draw_offer = None;
+        match ai::best_move(&self.board, self.ai_depth) {
+            Some(AiMove::Move(start, dest)) => {
+                self.player_move(start, dest, Some(ai::promotion(&self.board)));
+                self.message = format!("computer played {}", move_name(start, dest));
+                true
+            },
+            Some(AiMove::Drop(kind, square)) => {
+                self.player_drop(kind, square);
+                self.message = format!("computer dropped {}", drop_name(kind, square));
+                true
+            },
+            None => {
+                self.message = String::from("the computer has no moves");
+                false
+            },
+        }
+    }
+    // take back a move, or two against the computer so it's the player's turn again
+    fn undo(&mut self) {
+        let undo_count = if self.ai_player.is_some() {
+            2
+        } else {
+            1
+        };
+        for _ in 0..undo_count {
+            if self.board.undo().is_none() {
+                self.message = String::from("there's nothing to undo");
+                break;
+            }
+        }
+        self.draw_offer = None;
+        self.autosave();
+    }
+    // offer a draw or accept the other player's offer
+    fn draw(&mut self, player: bool) {
+        if self.board.get_winner().is_some() {
+            return;
+        }
+        if self.draw_offer == Some(!player) {
+            self.board.agree_draw();
+            self.draw_offer = None;
+            self.autosave();
+        } else if self.ai_player.is_some() {
+            self.message = String::from("the computer declines the draw");
+        } else {
+            self.draw_offer = Some(player);
+            self.message = format!("{} offers a draw, type draw to accept", player_name(player));
+        }
+    }
+    // keep the game in the recovery file in case the terminal gets closed
+    fn autosave(&mut self) {
+        if let Err(e) = self.board.autosave() {
+            self.message = format!("couldn't autosave: {}", e);
+        }
+    }
+    // ask whether to carry on with the game that wasn't finished last time,
+    // and throw it away if not
+    fn offer_restore(&mut self) {
+        if !save::recovery_exists() {
+            return;
+        }
+        print!("restore the unfinished game from last time? (y/n) > ");
+        io::stdout().flush().ok();
+        let mut line = String::new();
+        io::stdin().read_line(&mut line).ok();
+        if !line.trim().eq_ignore_ascii_case("y") {
+            if let Err(e) = save::clear_recovery() {
+                self.message = format!("couldn't discard the unfinished game: {}", e);
+            }
+            return;
+        }
+        if let Err(e) = self.board.restore_autosave() {
+            self.message = format!("couldn't restore the unfinished game: {}", e);
+            return;
+        }
+        let turns = self.board.get_num_turns();
+        if turns > 0 {
+            if let Err(e) = self.board.load_turn(turns-1) {
+                self.message = format!("couldn't restore the unfinished game: {}", e);
+                return;
+            }
+        }
+        self.message = String::from("restored the unfinished game");
+    }
+    // load the last turn of a saved game
+    fn load(&mut self, name: &str) {
+        if let Err(e) = self.board.load_game(name) {
+            self.message = format!("couldn't load {}: {}", name, e);
+            return;
+        }
+        let turns = self.board.get_num_turns();
+        if turns == 0 {
+            self.board.new_game();
+        } else if let Err(e) = self.board.load_turn(turns-1) {
+            self.message = format!("couldn't load {}: {}", name, e);
+            return;
+        }
+        self.draw_offer = None;
+        self.message = format!("loaded {}", name);
+    }
+}
+
+fn player_name(is_white: bool) -> &'static str {
+    if is_white {
+        "White"
+    } else {
+        "Black"
+    }
+}
+
+fn move_name(start: [usize; 2], dest: [usize; 2]) -> String {
+    format!("{} → {}", chess_core::square_name(start), chess_core::square_name(dest))
+}
+
+fn drop_name(kind: PieceType, square: [usize; 2]) -> String {
+    format!("{}@{}", kind.letter(), chess_core::square_name(square))
+}
+
+// keep asking until the player picks a piece to promote to
+fn ask_promotion(options: &[PieceType]) -> PieceType {
+    let letters: Vec<String> = options.iter().map(|kind| kind.letter().to_ascii_lowercase().to_string()).collect();
+    loop {
+        print!("promote to ({}) > ", letters.join(", "));
+        io::stdout().flush().ok();
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            return options.first().copied().unwrap_or(PieceType::Queen);
+        }
+        if let Some(kind) = line.trim().chars().next().and_then(parse_promotion) {
+            return kind;
+        }
+    }
+}
+
+fn main() {
+    // the profile decides where everything else gets read from, so it has
+    // to be picked first
+    let mut profile_args = env::args().skip_while(|arg| arg != "--profile");
+    if profile_args.next().is_some() {
+        let profile = profile_args.next().unwrap_or_default();
+        if let Err(e) = save::set_profile(Some(&profile)) {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    }
+    save::migrate_save_names();
+    if let Err(e) = chess_core::load_fairy_pieces() {
+        eprintln!("couldn't read the fairy pieces: {}", e);
+    }
+    let mut terminal = Terminal::new();
+    // a game picked on the command line is played instead of the unfinished one
+    let restore = !env::args().any(|arg| {
+        ["--load", "--variant", "--odds", "--odds-by", "--fen", "--chess960"].contains(&arg.as_str())
+    });
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ai" => {
+                terminal.ai_player = match args.next().as_deref() {
+                    Some("white") => Some(true),
+                    Some("black") => Some(false),
+                    _ => {
+                        eprintln!("{}", USAGE);
+                        process::exit(2);
+                    },
+                };
+            },
+            "--depth" => {
+                terminal.ai_depth = match args.next().and_then(|depth| depth.parse().ok()) {
+                    Some(depth) if depth > 0 => depth,
+                    _ => {
+                        eprintln!("{}", USAGE);
+                        process::exit(2);
+                    },
+                };
+            },
+            // already picked before anything was loaded
+            "--profile" => {
+                args.next();
+            },
+            "--load" => {
+                match args.next() {
+                    Some(name) => terminal.load(&name),
+                    None => {
+                        eprintln!("{}", USAGE);
+                        process::exit(2);
+                    },
+                }
+            },
+            "--variant" => {
+                match args.next().as_deref().and_then(Variant::from_name) {
+                    Some(variant) => {
+                        terminal.board.set_variant(variant);
+                        terminal.board.new_game();
+                    },
+                    None => {
+                        eprintln!("{}", USAGE);
+                        process::exit(2);
+                    },
+                }
+            },
+            "--odds" => {
+                match args.next().as_deref().and_then(Odds::from_name) {
+                    Some(odds) => {
+                        let (_, giver_is_white) = terminal.board.get_odds();
+                        terminal.board.set_odds(odds, giver_is_white);
+                        terminal.board.new_game();
+                    },
+                    None => {
+                        eprintln!("{}", USAGE);
+                        process::exit(2);
+                    },
+                }
+            },
+            "--odds-by" => {
+                let giver_is_white = match args.next().as_deref() {
+                    Some("white") => true,
+                    Some("black") => false,
+                    _ => {
+                        eprintln!("{}", USAGE);
+                        process::exit(2);
+                    },
+                };
+                let (odds, _) = terminal.board.get_odds();
+                terminal.board.set_odds(odds, giver_is_white);
+                terminal.board.new_game();
+            },
+            "--fen" => {
+                let fen = args.next().unwrap_or_default();
+                if let Err(e) = terminal.board.load_fen(&fen) {
+                    eprintln!("{}\n\n{}", e, USAGE);
+                    process::exit(2);
+                }
+            },
+            "--chess960" => {
+                let index = match args.next().as_deref() {
+                    Some("random") => Some(chess_core::random_chess960_index()),
+                    Some(index) => index.parse().ok().filter(|index| *index < 960),
+                    None => None,
+                };
+                match index {
+                    Some(index) => terminal.board.new_chess960(index),
+                    None => {
+                        eprintln!("{}", USAGE);
+                        process::exit(2);
+                    },
+                }
+            },
+            "--help" | "-h" => {
+                println!("{}\n\n{}", USAGE, HELP);
+                return;
+            },
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            },
+        }
+    }
+    if restore {
+        terminal.offer_restore();
+    }
+    terminal.game_loop();
+}
@@ -0,0 +1,105 @@
+use chess_core::{ChessBoard, ChessPiece, Game, PieceType, Variant};
+use std::env;
+
+const RESET: &str = "\x1b[0m";
+const LIGHT_SQUARE: &str = "\x1b[48;5;180m";
+const DARK_SQUARE: &str = "\x1b[48;5;137m";
+const WHITE_PIECE: &str = "\x1b[1;97m";
+const BLACK_PIECE: &str = "\x1b[1;30m";
+
+// colours can be turned off the usual way, see https://no-color.org
+pub fn colour_enabled() -> bool {
+    env::var_os("NO_COLOR").is_none()
+}
+
+// draw the board with white at the bottom and the files and ranks labelled
+pub fn draw_board(board: &ChessBoard, colour: bool) -> String {
+    let mut drawing = String::new();
+    let [width, height] = board.get_size();
+    for row in (0..height).rev() {
+        drawing.push_str(&format!("{} ", row + 1));
+        for col in 0..width {
+            let piece = board.get_board()[col][row];
+            if colour {
+                let square = if (col + row) % 2 == 0 {
+                    DARK_SQUARE
+                } else {
+                    LIGHT_SQUARE
+                };
+                drawing.push_str(square);
+                match piece {
+                    // use the solid symbols for both sides and tell them
+                    // apart by colour, the outlines are hard to see
+                    Some(piece) => {
+                        let fg = if piece.is_white() {
+                            WHITE_PIECE
+                        } else {
+                            BLACK_PIECE
+                        };
+                        drawing.push_str(&format!("{}{} ", fg, piece.get_symbol(1)));
+                    },
+                    None => drawing.push_str("  "),
+                }
+                drawing.push_str(RESET);
+            } else {
+                match piece {
+                    Some(piece) => drawing.push_str(&format!("{} ", piece)),
+                    None if (col + row) % 2 == 0 => drawing.push_str(". "),
+                    None => drawing.push_str("  "),
+                }
+            }
+        }
+        drawing.push('\n');
+    }
+    drawing.push(' ');
+    for file in (b'a'..).take(width) {
+        drawing.push_str(&format!(" {}", file as char));
+    }
+    drawing.push('\n');
+    drawing
+}
+
+// clear the terminal and show the whole game
+pub fn print_game(board: &ChessBoard, colour: bool, message: &str) {
+    if colour {
+        print!("\x1b[2J\x1b[H");
+    }
+    print!("{}", board.get_score());
+    if board.get_variant() == Variant::ThreeCheck {
+        let checks = board.get_checks();
+        println!("Checks: White {} Black {}", checks[0], checks[1]);
+    }
+    if board.get_rules().has_drops() {
+        println!("Reserve: White {} Black {}", reserve_string(board, true), reserve_string(board, false));
+    }
+    println!();
+    print!("{}", draw_board(board, colour));
+    println!();
+    if !message.is_empty() {
+        println!("{}", message);
+    }
+}
+
+// the pieces a player can drop, like P2 N1
+fn reserve_string(board: &ChessBoard, is_white: bool) -> String {
+    let counts: Vec<String> = PieceType::ALL.iter()
+        .zip(board.get_reserve(is_white).iter())
+        .filter(|(_, count)| **count > 0)
+        .map(|(kind, count)| format!("{}{}", kind.letter(), count))
+        .collect();
+    if counts.is_empty() {
+        return String::from("-");
+    }
+    counts.join(" ")
+}
+
+// say who won
+pub fn end_message(winner: i8) -> &'static str {
+    if winner > 0 {
+        "White has won!"
+    } else if winner < 0 {
+        "Black has won!"
+    } else {
+        "Draw!"
+    }
+}
@@ -8,7 +8,6 @@ onready var pawn_promotion_popup = $GUI/PawnPromotionPopup
 onready var mouse_input = get_node("/root/MouseInput")
 onready var move_list = $GUI/SaveLoadContainer/SaveLoadPanel/TabContainer/moves
 onready var turn_label = $GUI/InfoContainer/Turn
-onready var turn_timer = $TurnTimer
 onready var draw_button = $GUI/ActionGrid/DrawButton
 
 var popup_open = false
@@ -22,27 +21,98 @@ var animation_enabled = true
 var move_start = Vector3()
 var move_dest = Vector3()
 var winner = -2
+var end_reason = ""
 var draw_accept_mode = false
 var draw_offered = false
+# a drop doesn't have a held piece to animate, so its move_is_legal is
+# handled on its own
+var dropping = false
 
-const BOARD_WIDTH = 4.2
+const TILE = preload("res://chess/BoardTile.tscn")
+const INDICATOR = preload("res://gui/FloatingIndicator.tscn")
+const TILE_SIZE = 1.05
+const LIGHT_SQUARE = preload("res://chess/models/board/tilewhite.tres")
+const DARK_SQUARE = preload("res://chess/models/board/wood.tres")
+# how far pieces can be dragged from the middle of the board, which is
+# wider in capablanca and gothic chess
+var board_extents = Vector2(4.2, 4.2)
+const DROP_LETTERS = {"P": "pawn", "N": "knight", "B": "bishop", "R": "rook", "Q": "queen"}
 
 signal queue_resolved
 
 func _ready():
     draw_button.connect("button_up", self, "_handle_draw")
-    turn_timer.connect("timeout", self, "_out_of_time")
     $ChessDirector.connect("game_over", self, "_on_game_over")
     $ChessDirector.connect("move_is_legal", self, "_move_tried")
     $ChessDirector.connect("castle", self, "_castle")
     $ChessDirector.connect("piece_captured", self, "_piece_captured")
+    $ChessDirector.connect("exploded", self, "_exploded")
+    $ChessDirector.connect("piece_dropped", self, "_piece_dropped")
     $ChessDirector.connect("pawn_promoted", self, "_pawn_promoted")
     $Pieces.connect("piece_moved", self, "_piece_moved")
     $GUI/OptionsContainer/OptionsPanel/VBoxContainer/AnimationToggle.connect(
         "toggled", self, "_animation_toggled")
     move_entry.connect("text_entered", self, "_move_entered")
     submit_button.connect("pressed", self, "_move_entered")
-    $Pieces.new_game()
+    # the fairy pieces have to be known before a position with them is loaded
+    $ChessDirector.load_fairy_pieces()
+    $ChessDirector.show_board()
+
+# make a tile for every square of the board, with the board in the middle.
+# The board model only has 8x8 squares, so bigger boards get squares drawn
+# on their tiles. Nothing changes if the tiles are already the right size
+func build_tiles():
+    var size = $ChessDirector.get_board_size()
+    if $Tiles.get_child_count() == size.x*size.y:
+        return
+    for tile in $Tiles.get_children():
+        # take it out straight away so the new tiles can have its name
+        $Tiles.remove_child(tile)
+        tile.queue_free()
+    var draw_squares = size != Vector2(8, 8)
+    for col in range(size.x):
+        for row in range(size.y):
+            var tile = TILE.instance()
+            tile.set_name("%s,%s" % [col, row])
+            tile.tile = PoolIntArray([col, row])
+            tile.set_translation(Vector3(
+                (col - size.x/2)*TILE_SIZE, 0.546862, (size.y/2 - 1 - row)*TILE_SIZE))
+            if draw_squares:
+                var square = MeshInstance.new()
+                var mesh = CubeMesh.new()
+                mesh.size = Vector3(TILE_SIZE, 0.02, TILE_SIZE)
+                square.set_mesh(mesh)
+                square.set_translation(Vector3(TILE_SIZE/2, 0.01, TILE_SIZE/2))
+                if (col + row) % 2 == 0:
+                    square.set_surface_material(0, DARK_SQUARE)
+                else:
+                    square.set_surface_material(0, LIGHT_SQUARE)
+                tile.add_child(square)
+            $Tiles.add_child(tile)
+    board_extents = Vector2(size.x, size.y)*TILE_SIZE/2
+    place_indicators(size)
+
+# line the file letters up with the tiles, with more of them for wider
+# boards, and keep the rank numbers next to the left edge
+func place_indicators(size: Vector2):
+    var shown = $Indicators/a.is_visible()
+    for indicator in $Indicators.get_children():
+        if indicator.label.is_valid_integer():
+            indicator.translation.x = -board_extents.x - 0.48
+        elif ord(indicator.label) - 97 >= size.x:
+            $Indicators.remove_child(indicator)
+            indicator.queue_free()
+    for col in range(size.x):
+        var file = char(97 + col)
+        var indicator = $Indicators.get_node_or_null(file)
+        if not indicator:
+            indicator = INDICATOR.instance()
+            indicator.label = file
+            indicator.set_name(file)
+            indicator.translation = $Indicators/a.translation
+            indicator.set_visible(shown)
+            $Indicators.add_child(indicator)
+        indicator.translation.x = (col - size.x/2)*TILE_SIZE + 0.53
     
 func _handle_draw():
     if draw_accept_mode:
@@ -56,18 +126,7 @@ func reset_draw_mode():
     draw_offered = false
     draw_accept_mode = false
 
-func _out_of_time():
-    $Pieces.halt_pieces()
-    $GUI.timer_ran_out = true
-    if is_white_turn:
-        end_game("White Ran Out of Time")
-        $GUI._update_log("White Ran Out of Time\n")
-    else:
-        end_game("Black Ran Out of Time")
-        $GUI._update_log("Black Ran Out of Time\n")
-
 func end_game(message: String, topple_king = false, king_is_white = false):
-    $TurnTimer.stop()
     $GUI/EndPopup.set_visible(true)
     $GUI/EndPopup.set_text(message)
     $GUI.set_actions_enabled(false)
@@ -79,17 +138,32 @@ func _move_entered(input = ""):
     if input == "":
         input = move_entry.get_text()
     move_entry.set_text("")
+    # drops from the crazyhouse reserve are written like N@e4
+    if input.length() == 4 and input[1] == "@":
+        var piece_type = DROP_LETTERS.get(input[0].to_upper(), "")
+        var input_bytes = input.to_lower().to_ascii()
+        var dest = [input_bytes[2]-97, input_bytes[3]-49]
+        var size = $ChessDirector.get_board_size()
+        if piece_type != "" and dest[0] >= 0 and dest[0] < size.x and dest[1] >= 0 and dest[1] < size.y:
+            try_drop(piece_type, dest)
+            return
     if input.length() == 5:
         var input_bytes = input.to_ascii()
         var start = [input_bytes[0]-97, input_bytes[1]-49]
         var dest = [input_bytes[3]-97, input_bytes[4]-49]
-        if start[0] >= 0 and start[0] < 8 and start[1] >= 0 and start[1] < 8 \
-        and dest[0] >= 0 and dest[0] < 8 and dest[1] >= 0 and dest[1] < 8:
+        var size = $ChessDirector.get_board_size()
+        if start[0] >= 0 and start[0] < size.x and start[1] >= 0 and start[1] < size.y \
+        and dest[0] >= 0 and dest[0] < size.x and dest[1] >= 0 and dest[1] < size.y:
             try_move_text(start, dest)
             return
     $GUI/MoveEntryAnimator.play("move_entry_shake")
     
 func _move_tried(move_was_legal: bool):
+    if dropping:
+        dropping = false
+        if not move_was_legal:
+            $GUI/MoveEntryAnimator.play("move_entry_shake")
+        return
     $GUI.set_actions_enabled(false)
     mouse_input.suspend_input()
     if move_was_legal:
@@ -112,11 +186,7 @@ func _move_tried(move_was_legal: bool):
             # switch which player's turn it is
             is_white_turn = not is_white_turn
             turn_label.set_player(is_white_turn)
-            # reset the timer
-            if not turn_timer.is_stopped():
-                turn_timer.stop()
-                turn_timer.start()
-                $GUI/InfoContainer/Timer._update_label()
+            $GUI/InfoContainer/Timer._update_label()
     if not popup_open and winner == -2:
         $GUI.set_actions_enabled(true)
         mouse_input.resume_input()
@@ -125,7 +195,46 @@ func _move_tried(move_was_legal: bool):
     $GUI.clear_move_entry()
     
 func resolve_game_end(code: int):
-    if code == 0:
+    if end_reason == "king_of_the_hill":
+        if code == 1:
+            end_game("White's King Takes The Hill", true, false)
+        else:
+            end_game("Black's King Takes The Hill", true, true)
+    elif end_reason == "king_exploded":
+        if code == 1:
+            end_game("White Blows Up Black's King", true, false)
+        else:
+            end_game("Black Blows Up White's King", true, true)
+    elif end_reason == "three_check":
+        if code == 1:
+            end_game("White Gives Three Checks", true, false)
+        else:
+            end_game("Black Gives Three Checks", true, true)
+    elif end_reason == "no_pieces_left":
+        if code == 1:
+            end_game("White Loses Every Piece", true, false)
+        else:
+            end_game("Black Loses Every Piece", true, true)
+    elif end_reason == "racing_kings":
+        if code == 1:
+            end_game("White's King Wins The Race")
+        elif code == -1:
+            end_game("Black's King Wins The Race")
+        else:
+            end_game("Both Kings Finish The Race")
+    elif end_reason == "out_of_time":
+        if code == 1:
+            end_game("Black Ran Out of Time")
+        else:
+            end_game("White Ran Out of Time")
+    elif end_reason == "horde_captured":
+        end_game("Black Captures The Whole Horde")
+    elif end_reason == "stalemate" and code != 0:
+        if code == 1:
+            end_game("White Has No Moves Left", true, false)
+        else:
+            end_game("Black Has No Moves Left", true, true)
+    elif code == 0:
         end_game("Stalemate")
     elif code == -1:
         end_game("Black Checkmates White", true, true)
@@ -150,8 +259,13 @@ func resolve_queue():
             promote_pawn(signal_data)
         elif signal_type == "capture":
             capture_piece(signal_data)
+        elif signal_type == "explode":
+            explode(signal_data)
         elif signal_type == "move":
-            if signal_data == Vector2():
+            # the piece blew up along with what it captured
+            if held_piece.is_captured:
+                resolve_queue()
+            elif signal_data == Vector2():
                 held_piece.move(move_dest, 1.5)
             else:
                 held_piece.move(get_tile(held_piece.start).get_pos())
@@ -179,17 +293,28 @@ func capture_piece(position: Vector2):
             resolve_queue()
         captured_piece.capture()
     
-func castle(position: Vector2):
-    var castle = get_tile([position.x, position.y]).get_piece()
-    var castle_dest = Vector2()
-    if position == Vector2(0,0):
-        castle_dest = get_tile([3,0]).get_pos()
-    elif position == Vector2(7,0):
-        castle_dest = get_tile([5,0]).get_pos()
-    elif position == Vector2(0,7):
-        castle_dest = get_tile([3,7]).get_pos()
-    elif position == Vector2(7,7):
-        castle_dest = get_tile([5,7]).get_pos()
+func explode(blast: Array):
+    $Pieces.explode(blast[1])
+    if held_piece:
+        held_piece.move(get_tile([blast[0].x, blast[0].y]).get_pos(), 1, 2, false)
+        if animation_enabled:
+            yield(held_piece, "moved_internal")
+        # the queue carries on once the piece has flown off
+        held_piece.capture()
+        if not animation_enabled:
+            resolve_queue()
+    else:
+        resolve_queue()
+
+func castle(positions: Array):
+    var rook_start = positions[0]
+    var rook_dest = positions[1]
+    var king_dest = positions[2]
+    var castle = get_tile([rook_start.x, rook_start.y]).get_piece()
+    var castle_dest = get_tile([rook_dest.x, rook_dest.y]).get_pos()
+    # in chess960 the king can be dropped on its rook, so it might not
+    # end up where it was moved to
+    move_dest = get_tile([king_dest.x, king_dest.y]).get_pos()
     if held_piece:
         held_piece.move(move_dest+Vector3(0,3,0), 1, 2, false)
     castle.move(castle_dest, 1.0, 0.8)
@@ -202,6 +327,8 @@ func promote_pawn(position: Vector2):
     $GUI.set_actions_enabled(false)
     
     yield(held_piece, "moved_internal")
+    # the rules decide what pawns can become, like kings in antichess
+    pawn_promotion_popup.show_options($ChessDirector.get_promotion_options())
     pawn_promotion_popup.popup()
     # wait for signal from pawn promotion popup
     var new_piece_type = yield(pawn_promotion_popup, "piece_type_selected")
@@ -215,10 +342,26 @@ func promote_pawn(position: Vector2):
     popup_open = false
     
     
-func _castle(castle_position: Vector2):
+func _castle(rook_start: Vector2, rook_dest: Vector2, king_dest: Vector2):
     signal_queue[0].append("castle")
-    signal_queue[1].append(castle_position)
+    signal_queue[1].append([rook_start, rook_dest, king_dest])
     
+func _exploded(centre: Vector2, squares: PoolVector2Array):
+    signal_queue[0].append("explode")
+    signal_queue[1].append([centre, squares])
+
+# a piece came out of the crazyhouse reserve, which takes the place of a move
+func _piece_dropped(is_white: bool, piece_type: String, position: Vector2):
+    $Pieces.instance_piece(is_white, piece_type, position)
+    reset_draw_mode()
+    is_white_turn = not is_white_turn
+    turn_label.set_player(is_white_turn)
+    $GUI/InfoContainer/Timer._update_label()
+
+func try_drop(piece_type: String, dest: PoolIntArray):
+    dropping = true
+    $ChessDirector.try_drop(piece_type, Vector2(dest[0], dest[1]))
+
 func _piece_captured(capture_position: Vector2):
     signal_queue[0].append("capture")
     signal_queue[1].append(capture_position)
@@ -227,8 +370,9 @@ func _pawn_promoted(pawn_position: Vector2):
     signal_queue[0].append("pawn_promotion")
     signal_queue[1].append(pawn_position)
 
-func _on_game_over(code: int):
+func _on_game_over(code: int, reason: String):
     winner = code
+    end_reason = reason
     if not held_piece:
         resolve_game_end(code)
 
@@ -248,6 +392,11 @@ func try_move_text(start: PoolIntArray, dest: PoolIntArray):
     if not move_was_legal:
         $GUI/MoveEntryAnimator.play("move_entry_shake")
     
+# the board runs the clocks and ends the game when one runs out
+func _physics_process(delta):
+    if winner == -2:
+        $ChessDirector.tick(delta)
+
 func _process(delta):
     if not request_process:
         set_process(false)
@@ -270,16 +419,8 @@ func _input(event):
         var point = camera_ray.get_collision_point()
         if point:
             # make sure the player can't move pieces off the board
-            if abs(point.x) > BOARD_WIDTH:
-                if point.x > 0:
-                    point.x = BOARD_WIDTH
-                else:
-                    point.x = -BOARD_WIDTH
-            if abs(point.z) > BOARD_WIDTH:
-                if point.z > 0:
-                    point.z = BOARD_WIDTH
-                else:
-                    point.z = -BOARD_WIDTH
+            point.x = clamp(point.x, -board_extents.x, board_extents.x)
+            point.z = clamp(point.z, -board_extents.y, board_extents.y)
             var drag_position = point + Vector3(0, 4, 0)
             drag_point = drag_position
 #	elif event is InputEventMouseButton:
@@ -3,8 +3,13 @@ extends Node
 var log_hidden = true
 var options_hidden = true
 var save_load_hidden = true
-var timer_ran_out = false
 var timer_enabled = false
+# the handicaps the next new game is played with
+const ODDS = ["none", "pawn_and_move", "knight", "rook", "queen"]
+var odds_button = OptionButton.new()
+var odds_by_black = CheckBox.new()
+var black_minutes = SpinBox.new()
+var black_seconds = SpinBox.new()
 
 onready var log_button = $LogPanel/MarginContainer/VSplitContainer/HSplitContainer/ShowLogButton
 onready var log_animator = $LogPanelAnimator
@@ -20,10 +25,10 @@ onready var save_load_button = $SaveLoadContainer/CenterContainer/Button
 onready var save_load_animator = $SaveLoadAnimator
 onready var minutes = $OptionsContainer/OptionsPanel/VBoxContainer/TimerContainer/Minutes
 onready var seconds = $OptionsContainer/OptionsPanel/VBoxContainer/TimerContainer/Seconds
-onready var turn_timer = owner.get_node("TurnTimer")
 onready var timer_label = $InfoContainer/Timer
 onready var timer_toggle = $OptionsContainer/OptionsPanel/VBoxContainer/TimerToggle
 onready var indicator_toggle = $OptionsContainer/OptionsPanel/VBoxContainer/RankFileToggle
+onready var options_box = $OptionsContainer/OptionsPanel/VBoxContainer
 
 onready var chess_director = get_parent().get_node("ChessDirector")
 
@@ -37,31 +42,54 @@ func _ready():
     options_button.connect("button_up", self, "_toggle_options")
     save_load_button.connect("button_up", self, "_toggle_save_load")
     resign_button.connect("button_up", self, "_resign")
-    minutes.connect("value_changed", self, "_minutes_changed")
-    seconds.connect("value_changed", self, "_seconds_changed")
     timer_toggle.connect("toggled", self, "_timer_toggled")
     indicator_toggle.connect("toggled", self, "_indicators_toggled")
-    
+    add_handicap_options()
+
+# black's time for time odds and the piece odds go under the timer options.
+# Like the timer they're used from the next new game on
+func add_handicap_options():
+    var black_time = HBoxContainer.new()
+    var black_label = Label.new()
+    black_label.set_text("  Black's time: ")
+    black_time.add_child(black_label)
+    black_minutes.max_value = 60
+    black_minutes.value = minutes.get_value()
+    black_minutes.suffix = ":"
+    black_minutes.align = LineEdit.ALIGN_RIGHT
+    black_seconds.max_value = 59
+    black_seconds.value = seconds.get_value()
+    black_time.add_child(black_minutes)
+    black_time.add_child(black_seconds)
+    options_box.add_child(black_time)
+    var odds = HBoxContainer.new()
+    var odds_label = Label.new()
+    odds_label.set_text("  Odds: ")
+    odds.add_child(odds_label)
+    for name in ODDS:
+        odds_button.add_item(name.replace("_", " "))
+    odds.add_child(odds_button)
+    odds_by_black.set_text("by black")
+    odds.add_child(odds_by_black)
+    options_box.add_child(odds)
+
+# tell the board the clocks and odds for the game that's about to start
+func apply_game_options():
+    if timer_enabled:
+        chess_director.set_time_control(
+            minutes.get_value()*60 + seconds.get_value(),
+            black_minutes.get_value()*60 + black_seconds.get_value())
+    else:
+        chess_director.set_time_control(0, 0)
+    chess_director.set_odds(ODDS[odds_button.get_selected()], not odds_by_black.is_pressed())
+
 func _indicators_toggled(state: bool):
     owner.get_node("Indicators").set_visible(state)
     
+# the clocks start with the next new game
 func _timer_toggled(state: bool):
     timer_enabled = state
-    if !timer_ran_out:
-        if state:
-            turn_timer.start()
-        else:
-            turn_timer.stop()
-        timer_label._update_label()
-        timer_label.set_visible(state)
-    
-func _minutes_changed(num_minutes: float):
-    turn_timer.set_wait_time(num_minutes*60 + seconds.get_value())
-    
-func _seconds_changed(num_seconds: float):
-    turn_timer.set_wait_time(minutes.get_value()*60 + num_seconds)
 
-    
 func hide_end_popup():
     $EndPopup.set_visible(false)
     
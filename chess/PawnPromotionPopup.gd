@@ -4,6 +4,12 @@ onready var queen_button = $PawnOptionContainer/Queen
 onready var rook_button = $PawnOptionContainer/Rook
 onready var bishop_button = $PawnOptionContainer/Bishop
 onready var knight_button = $PawnOptionContainer/Knight
+onready var king_button = $PawnOptionContainer/King
+onready var archbishop_button = $PawnOptionContainer/Archbishop
+onready var chancellor_button = $PawnOptionContainer/Chancellor
+
+const BUILT_IN = ["queen", "rook", "bishop", "knight", "king", "archbishop", "chancellor"]
+var fairy_buttons = []
 
 signal piece_type_selected
 
@@ -12,6 +18,32 @@ func _ready():
     rook_button.connect("button_up", self, "_rook_selected")
     bishop_button.connect("button_up", self, "_bishop_selected")
     knight_button.connect("button_up", self, "_knight_selected")
+    king_button.connect("button_up", self, "_king_selected")
+    archbishop_button.connect("button_up", self, "_archbishop_selected")
+    chancellor_button.connect("button_up", self, "_chancellor_selected")
+
+# only show the pieces the rules let pawns become
+func show_options(options: PoolStringArray):
+    # fairy pieces get a button by name, since they aren't known until the
+    # fairy pieces file is read
+    for button in fairy_buttons:
+        $PawnOptionContainer.remove_child(button)
+        button.queue_free()
+    fairy_buttons.clear()
+    for option in options:
+        if not option in BUILT_IN:
+            var button = Button.new()
+            button.set_text(option.capitalize())
+            button.connect("button_up", self, "_fairy_selected", [option])
+            $PawnOptionContainer.add_child(button)
+            fairy_buttons.append(button)
+    queen_button.visible = "queen" in options
+    rook_button.visible = "rook" in options
+    bishop_button.visible = "bishop" in options
+    knight_button.visible = "knight" in options
+    king_button.visible = "king" in options
+    archbishop_button.visible = "archbishop" in options
+    chancellor_button.visible = "chancellor" in options
     
 func _queen_selected():
     emit_signal("piece_type_selected", "queen")
@@ -24,3 +56,15 @@ func _bishop_selected():
     
 func _knight_selected():
     emit_signal("piece_type_selected", "knight")
+    
+func _king_selected():
+    emit_signal("piece_type_selected", "king")
+
+func _archbishop_selected():
+    emit_signal("piece_type_selected", "archbishop")
+
+func _chancellor_selected():
+    emit_signal("piece_type_selected", "chancellor")
+
+func _fairy_selected(piece_type: String):
+    emit_signal("piece_type_selected", piece_type)
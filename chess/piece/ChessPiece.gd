@@ -20,6 +20,10 @@ onready var mouse_input = get_node("/root/MouseInput")
 onready var chess_game = get_parent().get_parent()
 const WHITE_MATERIAL = preload("res://chess/models/pieces/white_piece.tres")
 const BLACK_MATERIAL = preload("res://chess/models/pieces/black_piece.tres")
+const MODEL_PATH = "res://chess/models/pieces/%s.tres"
+# archbishops and chancellors don't have models of their own, so they're a
+# bishop or a rook with a small knight on top for the knight's jump
+const COMPOUND_MODELS = {"archbishop": "bishop", "chancellor": "rook"}
 
 var time = 0.0
 
@@ -41,14 +45,36 @@ func _ready():
     
 func set_piece_type(type: String):
     piece_type = type
-    $MeshInstance.set_mesh(load("res://chess/models/pieces/%s.tres" % type))
+    # a promoted pawn might have been something with a knight on top
+    var topper = $MeshInstance.get_node_or_null("Topper")
+    if topper:
+        $MeshInstance.remove_child(topper)
+        topper.queue_free()
+    if type in COMPOUND_MODELS:
+        var body = load(MODEL_PATH % COMPOUND_MODELS[type])
+        $MeshInstance.set_mesh(body)
+        topper = MeshInstance.new()
+        topper.set_name("Topper")
+        topper.set_mesh(load(MODEL_PATH % "knight"))
+        topper.set_scale(Vector3(0.5, 0.5, 0.5))
+        topper.set_translation(Vector3(0, body.get_aabb().end.y, 0))
+        $MeshInstance.add_child(topper)
+    elif ResourceLoader.exists(MODEL_PATH % type):
+        $MeshInstance.set_mesh(load(MODEL_PATH % type))
+    else:
+        # fairy pieces come from a file without models, so they look like queens
+        $MeshInstance.set_mesh(load(MODEL_PATH % "queen"))
 func set_is_white(is_white: bool):
     self.is_white = is_white
+    var material = BLACK_MATERIAL
     if is_white:
-        $MeshInstance.set_surface_material(0, WHITE_MATERIAL)
+        material = WHITE_MATERIAL
     else:
         $MeshInstance.set_rotation(Vector3(0, PI, 0))
-        $MeshInstance.set_surface_material(0, BLACK_MATERIAL)
+    $MeshInstance.set_surface_material(0, material)
+    var topper = $MeshInstance.get_node_or_null("Topper")
+    if topper:
+        topper.set_surface_material(0, material)
     
 func set_fields(is_white: bool, type: String):
     set_piece_type(type)
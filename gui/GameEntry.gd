@@ -18,7 +18,9 @@ func _ready():
     cancel_delete_button.connect("button_up", self, "_reset")
 
 func _load_game():
-    chess_director.load_game(save_name)
+    # a save that can't be loaded leaves the game being played alone
+    if not chess_director.load_game(save_name):
+        return
     games._new_game()
     save.name_edit.set_text(save_name)
     
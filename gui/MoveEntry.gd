@@ -7,7 +7,6 @@ onready var piece_controller = chess_game.get_node("Pieces")
 onready var log_label = chess_game.get_node("GUI").log_label
 onready var turn_label = chess_game.turn_label
 onready var gui = chess_game.get_node("GUI")
-onready var turn_timer = chess_game.get_node("TurnTimer")
 var turn_num = 0
 
 func _ready():
@@ -17,12 +16,6 @@ func _ready():
 func _button_up():
     # reset the draw button
     chess_game.reset_draw_mode()
-    # set the timer to not be depleted
-    gui.timer_ran_out = false
-    if gui.timer_enabled:
-        turn_timer.stop()
-        turn_timer.start()
-    gui.timer_label._update_label()
     # reset the winner storage variable
     get_parent().owner.winner = -2
     # clear the log
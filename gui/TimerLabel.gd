@@ -1,14 +1,16 @@
 extends Label
 
-onready var turn_timer = owner.get_node("TurnTimer")
+onready var chess_director = owner.get_node("ChessDirector")
 
 func _ready():
     $SecondTimer.connect("timeout", self, "_update_label")
     _update_label()
     
+# the time left for the player to move, hidden in games without clocks
 func _update_label():
-    if not turn_timer.is_stopped():
-        var total_seconds = turn_timer.get_time_left()
+    var total_seconds = chess_director.get_clock(owner.is_white_turn)
+    set_visible(total_seconds >= 0)
+    if total_seconds >= 0:
         var minutes = int(total_seconds/60)
         var seconds = int(total_seconds - minutes*60)
-        set_text("%s:%s" % [minutes, seconds])
+        set_text("%s:%02d" % [minutes, seconds])
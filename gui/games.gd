@@ -9,48 +9,112 @@ onready var piece_controller = owner.get_node("Pieces")
 onready var chess_director = owner.get_node("ChessDirector")
 onready var list_container = $VSplitContainer/MarginContainer/ScrollContainer/VBoxContainer
 onready var gui = owner.get_node("GUI")
-onready var turn_timer = owner.get_node("TurnTimer")
 onready var name_entry = gui.get_node("SaveLoadContainer/SaveLoadPanel/TabContainer/save/VBoxContainer/MarginContainer/NameEdit")
 
+# the variant the next new game is played as
+var variant = "standard"
+# narrows the list down to one player's games
+var search_edit = LineEdit.new()
+
 func _ready():
+    search_edit.placeholder_text = "search by player"
+    search_edit.add_to_group("flag_placeholder")
+    search_edit.connect("text_changed", self, "_search_changed")
+    list_container.add_child(search_edit)
+    list_container.move_child(search_edit, 0)
     new_game_button.connect("button_up", self, "_new_game")
     owner.get_node("GUI/EndPopup/VSplitContainer/NewGameContainer/NewGame").connect("button_up", self, "_new_game")
     populate_save_list()
+    _offer_restore()
     
 func _new_game():
     gui.hide_end_popup()
     moves.update_moves()
     piece_controller.new_game()
-    chess_director.reset_game()
+    gui.apply_game_options()
+    chess_director.reset_game(variant)
     gui.log_label.set_text("")
     gui.score_label.set_text("White Score: 0\n\nBlack Score: 0\n\n")
     name_entry.set_text("")
     reset_misc()
 
+# a game that was never finished or saved, like when the game crashed,
+# can be carried on with or thrown away
+func _offer_restore():
+    if not chess_director.has_autosave():
+        return
+    var dialog = ConfirmationDialog.new()
+    dialog.dialog_text = "Restore the unfinished game from last time?"
+    dialog.get_ok().set_text("restore")
+    dialog.get_cancel().set_text("discard")
+    dialog.connect("confirmed", self, "_restore_autosave")
+    dialog.get_cancel().connect("pressed", chess_director, "clear_autosave")
+    # the gui is still setting up its own children
+    gui.call_deferred("add_child", dialog)
+    dialog.call_deferred("popup_centered")
+
+func _restore_autosave():
+    _new_game()
+    chess_director.restore_autosave()
+    moves.update_moves()
+    owner.is_white_turn = chess_director.get_num_turns() % 2 == 0
+    owner.turn_label.set_player(owner.is_white_turn)
+
 func get_save_names() -> PoolStringArray:
     return chess_director.get_save_names()
     
+# newest games first. Old saves don't have a date so they go at the end
 func populate_save_list():
     for child in list_container.get_children():
         if not child.is_in_group("flag_placeholder"):
             child.queue_free()
-    for entry_name in get_save_names():
-        add_entry(entry_name)
+    var summaries
+    if search_edit.get_text() == "":
+        summaries = chess_director.get_save_summaries()
+        summaries.sort_custom(self, "_newer_first")
+    else:
+        # the database already has them newest first
+        summaries = chess_director.find_games({"player": search_edit.get_text()})
+    for summary in summaries:
+        add_entry(summary)
+
+func _search_changed(_text: String):
+    populate_save_list()
+
+func _newer_first(a: Dictionary, b: Dictionary) -> bool:
+    return a["date"] > b["date"]
         
-func add_entry(entry_name: String):
+func add_entry(summary: Dictionary):
     var entry = GAME_ENTRY.instance()
-    entry.get_node("GameName").set_text(entry_name)
+    entry.get_node("GameName").set_text(summary["name"])
+    entry.hint_tooltip = describe(summary)
     list_container.add_child(entry)
 
+# who played, when and how it went, for hovering over a save
+func describe(summary: Dictionary) -> String:
+    var white = summary["white"] if summary["white"] != "" else "white"
+    var black = summary["black"] if summary["black"] != "" else "black"
+    var description = "%s vs %s, %s" % [white, black, summary["result"]]
+    if summary["termination"] != "":
+        description += " by " + summary["termination"].replace("_", " ")
+    description += "\n%s, %d moves" % [summary["variant"].replace("_", " "), summary["move_count"]]
+    if summary["event"] != "":
+        description += "\n" + summary["event"]
+    if summary["date"] != "":
+        description += "\n" + summary["date"]
+    # only searches find out the opening and material
+    if summary.get("opening", "") != "":
+        description += "\n" + summary["opening"]
+    if summary.get("material", "") != "":
+        description += "\n" + summary["material"]
+    return description
+
 func reset_misc():
     owner.reset_draw_mode()
     get_node("/root/MouseInput").resume_input()
-    if gui.timer_enabled:
-        turn_timer.stop()
-        turn_timer.start()
-    gui.timer_ran_out = false
-    gui.timer_label._update_label()
     gui.set_actions_enabled(true)
     owner.held_piece = null
-    owner.is_white_turn = true
+    owner.is_white_turn = chess_director.get_fen(false).split(" ")[1] == "w"
+    owner.turn_label.set_player(owner.is_white_turn)
+    gui.timer_label._update_label()
     owner.winner = -2
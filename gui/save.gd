@@ -17,7 +17,10 @@ func _ready():
     cancel_button.connect("button_up", self, "_reset_buttons")
 
 func _save_button_clicked(text = ""):
-    if name_edit.get_text().length() != 0:
+    # names that can't be used say why when hovered over
+    var problem = chess_director.check_save_name(name_edit.get_text())
+    name_edit.hint_tooltip = problem
+    if problem == "":
         if chess_director.save_file_exists(name_edit.get_text()):
             name_edit.set_editable(false)
             save_button.set_disabled(true)
@@ -28,7 +31,7 @@ func _save_button_clicked(text = ""):
 func _save_game():
     var save_name = name_edit.get_text()
     chess_director.save_game(save_name)
-    games.add_entry(save_name)
+    games.populate_save_list()
 
 func _reset_buttons():
     overwrite_container.set_visible(false)
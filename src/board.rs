@@ -1,43 +1,31 @@
-extern crate dirs;
-
-use crate::{chesspiece::*, game::*};
-use std::{fs::{self, File}, fmt, rc::Rc, io::{self, Stdin, prelude::*}, path::PathBuf};
+use chess_core::{
+    database::{GameDatabase, GameQuery},
+    save::{self, SaveMetadata},
+    ChessPiece, Game, GameEvent, Piece, PieceType, RuleSet,
+};
 use godot::{
     init::{PropertyHint, PropertyUsage, SignalArgument, Signal, ClassBuilder},
     user_data::MutexData,
-    Node, Int32Array, Variant, GodotString, Vector2, NodePath, StringArray
+    Node, Int32Array, Variant, GodotString, Vector2, Vector2Array, NodePath, StringArray, Dictionary,
+    VariantArray
 };
 
-// ChessBoard struct
+// godot side of the board. All the rules live in chess_core, this just
+// forwards calls from godot and turns the game's events into signals
 pub struct ChessBoard {
-    board: Vec<Vec<Option<Rc<dyn ChessPiece>>>>,
-    player: bool,
-    input: Stdin,
-    score: [u8; 2],
-    winner: Option<i8>,
-    white_captured: String,
-    black_captured: String,
-    white_en_passant: Option<[usize; 2]>,
-    black_en_passant: Option<[usize; 2]>,
-    white_can_castle_left: bool,
-    white_can_castle_right: bool,
-    black_can_castle_left: bool,
-    black_can_castle_right: bool,
-    white_king_pos: [usize; 2],
-    black_king_pos: [usize; 2],
-    game_save: String,
-    turn_num: usize,
+    game: chess_core::ChessBoard,
+    // the index of the saves for find_games, opened the first time it's
+    // searched and kept up to date as saves are written and deleted
+    games: Option<GameDatabase>,
 }
 
-unsafe impl Send for ChessBoard {}
-
 impl godot::NativeClass for ChessBoard {
     type Base = Node;
     type UserData = MutexData<ChessBoard>;
     fn class_name() -> &'static str {
         "ChessBoard"
     }
-    fn init(owner: Self::Base) -> Self {
+    fn init(_owner: Self::Base) -> Self {
         Self::new()
     }
     // set up all the signals
@@ -49,6 +37,11 @@ impl godot::NativeClass for ChessBoard {
                 default: Variant::from_i64(0),
                 hint: PropertyHint::None,
                 usage: PropertyUsage::DEFAULT,
+            }, SignalArgument {
+                name: "reason",
+                default: Variant::from_str(""),
+                hint: PropertyHint::None,
+                usage: PropertyUsage::DEFAULT,
             }],
         });
         builder.add_signal(Signal {
@@ -90,12 +83,76 @@ impl godot::NativeClass for ChessBoard {
         builder.add_signal(Signal {
             name: "castle",
             args: &[SignalArgument {
+                name: "rook_start",
+                default: Variant::from_vector2(&Vector2::zero()),
+                hint: PropertyHint::None,
+                usage: PropertyUsage::DEFAULT,
+            }, SignalArgument {
+                name: "rook_dest",
+                default: Variant::from_vector2(&Vector2::zero()),
+                hint: PropertyHint::None,
+                usage: PropertyUsage::DEFAULT,
+            }, SignalArgument {
+                name: "king_dest",
+                default: Variant::from_vector2(&Vector2::zero()),
+                hint: PropertyHint::None,
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+        // an atomic capture blew up the piece that moved to centre and
+        // the pieces on squares
+        builder.add_signal(Signal {
+            name: "exploded",
+            args: &[SignalArgument {
+                name: "centre",
+                default: Variant::from_vector2(&Vector2::zero()),
+                hint: PropertyHint::None,
+                usage: PropertyUsage::DEFAULT,
+            }, SignalArgument {
+                name: "squares",
+                default: Variant::from_vector2_array(&Vector2Array::new()),
+                hint: PropertyHint::None,
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+        builder.add_signal(Signal {
+            name: "piece_dropped",
+            args: &[SignalArgument {
+                name: "is_white",
+                default: Variant::from_bool(false),
+                hint: PropertyHint::None,
+                usage: PropertyUsage::DEFAULT,
+            }, SignalArgument {
+                name: "piece_type",
+                default: Variant::from_str(""),
+                hint: PropertyHint::None,
+                usage: PropertyUsage::DEFAULT,
+            }, SignalArgument {
                 name: "position",
                 default: Variant::from_vector2(&Vector2::zero()),
                 hint: PropertyHint::None,
                 usage: PropertyUsage::DEFAULT,
             }],
         });
+        // the player's crazyhouse reserve changed, get_reserve has the new one
+        builder.add_signal(Signal {
+            name: "reserve_changed",
+            args: &[SignalArgument {
+                name: "is_white",
+                default: Variant::from_bool(false),
+                hint: PropertyHint::None,
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+        builder.add_signal(Signal {
+            name: "check",
+            args: &[SignalArgument {
+                name: "is_white",
+                default: Variant::from_bool(false),
+                hint: PropertyHint::None,
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
         builder.add_signal(Signal {
             name: "pawn_promoted",
             args: &[SignalArgument {
@@ -114,7 +171,6 @@ impl godot::NativeClass for ChessBoard {
                 usage: PropertyUsage::DEFAULT,
             }],
         });
-        // currently unused
         builder.add_signal(Signal {
             name: "error",
             args: &[SignalArgument {
@@ -138,863 +194,671 @@ impl godot::NativeClass for ChessBoard {
     }
 }
 
-// make ChessBoard a game
-impl Game for ChessBoard {
-    fn get_player(&self) -> bool {
-        self.player
-    }
-    fn set_player(&mut self, player: bool) {
-        self.player = player;
-    }
-    // reset fields
-    fn new_game(&mut self) {
-        self.player = true;
-        self.board = Self::new_board();
-        self.score = [0; 2];
-        self.winner = None;
-        self.white_captured = String::new();
-        self.black_captured = String::new();
-        self.white_en_passant = None;
-        self.black_en_passant = None;
-        self.white_can_castle_left = true;
-        self.white_can_castle_right = true;
-        self.black_can_castle_left = true;
-        self.black_can_castle_right = true;
-        self.white_king_pos = [4, 0];
-        self.black_king_pos = [4, 7];
-        self.turn_num = 0;
-    }
-    // called from godot whenever the player does something
-    // represents the course of a turn
-    unsafe fn take_turn(&mut self, mut owner: Node, start: [usize; 2], dest: [usize;2]) -> bool {
-        if let Some(piece) = (self.board[start[0]][start[1]]).clone() {
-            let mut pawn_promoted = false;
-            if !self.test_check(start, dest, self.player)
-            && piece.is_white() == self.player {
-                //let save_clone = self.game_save.clone();
-                if piece.test_move(start, dest, self) {
-                    let mut save_vec: Vec<&str> = self.game_save.lines().collect(); 
-                    // truncate save to current move after a move is made
-                    if save_vec.len()/17 > self.turn_num {
-                        let truncated_save_iter = save_vec.drain(0..self.turn_num*17);
-                        let truncated_save = truncated_save_iter.fold(
-                            String::new(), |mut save, line| {
-                                save.push_str(&format!("{}\n", line));
-                                save
-                            }
-                        );
-                        self.game_save = truncated_save;
-                    }
-                    self.turn_num += 1;
-                    let move_string = format!("{}: {} {}{} → {}{}",
-                                              self.turn_num,
-                                              piece,
-                                              (start[0]+97) as u8 as char,
-                                              start[1]+1,
-                                              (dest[0]+97) as u8 as char,
-                                              dest[1]+1);
+#[methods]
+impl ChessBoard {
+    // send the events of a move in the game being played to godot, and keep
+    // the game after every move in case godot crashes
+    unsafe fn emit_move_events(&self, owner: Node, events: Vec<GameEvent>) {
+        let turn_over = events.iter().any(|event| match event {
+            GameEvent::TurnEnded | GameEvent::GameOver { .. } => true,
+            _ => false,
+        });
+        self.emit_events(owner, events);
+        if turn_over {
+            if let Err(e) = self.game.autosave() {
+                self.emit_error(owner, &format!("couldn't autosave: {}", e));
+            }
+        }
+    }
+    // send the events of a turn to godot as signals. Loading and looking
+    // back through a game sends them too, which mustn't touch the autosave
+    unsafe fn emit_events(&self, mut owner: Node, events: Vec<GameEvent>) {
+        for event in events {
+            if let Some(entry) = event.log_entry() {
+                owner.emit_signal(
+                    GodotString::from_str("log_update"),
+                    &[Variant::from_str(&entry)]);
+            }
+            match event {
+                GameEvent::Captured { square, .. } => {
                     owner.emit_signal(
-                        GodotString::from_str("log_update"),
-                        &[Variant::from_str(&move_string)]);
-                    // we need to store the string representation of any captures that happen and
-                    // then write them later because of how pawn promotion is out of sync with the
-                    // rust turn order.
-                    let mut capture_string = String::new();
-                    // if piece is a pawn
-                    if piece.get_piece_type() == &PieceType::Pawn {
-                        if self.player {
-                            if self.black_en_passant.is_some() && dest == self.black_en_passant.unwrap() {
-                                capture_string = self.capture(owner, [dest[0], dest[1]-1]);
-                            } else if dest[1]-start[1] == 2 {
-                                self.white_en_passant = Some([dest[0], dest[1]-1]);
-                            } else {
-                                capture_string = self.capture(owner, dest);
-                            }
-                        } else {
-                            if self.white_en_passant.is_some() && dest == self.white_en_passant.unwrap() {
-                                capture_string = self.capture(owner, [dest[0], dest[1]+1]);
-                            } else if start[1]-dest[1] == 2 {
-                                self.black_en_passant = Some([dest[0], dest[1]+1]);
-                            } else {
-                                capture_string = self.capture(owner, dest);
-                            }
-                        }
-                    } else {
-                        // If piece is a king
-                        if piece.get_piece_type() == &PieceType::King {
-                            if dest[0] == 2 {
-                                if self.player && self.white_can_castle_left {
-                                    owner.emit_signal(
-                                        GodotString::from_str("castle"),
-                                        &[Variant::from_vector2(&Vector2::new(0.0, 0.0))]);
-                                    self.board[0][0] = None;
-                                    self.board[3][0] = Some(Rc::new(Rook::new(true)));
-                                } else if !self.player && self.black_can_castle_left {
-                                    owner.emit_signal(
-                                        GodotString::from_str("castle"),
-                                        &[Variant::from_vector2(&Vector2::new(0.0, 7.0))]);
-                                    self.board[0][7] = None;
-                                    self.board[3][7] = Some(Rc::new(Rook::new(false)));
-                                }
-                            } else if dest[0] == 6 {
-                                if self.player && self.white_can_castle_right {
-                                    owner.emit_signal(
-                                        GodotString::from_str("castle"),
-                                        &[Variant::from_vector2(&Vector2::new(7.0, 0.0))]);
-                                    self.board[7][0] = None;
-                                    self.board[5][0] = Some(Rc::new(Rook::new(true)));
-                                } else if !self.player && self.black_can_castle_right {
-                                    owner.emit_signal(
-                                        GodotString::from_str("castle"),
-                                        &[Variant::from_vector2(&Vector2::new(7.0, 7.0))]);
-                                    self.board[7][7] = None;
-                                    self.board[5][7] = Some(Rc::new(Rook::new(false)));
-                                }
-                            }
-                            if self.player {
-                                self.white_king_pos = dest;
-                                self.white_can_castle_left = false;                     
-                                self.white_can_castle_right = false;
-                            } else {
-                                self.black_king_pos = dest;
-                                self.black_can_castle_left = false;
-                                self.black_can_castle_right = false;
-                            }
-                        // if piece is a rook
-                        } else if piece.get_piece_type() == &PieceType::Rook {
-                            if start[0] == 0 {
-                                if self.player && self.white_can_castle_left {
-                                    self.white_can_castle_left = false;
-                                } else if !self.player && self.black_can_castle_left {
-                                    self.black_can_castle_left = false;
-                                }
-                            } else if start[0] == 7 {
-                                if self.player && self.white_can_castle_right {
-                                    self.white_can_castle_right = false;
-                                } else if !self.player && self.black_can_castle_right {
-                                    self.black_can_castle_right = false;
-                                }
-                            }
-                        }
-                        capture_string = self.capture(owner, dest);
-                    }
-                    self.board[dest[0]][dest[1]] = Some(piece.clone());
-                    self.board[start[0]][start[1]] = None;
-                    if piece.get_piece_type() == &PieceType::Pawn 
-                    && (dest[1] == 0 || dest[1] == 7) {
-                        pawn_promoted = true;
-                        self.upgrade_pawn_signal(owner, dest);
-                    }
-                    if self.player && self.black_en_passant.is_some() {
-                        self.black_en_passant = None;
-                    } else if !self.player && self.white_en_passant.is_some() {
-                        self.white_en_passant = None;
+                        GodotString::from_str("piece_captured"),
+                        &[Variant::from_vector2(&to_vector2(square))]);
+                },
+                GameEvent::Castled { rook_start, rook_dest, king_dest } => {
+                    owner.emit_signal(
+                        GodotString::from_str("castle"),
+                        &[Variant::from_vector2(&to_vector2(rook_start)),
+                        Variant::from_vector2(&to_vector2(rook_dest)),
+                        Variant::from_vector2(&to_vector2(king_dest))]);
+                },
+                GameEvent::Exploded { centre, squares } => {
+                    let mut square_array = Vector2Array::new();
+                    for square in squares {
+                        square_array.push(&to_vector2(square));
                     }
-                    // save file stuff
-                    // score
-                    self.game_save.push_str(&self.get_score());
-                    // white's allowed castling directions
-                    let white_castle_str = match (self.white_can_castle_left, 
-                                                  self.white_can_castle_right) {
-                        (true, true) => "b\n",
-                        (true, false) => "l\n",
-                        (false, true) => "r\n",
-                        _ => "\n",
-                    };
-                    self.game_save.push_str(white_castle_str);
-                    // black's allowed castling directions
-                    let black_castle_str = match (self.black_can_castle_left,
-                                                  self.black_can_castle_right) {
-                        (true, true) => "b\n",
-                        (true, false) => "l\n",
-                        (false, true) => "r\n",
-                        _ => "\n",
-                    };
-                    self.game_save.push_str(black_castle_str);
-                    // white's en passant col
-                    let white_en_passant_string = match (self.white_en_passant) {
-                        Some(space) => format!("{}\n", space[0]),
-                        None => String::from("\n"), 
-                    };
-                    // black's en passant col
-                    self.game_save.push_str(&white_en_passant_string);
-                    let black_en_passant_string = match (self.black_en_passant) {
-                        Some(space) => format!("{}\n", space[0]),
-                        None => String::from("\n"),
-                    };
-                    self.game_save.push_str(&black_en_passant_string);
-                    // most recent move
-                    self.game_save.push_str(&move_string);
-                    self.game_save.push_str(&capture_string);
-                    // tell godot to refresh the list of moves
+                    owner.emit_signal(
+                        GodotString::from_str("exploded"),
+                        &[Variant::from_vector2(&to_vector2(centre)),
+                        Variant::from_vector2_array(&square_array)]);
+                },
+                GameEvent::Dropped { piece, square, .. } => {
+                    owner.emit_signal(
+                        GodotString::from_str("piece_dropped"),
+                        &[Variant::from_bool(piece.is_white()),
+                        Variant::from_str(piece.kind().as_str()),
+                        Variant::from_vector2(&to_vector2(square))]);
+                },
+                // the reserves are shown with the score
+                GameEvent::ReserveChanged { is_white } => {
+                    owner.emit_signal(
+                        GodotString::from_str("reserve_changed"),
+                        &[Variant::from_bool(is_white)]);
+                    owner.emit_signal(
+                        GodotString::from_str("score_update"),
+                        &[Variant::from_str(&self.score_text())]);
+                },
+                GameEvent::PromotionPending { square } => {
+                    owner.emit_signal(
+                        GodotString::from_str("pawn_promoted"),
+                        &[Variant::from_vector2(&to_vector2(square))]);
+                },
+                GameEvent::Check { is_white } => {
+                    owner.emit_signal(
+                        GodotString::from_str("check"),
+                        &[Variant::from_bool(is_white)]);
+                },
+                GameEvent::GameOver { winner, reason } => {
+                    owner.emit_signal(
+                        GodotString::from_str("game_over"),
+                        &[Variant::from_i64(winner as i64),
+                        Variant::from_str(reason.as_str())]);
+                },
+                GameEvent::ScoreChanged { .. } => {
+                    owner.emit_signal(
+                        GodotString::from_str("score_update"),
+                        &[Variant::from_str(&self.score_text())]);
+                },
+                GameEvent::MovesUpdated { turns } => {
                     owner.emit_signal(
                         GodotString::from_str("update_moves"),
-                        &[Variant::from_i64(self.turn_num as i64)]);
-                    if !pawn_promoted {
-                        self.test_checkmate_stalemate(owner);
+                        &[Variant::from_i64(turns as i64)]);
+                },
+                // the log of a loaded game is sent all at once
+                GameEvent::TurnLoaded { turn } => {
+                    for entry in self.game.get_log(turn) {
                         owner.emit_signal(
                             GodotString::from_str("log_update"),
-                            &[Variant::from_str("\n")]);
-                        self.game_save.push_str("\n");
-                        // the board
-                        self.game_save.push_str(&format!("{}", self));
+                            &[Variant::from_str(&entry)]);
                     }
-                    return true;
-                }
+                },
+                _ => {},
             }
         }
-        false
     }
-    fn print_game(&self) {
-        print!("{}[2J", 27 as char);
-        println!("{}", self.get_score());
-        println!("{}", self);
+    // the score, plus the checks each side has given in three-check and
+    // the pieces each side can drop in crazyhouse
+    fn score_text(&self) -> String {
+        let mut score = self.game.get_score();
+        if self.game.get_variant() == chess_core::Variant::ThreeCheck {
+            let checks = self.game.get_checks();
+            score.push_str(&format!("White Checks: {}\nBlack Checks: {}\n", checks[0], checks[1]));
+        }
+        if self.game.get_rules().has_drops() {
+            for (name, is_white) in [("White", true), ("Black", false)].iter() {
+                let mut reserve = String::new();
+                for (kind, count) in PieceType::ALL.iter().zip(self.game.get_reserve(*is_white).iter()) {
+                    reserve.push_str(&Piece::new(*kind, *is_white).as_str().repeat(*count as usize));
+                }
+                score.push_str(&format!("{} Reserve: {}\n", name, reserve));
+            }
+        }
+        score
     }
-    fn get_score(&self) -> String {
-        format!("White Score: {} \n{}\nBlack Score: {}\n{}\n", 
-                self.score[0], self.white_captured,
-                self.score[1], self.black_captured)
+    // tell godot something went wrong
+    unsafe fn emit_error(&self, mut owner: Node, message: &str) {
+        owner.emit_signal(
+            GodotString::from_str("error"),
+            &[Variant::from_str(message)]);
     }
-    fn get_winner(&self) -> Option<i8> {
-        self.winner
+    // keep the open index up to date with a save that's been written
+    fn save_written(&mut self, save_name: &str) {
+        if let Some(games) = &mut self.games {
+            games.save_written(save_name).ok();
+        }
     }
-    fn end(&self, winner: i8) {
-        if winner > 0 {
-            println!("White has won!");
-        } else if winner < 0 {
-            println!("Black has won!");
-        } else {
-            println!("Draw!");
+    // start a new game of the given variant, like "standard" or "king_of_the_hill"
+    #[export]
+    unsafe fn reset_game(&mut self, owner: Node, variant: GodotString) {
+        match chess_core::Variant::from_name(&variant.to_string()) {
+            Some(variant) => self.game.set_variant(variant),
+            None => self.emit_error(owner, &format!("{} isn't a variant", variant.to_string())),
         }
+        self.game.new_game();
+        // some variants like horde don't start from the usual setup
+        self.show_board(owner);
     }
-}
-// allow ChessBoard to be represented as a string.
-// used to be for displaying the board, but now it's used
-// for saving the game into a file to be loaded later
-impl fmt::Display for ChessBoard {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for row in 0..8 {
-            for col in 0..8 {
-                if let Some(chess_piece) = &self.board[col][row] {
-                    write!(f, "{}", chess_piece)?;
-                } else {
-                    write!(f, " ")?;
-                }
-            }
-            writeln!(f)?;
+    // the name of the variant being played
+    #[export]
+    fn get_variant(&self, _owner: Node) -> GodotString {
+        GodotString::from_str(self.game.get_variant().as_str())
+    }
+    // pick the rules for the next new game by name. The variants are all
+    // rule sets, and so is anything added with chess_core::add_rule_set
+    #[export]
+    unsafe fn set_rule_set(&mut self, owner: Node, name: GodotString) {
+        match chess_core::rule_set(&name.to_string()) {
+            Some(rules) => self.game.set_rules(rules),
+            None => self.emit_error(owner, &format!("{} isn't a rule set", name.to_string())),
         }
-        write!(f, "")
     }
-}
-#[methods]
-impl ChessBoard {
-    // test for checkmate and stalemate and tell godot if anything was detected
-    unsafe fn test_checkmate_stalemate(&mut self, mut owner: Node) {
-        if self.test_stalemate(!self.player) {
-            if self.test_checkmate(!self.player) { 
-                if self.player {
-                    owner.emit_signal(
-                        GodotString::from_str("game_over"),
-                        &[Variant::from_i64(1)]);
-                } else {
-                    owner.emit_signal(
-                        GodotString::from_str("game_over"),
-                        &[Variant::from_i64(-1)]);
-                }
-                self.game_save.push_str(" checkmate");
-            } else {
-                owner.emit_signal(
-                    GodotString::from_str("game_over"),
-                    &[Variant::from_i64(0)]);
-                self.game_save.push_str(" stalemate");
-            }
+    // the name of the rules being played by
+    #[export]
+    fn get_rule_set(&self, _owner: Node) -> GodotString {
+        GodotString::from_str(self.game.get_rules().name())
+    }
+    // the names of every rule set godot can pick from
+    #[export]
+    fn get_rule_set_names(&self, _owner: Node) -> StringArray {
+        let mut names = StringArray::new();
+        for name in chess_core::rule_set_names() {
+            names.push(&GodotString::from_str(&name));
         }
+        names
     }
-    // just a call to new_game() that is  exposed to godot
+    // the names of the pieces a pawn can turn into under the current rules
     #[export]
-    fn reset_game(&mut self, owner: Node) {
-        self.new_game();
+    fn get_promotion_options(&self, _owner: Node) -> StringArray {
+        let mut names = StringArray::new();
+        for kind in self.game.get_promotion_options() {
+            names.push(&GodotString::from_str(kind.as_str()));
+        }
+        names
     }
-    // movement method, but this one is exposed to godot and emits signals
+    // give odds from the next new game on, like "knight" or "pawn_and_move".
+    // "none" goes back to an even game
     #[export]
-    unsafe fn try_move(&mut self, owner: Node, start: Int32Array, dest: Int32Array) {
-        let start = [start.get(0) as usize, start.get(1) as usize];
-        let dest = [dest.get(0) as usize, dest.get(1) as usize];
-        owner.clone().emit_signal(
-            GodotString::from_str("move_is_legal"),
-            &[Variant::from_bool(self.next_turn(owner, start, dest))]);
+    unsafe fn set_odds(&mut self, owner: Node, odds: GodotString, giver_is_white: bool) {
+        match chess_core::Odds::from_name(&odds.to_string()) {
+            Some(odds) => self.game.set_odds(odds, giver_is_white),
+            None => self.emit_error(owner, &format!("{} isn't a kind of odds", odds.to_string())),
+        }
     }
-    // write the contents of game_save field to a file
+    // the odds being given and who's giving them
     #[export]
-    fn save_game(&self, owner: Node, save_name: GodotString) {
-        let save_name = save_name.to_string();
-        let data_dir = if let Some(dir) = Self::get_data_dir() {
-            dir
+    fn get_odds(&self, _owner: Node) -> Dictionary {
+        let (odds, giver_is_white) = self.game.get_odds();
+        let mut dict = Dictionary::new();
+        dict.set(&Variant::from_str("odds"), &Variant::from_str(odds.as_str()));
+        dict.set(&Variant::from_str("giver_is_white"), &Variant::from_bool(giver_is_white));
+        dict
+    }
+    // the seconds each player starts the next new game with, which can be
+    // different for time odds. Zero or less for either plays without clocks
+    #[export]
+    fn set_time_control(&mut self, _owner: Node, white_seconds: f64, black_seconds: f64) {
+        let time_control = if white_seconds > 0.0 && black_seconds > 0.0 {
+            Some([(white_seconds * 1000.0) as u64, (black_seconds * 1000.0) as u64])
         } else {
-            return;
+            None
         };
-        if !data_dir.exists() {
-            fs::create_dir_all(&data_dir);
-        }
-        if let Ok(mut save_file) = File::create(data_dir.join(&save_name)) {
-            save_file.write(self.game_save.as_bytes());
+        self.game.set_time_control(time_control);
+    }
+    // run the clock of the player to move, call this every frame with the
+    // frame's delta
+    #[export]
+    unsafe fn tick(&mut self, owner: Node, delta: f64) {
+        let events = self.game.tick((delta * 1000.0) as u64);
+        self.emit_move_events(owner, events);
+    }
+    // seconds the player has left, or -1 if the game isn't timed
+    #[export]
+    fn get_clock(&self, _owner: Node, is_white: bool) -> f64 {
+        match self.game.get_clock(is_white) {
+            Some(ms) => ms as f64 / 1000.0,
+            None => -1.0,
         }
     }
-    // delete a saved game
+    // how many files and ranks the board has, capablanca and gothic are 10x8
     #[export]
-    fn delete_save(&self, owner: Node, save_name: GodotString) {
-        let save_name = save_name.to_string();
-        let save_path = if let Some(dir) = Self::get_data_dir() {
-            dir.join(save_name)
+    fn get_board_size(&self, _owner: Node) -> Vector2 {
+        let [width, height] = self.game.get_size();
+        Vector2::new(width as f32, height as f32)
+    }
+    // start a chess960 game from the given position, or a random one if
+    // the index is negative, and set up the godot pieces to match
+    #[export]
+    unsafe fn reset_game_960(&mut self, owner: Node, index: i64) {
+        let index = if index < 0 {
+            chess_core::random_chess960_index()
         } else {
-            return;
+            index as u16
         };
-        fs::remove_file(save_path);
+        self.game.new_chess960(index);
+        self.show_board(owner);
     }
-    // check if a file exists so godot knows whether or not to
-    // prompt the user to overwrite
+    // the current position as FEN, with the rook files for castling if shredder is set
     #[export]
-    fn save_file_exists(&self, owner: Node, save_name: GodotString) -> bool {
-        let save_name = save_name.to_string();
-        let save_dir = if let Some(dir) = Self::get_data_dir() {
-            dir
-        } else {
-            return false;
+    fn get_fen(&self, _owner: Node, shredder: bool) -> GodotString {
+        GodotString::from_str(&self.game.to_fen(shredder))
+    }
+    // start a new game from a FEN position
+    #[export]
+    unsafe fn load_fen(&mut self, owner: Node, fen: GodotString) {
+        match self.game.load_fen(&fen.to_string()) {
+            Ok(()) => self.show_board(owner),
+            Err(e) => self.emit_error(owner, &e),
+        }
+    }
+    // board editor. Pieces are placed and removed freely, then
+    // start_from_position checks the position and plays from it
+    // put a piece on a square, replacing anything already there
+    #[export]
+    unsafe fn place_piece(&mut self, owner: Node, square: Vector2, piece_type: GodotString, is_white: bool) {
+        let kind = match PieceType::from_name(&piece_type.to_string()) {
+            Some(kind) => kind,
+            None => return self.emit_error(owner, &format!("{} isn't a piece", piece_type.to_string())),
         };
-        if save_dir.exists() && save_dir.join(save_name).exists() {
-            return true;
+        match self.game.place_piece(to_square(square), Some(Piece::new(kind, is_white))) {
+            Ok(()) => self.show_board(owner),
+            Err(e) => self.emit_error(owner, &e),
         }
-        false
     }
-    // load the contents of a file into the game_save field
     #[export]
-    unsafe fn load_game(&mut self, mut owner: Node, save_name: GodotString) {
-        let save_name = save_name.to_string();
-        let save_path = if let Some(dir) = Self::get_data_dir() {
-            dir.join(save_name)
+    unsafe fn remove_piece(&mut self, owner: Node, square: Vector2) {
+        match self.game.place_piece(to_square(square), None) {
+            Ok(()) => self.show_board(owner),
+            Err(e) => self.emit_error(owner, &e),
+        }
+    }
+    #[export]
+    unsafe fn clear_board(&mut self, owner: Node) {
+        self.game.clear_board();
+        self.show_board(owner);
+    }
+    #[export]
+    fn set_side_to_move(&mut self, _owner: Node, is_white: bool) {
+        self.game.set_player(is_white);
+    }
+    // the files of the rooks the player can castle with, left then right.
+    // A negative file takes away castling on that side
+    #[export]
+    unsafe fn set_castling(&mut self, owner: Node, is_white: bool, left_file: i64, right_file: i64) {
+        let file = |file: i64| if file < 0 {
+            None
         } else {
-            return;
+            Some(file as usize)
         };
-        let save_file = File::open(save_path);
-        match save_file {
-            Err(e) => {
-                owner.emit_signal(
-                    GodotString::from_str("error"),
-                    &[Variant::from_str(&e.to_string())]);
-            },
-            Ok(mut file) => {
-                let mut save_contents = String::new();
-                file.read_to_string(&mut save_contents);
-                self.game_save = save_contents;
-            },
+        if let Err(e) = self.game.set_castle_rooks(is_white, [file(left_file), file(right_file)]) {
+            self.emit_error(owner, &e);
         }
     }
-    // send godot the names of the avaiable saved games
+    // the square a pawn can be taken en passant on, or a negative square for none
     #[export]
-    fn get_save_names(&self, owner: Node) -> StringArray {
-        let mut names = StringArray::new();
-        let save_dir = if let Some(dir) = Self::get_data_dir() {
-            dir
+    unsafe fn set_en_passant(&mut self, owner: Node, square: Vector2) {
+        let square = if square.x < 0.0 || square.y < 0.0 {
+            None
         } else {
-            return names;
+            Some(to_square(square))
         };
-        if let Ok(entries) = save_dir.read_dir() {
-            for entry in entries {
-                let entry = entry.unwrap();
-                if let Some(name) = entry.file_name().to_str() {
-                    names.push(&GodotString::from_str(name));
-                }
-            }
+        if let Err(e) = self.game.set_en_passant(square) {
+            self.emit_error(owner, &e);
         }
-        names
     }
-    // return how many turns the loaded game has
+    // what's wrong with the position on the board, so the editor can
+    // show it before starting a game from it
     #[export]
-    fn get_num_turns(&self, owner: Node) -> usize {
-        self.game_save.lines().collect::<Vec<&str>>().len()/17
+    fn get_position_problems(&mut self, _owner: Node) -> StringArray {
+        let mut problems = StringArray::new();
+        for problem in self.game.validate_position() {
+            problems.push(&GodotString::from_str(&problem.to_string()));
+        }
+        problems
     }
-    // get the path to where saves are stored (platform dependant)
-    fn get_data_dir() -> Option<PathBuf> {
-        if let Some(dir) = dirs::data_dir() {
-            Some(dir.join("dexter_chess_saves/"))
-        } else {
-            None
+    // start a new game from the position that was set up
+    #[export]
+    unsafe fn start_from_position(&mut self, owner: Node) {
+        match self.game.start_from_position() {
+            Ok(()) => self.show_board(owner),
+            Err(e) => self.emit_error(owner, &e),
         }
     }
-    // return the path to the data dir as a string so godot can display it
+    // movement method, but this one is exposed to godot and emits signals
     #[export]
-    fn data_dir_string(&self, owner: Node) -> GodotString {
-        GodotString::from_str(Self::get_data_dir().unwrap().to_str().unwrap())
+    unsafe fn try_move(&mut self, mut owner: Node, start: Int32Array, dest: Int32Array) {
+        let start = [start.get(0) as usize, start.get(1) as usize];
+        let dest = [dest.get(0) as usize, dest.get(1) as usize];
+        let events = self.game.next_turn(start, dest);
+        let is_legal = events.is_some();
+        self.emit_move_events(owner, events.unwrap_or_default());
+        owner.emit_signal(
+            GodotString::from_str("move_is_legal"),
+            &[Variant::from_bool(is_legal)]);
     }
-    // load a specific turn from the game
+    // drop a piece from the reserve in crazyhouse. The piece type is the
+    // name godot uses for it, like "knight", and drops are typed into the
+    // move entry like N@e4
     #[export]
-    unsafe fn load_turn(&mut self, mut owner: Node, turn: usize) {
-        // plus 1 since the current move will be AFTER the turn that
-        // gets loaded
-        self.turn_num =  turn+1;
-        let mut piece_controller = owner
-            .get_parent()
-            .unwrap()
-            .get_node(NodePath::from_str("Pieces"))
-            .unwrap();
-        // wrapper over godot method to make new pieces
-        let mut godot_instance_piece = |is_white: bool, piece_type: &str, pos: [usize; 2]| {
-            piece_controller.call(
-                GodotString::from_str("instance_piece"),
-                &[Variant::from_bool(is_white), 
-                Variant::from_str(piece_type), 
-                Variant::from_vector2(&Vector2::new(pos[0] as f32, pos[1] as f32))]);
-        };
-        let mut save_vec: Vec<&str> = self.game_save.lines().collect(); 
-        let start_index = 17*turn;
-        // send previous log entries
-        for log_entry in 0..=turn {
-            owner.emit_signal(
-                GodotString::from_str("log_update"),
-                &[Variant::from_str(&format!("{}\n", save_vec[log_entry*17+8]))]);
-        }
-        let this_turn: Vec<&str> = save_vec.drain(start_index..(start_index+17)).collect();
-        // update the score
-        // read into the rust code's fields
-        self.score[0] = this_turn[0]
-            .trim()
-            .split(" ")
-            .last()
-            .unwrap()
-            .parse::<u8>()
-            .unwrap();
-        self.score[1] = this_turn[2]
-            .trim()
-            .split(" ")
-            .last()
-            .unwrap()
-            .parse::<u8>()
-            .unwrap();
-        self.white_captured = String::from(this_turn[1]);
-        self.black_captured = String::from(this_turn[3]);
-        // read into godot
-        let score = format!("{}\n{}\n{}\n{}\n", 
-                            this_turn[0], this_turn[1], this_turn[2], this_turn[3]);
+    unsafe fn try_drop(&mut self, mut owner: Node, piece_type: GodotString, square: Vector2) {
+        let piece_type = piece_type.to_string();
+        let kind = PieceType::ALL.iter().copied().find(|kind| kind.as_str() == piece_type);
+        let events = kind.and_then(|kind| {
+            self.game.next_drop(kind, to_square(square))
+        });
+        let is_legal = events.is_some();
+        self.emit_move_events(owner, events.unwrap_or_default());
         owner.emit_signal(
-            GodotString::from_str("score_update"),
-            &[Variant::from_str(&score)]);
-        // set the player
-        if turn%2 == 0 {
-            self.player = false;
-        } else {
-            self.player = true;
-        }
-        // set white player's castle directions
-        match this_turn[4] {
-            "l" | "b" => self.white_can_castle_left = true,
-            "r" | "b" => self.white_can_castle_right = true,
-            _ => {},
-        }
-        // set black player's castle directions
-        match this_turn[5] {
-            "l" | "b" => self.black_can_castle_left = true,
-            "r" | "b" => self.black_can_castle_right = true,
-            _ => {},
-        }
-        // white en passant
-        if let Ok(col) = this_turn[6].parse() {
-            self.white_en_passant = Some([col, 2]);
-        } else {
-            self.white_en_passant = None;
+            GodotString::from_str("move_is_legal"),
+            &[Variant::from_bool(is_legal)]);
+    }
+    // how many of each piece the player has in their crazyhouse reserve,
+    // keyed by the names godot uses for the pieces
+    #[export]
+    fn get_reserve(&self, _owner: Node, is_white: bool) -> Dictionary {
+        let mut reserve = Dictionary::new();
+        for (kind, count) in PieceType::ALL.iter().zip(self.game.get_reserve(is_white).iter()) {
+            reserve.set(&Variant::from_str(kind.as_str()), &Variant::from_i64(*count as i64));
         }
-        // black en passant
-        if let Ok(col) = this_turn[7].parse() {
-            self.black_en_passant = Some([col, 5]);
-        } else {
-            self.black_en_passant = None;
-        }
-        // load the board
-        for row in 0..8 {
-            let chars = this_turn[9+row].chars();
-            for (col, char) in chars.enumerate() {
-                let piece: Option<Rc<dyn ChessPiece>> = match char {
-                    '♙' => {
-                        godot_instance_piece(true, "pawn", [col, row]);
-                        Some(Rc::new(Pawn::new(true)))
-                    },
-                    '♘' => {
-                        godot_instance_piece(true, "knight", [col, row]);
-                        Some(Rc::new(Knight::new(true)))
-                    },
-                    '♗' => {
-                        godot_instance_piece(true, "bishop", [col, row]);
-                        Some(Rc::new(Bishop::new(true)))
-                    },
-                    '♖' => {
-                        godot_instance_piece(true, "rook", [col, row]);
-                        Some(Rc::new(Rook::new(true)))
-                    },
-                    '♕' => {
-                        godot_instance_piece(true, "queen", [col, row]);
-                        Some(Rc::new(Queen::new(true)))
-                    },
-                    '♔' => {
-                        godot_instance_piece(true, "king", [col, row]);
-                        Some(Rc::new(King::new(true)))
-                    },
-                    '♟' => {
-                        godot_instance_piece(false, "pawn", [col, row]);
-                        Some(Rc::new(Pawn::new(false)))
-                    },
-                    '♞' => {
-                        godot_instance_piece(false, "knight", [col, row]);
-                        Some(Rc::new(Knight::new(false)))
-                    },
-                    '♝' => {
-                        godot_instance_piece(false, "bishop", [col, row]);
-                        Some(Rc::new(Bishop::new(false)))
-                    },
-                    '♜' => {
-                        godot_instance_piece(false, "rook", [col, row]);
-                        Some(Rc::new(Rook::new(false)))
-                    },
-                    '♛' => {
-                        godot_instance_piece(false, "queen", [col, row]);
-                        Some(Rc::new(Queen::new(false)))
-                    },
-                    '♚' => {
-                        godot_instance_piece(false, "king", [col, row]);
-                        Some(Rc::new(King::new(false)))
-                    },
-                    _ => None,
-                };
-                self.board[col][row] = piece;
-            }
+        reserve
+    }
+    // write the contents of game_save field to a file
+    #[export]
+    unsafe fn save_game(&mut self, owner: Node, save_name: GodotString) {
+        let save_name = save_name.to_string();
+        match self.game.save_game(&save_name) {
+            Ok(()) => self.save_written(&save_name),
+            Err(e) => self.emit_error(owner, &e.to_string()),
         }
-        let move_line = this_turn[8].trim();
-        // make the game end popup show up in godot
-        if move_line.contains("checkmate") {
-            let winner = if self.player {
-                -1
-            } else {
-                1
-            };
-            owner.emit_signal(
-                GodotString::from_str("game_over"),
-                &[Variant::from_i64(winner)]);
-        } else if move_line.contains("stalemate") {
-        } else if move_line.contains("draw") {
-        }
-    }
-    unsafe fn _init(owner: Node) -> Self {
-        Self::new()
     }
-    // constructor
-    pub fn new() -> Self {
-        ChessBoard {
-            board: Self::new_board(),
-            player: true,
-            input: io::stdin(),
-            score: [0; 2],
-            winner: None,
-            white_captured: String::new(),
-            black_captured: String::new(),
-            white_en_passant: None,
-            black_en_passant: None,
-            white_can_castle_left: true,
-            white_can_castle_right: true,
-            black_can_castle_left: true,
-            black_can_castle_right: true,
-            white_king_pos: [4, 0],
-            black_king_pos: [4, 7],
-            game_save: String::new(),
-            turn_num: 0,
-        }
-    }
-    // test a move and see (regarless of actual legality) if it will put
-    // the current player's king in check
-    pub fn test_check(
-        &mut self, 
-        start: [usize; 2], 
-        dest: [usize; 2], 
-        is_white: bool) -> bool {
-        let start_piece = self.board[start[0]][start[1]].clone();
-        let dest_piece = self.board[dest[0]][dest[1]].clone();
-        self.board[dest[0]][dest[1]] = start_piece.clone();
-        self.board[start[0]][start[1]] = None;
-        // we need to check a different position if it's the king that moved
-        let king_pos = if let Some(piece) = start_piece.clone() {
-            if piece.get_piece_type() == &PieceType::King {
-                dest
-            } else {
-                if is_white {
-                    self.white_king_pos
-                } else {
-                    self.black_king_pos
-                }
-            }
-        } else {
-            if is_white {
-                self.white_king_pos
-            } else {
-                self.black_king_pos
-            }
-        };
-        let in_check = self.is_threatened(king_pos, is_white);
-        self.board[start[0]][start[1]] = start_piece;
-        self.board[dest[0]][dest[1]] = dest_piece;
-        in_check
-    }
-    // test checkmate on one of the two kings
-    pub fn test_checkmate(&mut self, is_white: bool) -> bool {
-        let king_pos = if is_white {
-            self.white_king_pos
-        } else {
-            self.black_king_pos
-        };
-        if self.is_threatened(king_pos, is_white) {
-            return true;
-        }
-        false
-    }
-    // test if the king is in stalemate
-    pub fn test_stalemate(&mut self, is_white: bool) -> bool {
-        for row in 0..8 {
-            for col in 0..8 {
-                if let Some(piece) = self.board[col][row].clone() {
-                    if piece.is_white() == is_white {
-                        if self.test_block([col, row], piece) {
-                            return false;
-                        }
-                    }
+    // delete a saved game
+    #[export]
+    unsafe fn delete_save(&mut self, owner: Node, save_name: GodotString) {
+        let save_name = save_name.to_string();
+        match save::delete_save(&save_name) {
+            Ok(()) => {
+                if let Some(games) = &mut self.games {
+                    games.save_deleted(&save_name).ok();
                 }
-            }
+            },
+            Err(e) => self.emit_error(owner, &e.to_string()),
         }
-        true
     }
-    // test if a piece can block check for resolving checkmate
-    fn test_block(&mut self, start: [usize; 2], piece: Rc<dyn ChessPiece>) -> bool {
-        for row in 0..8 {
-            for col in 0..8 {
-                if piece.test_move(start, [col, row], self) {
-                    if !self.test_check(start, [col, row], piece.is_white()) {
-                        return true;
-                    }
-                }
-            }
+    // check if a file exists so godot knows whether or not to
+    // prompt the user to overwrite
+    #[export]
+    fn save_file_exists(&self, _owner: Node, save_name: GodotString) -> bool {
+        save::save_exists(&save_name.to_string())
+    }
+    // load the contents of a file into the game_save field, and tell godot
+    // if it worked. The game carries on as it was if it didn't
+    #[export]
+    unsafe fn load_game(&mut self, owner: Node, save_name: GodotString) -> bool {
+        match self.game.load_game(&save_name.to_string()) {
+            Ok(()) => {
+                // old saves get written back in the new format when they're loaded
+                self.save_written(&save_name.to_string());
+                true
+            },
+            Err(e) => {
+                self.emit_error(owner, &format!("couldn't load {}: {}", save_name.to_string(), e));
+                false
+            },
         }
-        false
-    }
-    // capture a piece (remove it from board and increment score)
-    pub unsafe fn capture(&mut self, mut owner: Node, space: [usize; 2]) -> String {
-        if let Some(piece) = &self.board[space[0]][space[1]] {
-            if piece.is_white() {
-                self.score[1] += piece.get_points();
-                self.black_captured.push_str(piece.as_str());
-            } else {
-                self.score[0] += piece.get_points();
-                self.white_captured.push_str(piece.as_str());
-            }
-            owner.emit_signal(
-                GodotString::from_str("piece_captured"),
-                &[Variant::from_vector2(&Vector2::new(space[0] as f32, space[1] as f32))]);
-            let capture_string = format!(" captures {}", piece);
-            owner.emit_signal(
-                GodotString::from_str("log_update"),
-                &[Variant::from_str(&capture_string)]);
-            self.board[space[0]][space[1]] = None;
-            owner.emit_signal(
-                GodotString::from_str("score_update"),
-                &[Variant::from_str(self.get_score())]);
-            return capture_string;
-        }
-        String::new()
-    }
-    // check if a square is threatened
-    pub fn is_threatened(&mut self, space: [usize; 2], is_white: bool) -> bool {
-        // Pawns won't return that they can move to a square they threaten unless
-        // there is a piece on it, so we create a "dummy" piece and then delete it
-        let space_is_empty = if self.board[space[0]][space[1]].is_none() {
-            self.board[space[0]][space[1]] = Some(Rc::new(Pawn::new(is_white)));
-            true
-        } else {
-            false
-        };
-        for row in 0..8 {
-            for col in 0..8 {
-                if let Some(piece) = self.board[col][row].clone() {
-                    if piece.is_white() != is_white {
-                        if piece.test_move([col, row], space, self) {
-                            if space_is_empty {
-                                self.board[space[0]][space[1]] = None;
-                            }
-                            return true;
-                        }
-                    }
-                }
+    }
+    // check if there's an unfinished game from last time to offer to restore
+    #[export]
+    fn has_autosave(&self, _owner: Node) -> bool {
+        save::recovery_exists()
+    }
+    // carry on with the unfinished game from last time, from its last move
+    #[export]
+    unsafe fn restore_autosave(&mut self, owner: Node) {
+        if let Err(e) = self.game.restore_autosave() {
+            return self.emit_error(owner, &e.to_string());
+        }
+        let turns = self.game.get_num_turns();
+        if turns > 0 {
+            match self.game.load_turn(turns - 1) {
+                Ok(events) => {
+                    self.show_board(owner);
+                    self.emit_events(owner, events);
+                },
+                Err(e) => self.emit_error(owner, &e.to_string()),
             }
         }
-        if space_is_empty {
-            self.board[space[0]][space[1]] = None;
+    }
+    // throw away the unfinished game from last time
+    #[export]
+    unsafe fn clear_autosave(&self, owner: Node) {
+        if let Err(e) = save::clear_recovery() {
+            self.emit_error(owner, &e.to_string());
         }
-        false
     }
-    // accessors and mutators for king positions
-    pub fn get_white_king_pos(&self) -> [usize; 2] {
-        self.white_king_pos
+    // read the fairy pieces file in the data dir so its pieces can be used
+    // in FEN positions, and send godot their names
+    #[export]
+    unsafe fn load_fairy_pieces(&mut self, owner: Node) -> StringArray {
+        let mut names = StringArray::new();
+        match chess_core::load_fairy_pieces() {
+            Ok(kinds) => {
+                for kind in kinds {
+                    names.push(&GodotString::from_str(kind.as_str()));
+                }
+            },
+            Err(e) => self.emit_error(owner, &e),
+        }
+        names
     }
-    pub fn get_black_king_pos(&self) -> [usize; 2] {
-        self.black_king_pos
+    // send godot the names of the avaiable saved games
+    #[export]
+    fn get_save_names(&self, _owner: Node) -> StringArray {
+        let mut names = StringArray::new();
+        for name in save::save_names() {
+            names.push(&GodotString::from_str(&name));
+        }
+        names
     }
-    pub fn set_white_king_pos(&mut self, pos: [usize; 2]) {
-        self.white_king_pos = pos;
+    // everything the saves say about themselves, for the games tab. Each
+    // save is a dictionary with its name and metadata like the players,
+    // date, result and how many moves were made
+    #[export]
+    fn get_save_summaries(&self, _owner: Node) -> VariantArray {
+        let mut summaries = VariantArray::new();
+        for (name, metadata) in save::save_summaries() {
+            summaries.push(&Variant::from_dictionary(&summary(&name, &metadata)));
+        }
+        summaries
     }
-    pub fn set_black_king_pos(&mut self, pos: [usize; 2]) {
-        self.black_king_pos = pos;
+    // search the saved games. The query is a dictionary with any of
+    // player, date_from, date_to, result, variant, opening and material as
+    // strings, and min_moves and max_moves as numbers. Games come back
+    // newest first like get_save_summaries, along with their opening, like
+    // "e2e4 e7e5", and the material left at the end, like "KRPPvKR"
+    #[export]
+    fn find_games(&mut self, _owner: Node, query: Dictionary) -> VariantArray {
+        // keys that are missing or empty, or counts of 0, match every game
+        let value = |key: &str| Some(query.get(&Variant::from_str(key))).filter(|value| !value.is_nil());
+        let text = |key: &str| value(key).map(|value| value.to_string()).filter(|text| !text.is_empty());
+        let count = |key: &str| {
+            value(key).map(|value| value.to_i64()).filter(|count| *count > 0).map(|count| count as usize)
+        };
+        let query = GameQuery {
+            player: text("player"),
+            date_from: text("date_from"),
+            date_to: text("date_to"),
+            result: text("result"),
+            variant: text("variant"),
+            opening: text("opening"),
+            min_moves: count("min_moves"),
+            max_moves: count("max_moves"),
+            material: text("material"),
+        };
+        let mut games = VariantArray::new();
+        for record in self.games.get_or_insert_with(GameDatabase::open).query(&query) {
+            let mut game = summary(&record.name, &record.metadata);
+            game.set(&Variant::from_str("opening"), &Variant::from_str(&record.opening));
+            game.set(&Variant::from_str("material"), &Variant::from_str(&record.material));
+            games.push(&Variant::from_dictionary(&game));
+        }
+        games
     }
-    // accessors and mutators for en passant
-    pub fn get_white_en_passant(&self) -> Option<[usize; 2]> {
-        self.white_en_passant
+    // the names that go in the save for the players
+    #[export]
+    fn set_players(&mut self, _owner: Node, white: GodotString, black: GodotString) {
+        self.game.set_players(&white.to_string(), &black.to_string());
     }
-    pub fn get_black_en_passant(&self) -> Option<[usize; 2]> {
-        self.black_en_passant
+    // what the game is being played for, which goes in the save
+    #[export]
+    fn set_event(&mut self, _owner: Node, event: GodotString) {
+        self.game.set_event(&event.to_string());
     }
-    pub fn set_white_en_passant(&mut self, space: Option<[usize; 2]>) {
-        self.white_en_passant = space;
+    // return how many turns the loaded game has
+    #[export]
+    fn get_num_turns(&self, _owner: Node) -> usize {
+        self.game.get_num_turns()
     }
-    pub fn set_black_en_passant(&mut self, space: Option<[usize; 2]>) {
-        self.black_en_passant = space;
+    // keep saves for a profile, which has its own directory of them. An
+    // empty name goes back to the saves that aren't for any profile
+    #[export]
+    unsafe fn set_profile(&mut self, owner: Node, profile: GodotString) {
+        let profile = profile.to_string();
+        let profile = Some(profile.as_str()).filter(|profile| !profile.is_empty());
+        if save::get_profile().as_deref() == profile {
+            return;
+        }
+        match save::set_profile(profile) {
+            Ok(()) => {
+                save::migrate_save_names();
+                // the other directory has its own index
+                self.games = None;
+            },
+            Err(e) => self.emit_error(owner, &e),
+        }
     }
-    // accessors and mutators for castling
-    pub fn get_white_can_castle_left(&self) -> bool {
-        self.white_can_castle_left
+    #[export]
+    fn get_profile(&self, _owner: Node) -> GodotString {
+        GodotString::from_str(&save::get_profile().unwrap_or_default())
     }
-    pub fn get_white_can_castle_right(&self) -> bool {
-        self.white_can_castle_right
+    // what's wrong with a name for a save, or an empty string if it can be
+    // used, so godot can say before trying to save
+    #[export]
+    fn check_save_name(&self, _owner: Node, save_name: GodotString) -> GodotString {
+        match save::check_save_name(&save_name.to_string()) {
+            Ok(()) => GodotString::from_str(""),
+            Err(e) => GodotString::from_str(&e),
+        }
     }
-    pub fn get_black_can_castle_left(&self) -> bool {
-        self.black_can_castle_left
+    // return the path to the data dir as a string so godot can display it
+    #[export]
+    fn data_dir_string(&self, _owner: Node) -> GodotString {
+        let data_dir = save::get_data_dir().map(|dir| dir.to_string_lossy().into_owned());
+        GodotString::from_str(&data_dir.unwrap_or_default())
     }
-    pub fn get_black_can_castle_right(&self) -> bool {
-        self.black_can_castle_right
+    // load a specific turn from the game
+    #[export]
+    unsafe fn load_turn(&mut self, owner: Node, turn: usize) {
+        match self.game.load_turn(turn) {
+            Ok(events) => {
+                self.instance_pieces(owner);
+                self.emit_events(owner, events);
+            },
+            // godot has already cleared its pieces, so put back the ones
+            // for the position that's still being played
+            Err(e) => {
+                self.instance_pieces(owner);
+                self.emit_error(owner, &e.to_string());
+            },
+        }
     }
-    // return board
-    pub fn get_board(&self) -> &Vec<Vec<Option<Rc<dyn ChessPiece>>>> {
-        &self.board
+    // replace the godot pieces with the ones on the board
+    #[export]
+    unsafe fn show_board(&self, owner: Node) {
+        self.pieces(owner).call(GodotString::from_str("clear_board"), &[]);
+        self.instance_pieces(owner);
     }
-    // set a piece at the given position
-    pub fn set(&mut self, pos: [usize; 2], piece: Option<Rc<dyn ChessPiece>>) {
-        self.board[pos[0]][pos[1]] = piece;
+    // the node that holds the godot pieces
+    unsafe fn pieces(&self, owner: Node) -> Node {
+        owner
+            .get_parent()
+            .unwrap()
+            .get_node(NodePath::from_str("Pieces"))
+            .unwrap()
     }
-    pub unsafe fn upgrade_pawn_signal(&self, mut owner: Node, space: [usize; 2]) {
-        owner.emit_signal(
-            GodotString::from_str("pawn_promoted"),
-            &[Variant::from_vector2(&Vector2::new(space[0] as f32, space[1] as f32))]);
+    // make the godot pieces for the board, after making sure there's a
+    // tile for every square since the board can change size
+    unsafe fn instance_pieces(&self, owner: Node) {
+        let mut chess = owner.get_parent().unwrap();
+        chess.call(GodotString::from_str("build_tiles"), &[]);
+        let mut piece_controller = self.pieces(owner);
+        for (col, column) in self.game.get_board().iter().enumerate() {
+            for (row, piece) in column.iter().enumerate() {
+                if let Some(piece) = piece {
+                    piece_controller.call(
+                        GodotString::from_str("instance_piece"),
+                        &[Variant::from_bool(piece.is_white()),
+                        Variant::from_str(piece.kind().as_str()),
+                        Variant::from_vector2(&to_vector2([col, row]))]);
+                }
+            }
+        }
     }
     // turn a pawn into a different piece
     #[export]
-    pub unsafe fn upgrade_pawn(&mut self, mut owner: Node, dest: Vector2, piece_type: GodotString) {
-        // do this since this method isn't called in sync with the turns
-        // so basing the colour off the player turn could cause problems if players move
-        // too fast.
-        let dest = [dest.x as usize, dest.y as usize];
-        let is_white = self.board[dest[0]][dest[1]].clone().unwrap().is_white();
-        match piece_type.to_string().as_str() {
-            "queen" => {
-                self.board[dest[0]][dest[1]] =
-                    Some(Rc::new(Queen::new(is_white)));
+    pub unsafe fn upgrade_pawn(&mut self, owner: Node, dest: Vector2, piece_type: GodotString) {
+        let kind = match piece_type.to_string().as_str() {
+            "queen" => PieceType::Queen,
+            "rook" => PieceType::Rook,
+            "bishop" => PieceType::Bishop,
+            "knight" => PieceType::Knight,
+            "king" => PieceType::King,
+            "archbishop" => PieceType::Archbishop,
+            "chancellor" => PieceType::Chancellor,
+            // fairy pieces go by the name in the fairy pieces file
+            name => match PieceType::from_name(name) {
+                Some(kind) => kind,
+                None => return self.emit_error(owner, &format!("{} isn't a piece", name)),
             },
-            "rook" => {
-                self.board[dest[0]][dest[1]] =
-                    Some(Rc::new(Rook::new(is_white)));
-            },
-            "3" => {
-                self.board[dest[0]][dest[1]] =
-                    Some(Rc::new(Bishop::new(is_white)));
-            },
-            "4" => {
-                self.board[dest[0]][dest[1]] =
-                    Some(Rc::new(Knight::new(is_white)));
-            },
-            _ => {},
+        };
+        match self.game.upgrade_pawn(to_square(dest), kind) {
+            Ok(events) => self.emit_move_events(owner, events),
+            Err(e) => self.emit_error(owner, &e),
         }
-        let promote_string = format!(" promoted to {}",
-                                     self.board[dest[0]][dest[1]].as_ref().unwrap());
-        owner.emit_signal(
-            GodotString::from_str("log_update"),
-            &[Variant::from_str(&promote_string)]);
-        self.game_save.push_str(&promote_string);
-        self.test_checkmate_stalemate(owner);
-        owner.emit_signal(
-            GodotString::from_str("log_update"),
-            &[Variant::from_str("\n")]);
-        self.game_save.push_str("\n");
-        self.game_save.push_str(&format!("{}", self));
-    }
-    // read input not used anymore, but I don't want to go modify my game trait
-    // so it's still here
-    pub fn parse_input(&mut self) -> Option<([usize; 2], [usize; 2])> {
-        let mut input_string = String::new();
-        self.input.read_line(&mut input_string)
-            .expect("failed to read from stdin");
-        if input_string.trim() == "resign" {
-            if self.player {
-                self.winner = Some(-1);
-            } else {
-                self.winner = Some(1);
-            }
-            return None;
-        }
-        let input_split: Vec<&str> = input_string.trim().split(" ").collect();
-        if input_split.len() != 2 {
-            return None;
-        }
-        let start_bytes: Vec<u8> = input_split[0].bytes().collect();
-        let dest_bytes: Vec<u8> = input_split[1].bytes().collect();
-        if start_bytes.len() != 2 || dest_bytes.len() != 2 {
-            return None;
-        }
-        let start = [start_bytes[0] as i32 -97, start_bytes[1] as i32 -49];
-        let dest = [dest_bytes[0] as i32 -97, dest_bytes[1] as i32 -49];
-        if start[0] > 7 || start[0] < 0 || start[1] > 7 || start[1] < 0
-        || dest[0] > 7 || dest[0] < 0 || dest[1] > 7 || dest[1] < 0 {
-            return None;
-        }
-        Some((
-            [start[0] as usize, start[1] as usize],
-            [dest[0] as usize, dest[1] as usize]))
-    }
-    // make a new board. I could have repurposed my file loading method here, but I want
-    // to have the default board hardcoded so that users can't delete the default board
-    // save and break the game.
-    fn new_board() -> Vec<Vec<Option<Rc<dyn ChessPiece>>>> {
-        let mut board: Vec<Vec<Option<Rc<dyn ChessPiece>>>> = vec![vec![None; 8]; 8];
-        for col in 0..8 {
-            // White Pawns
-            board[col][1] = Some(Rc::new(Pawn::new(true)));
-            // Black Pawns
-            board[col][6] = Some(Rc::new(Pawn::new(false)));
-        }
-        // White Rooks
-        board[0][0] = Some(Rc::new(Rook::new(true)));
-        board[7][0] = Some(Rc::new(Rook::new(true)));
-        // Black Rooks
-        board[0][7] = Some(Rc::new(Rook::new(false)));
-        board[7][7] = Some(Rc::new(Rook::new(false)));
-        // White Knights
-        board[1][0] = Some(Rc::new(Knight::new(true)));
-        board[6][0] = Some(Rc::new(Knight::new(true)));
-        // Black Knights
-        board[1][7] = Some(Rc::new(Knight::new(false)));
-        board[6][7] = Some(Rc::new(Knight::new(false)));
-        // White Bishops
-        board[2][0] = Some(Rc::new(Bishop::new(true)));
-        board[5][0] = Some(Rc::new(Bishop::new(true)));
-        // Black Bishops
-        board[2][7] = Some(Rc::new(Bishop::new(false)));
-        board[5][7] = Some(Rc::new(Bishop::new(false)));
-        // White King and Queen
-        board[4][0] = Some(Rc::new(King::new(true)));
-        board[3][0] = Some(Rc::new(Queen::new(true)));
-        // Black King and Queen
-        board[4][7] = Some(Rc::new(King::new(false)));
-        board[3][7] = Some(Rc::new(Queen::new(false)));
-        // return
-        board
     }
+    unsafe fn _init(_owner: Node) -> Self {
+        Self::new()
+    }
+    // constructor
+    pub fn new() -> Self {
+        save::migrate_save_names();
+        ChessBoard {
+            game: chess_core::ChessBoard::new(),
+            games: None,
+        }
+    }
+}
+// what godot gets told about a saved game
+fn summary(name: &str, metadata: &SaveMetadata) -> Dictionary {
+    let mut summary = Dictionary::new();
+    let mut set = |key: &str, value: Variant| summary.set(&Variant::from_str(key), &value);
+    set("name", Variant::from_str(name));
+    set("white", Variant::from_str(&metadata.white));
+    set("black", Variant::from_str(&metadata.black));
+    set("event", Variant::from_str(&metadata.event));
+    set("date", Variant::from_str(&metadata.date));
+    set("variant", Variant::from_str(&metadata.variant));
+    set("result", Variant::from_str(&metadata.result));
+    set("termination", Variant::from_str(metadata.termination.as_deref().unwrap_or("")));
+    set("move_count", Variant::from_u64(metadata.move_count as u64));
+    // seconds each player started with, or 0 for untimed games
+    let [white_time, black_time] = metadata.time_control.unwrap_or_default();
+    set("white_time", Variant::from_f64(white_time as f64 / 1000.0));
+    set("black_time", Variant::from_f64(black_time as f64 / 1000.0));
+    summary
+}
+// board squares are sent to godot as vectors
+fn to_vector2(space: [usize; 2]) -> Vector2 {
+    Vector2::new(space[0] as f32, space[1] as f32)
+}
+fn to_square(vector: Vector2) -> [usize; 2] {
+    [vector.x as usize, vector.y as usize]
 }
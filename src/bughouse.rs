@@ -0,0 +1,272 @@
+use chess_core::{BoardEvent, ChessPiece, GameEvent, PieceType};
+use godot::{
+    init::{PropertyHint, PropertyUsage, SignalArgument, Signal, ClassBuilder},
+    user_data::MutexData,
+    Node, Variant, GodotString, Vector2, Dictionary
+};
+
+// three minutes each unless godot asks for something else
+const DEFAULT_TIME_MS: u64 = 180_000;
+
+// godot side of a bughouse match. Every signal starts with the board it
+// happened on, 0 or 1, so one GUI can drive both boards
+pub struct Bughouse {
+    game: chess_core::Bughouse,
+}
+
+impl godot::NativeClass for Bughouse {
+    type Base = Node;
+    type UserData = MutexData<Bughouse>;
+    fn class_name() -> &'static str {
+        "Bughouse"
+    }
+    fn init(_owner: Self::Base) -> Self {
+        Self::new()
+    }
+    // set up all the signals
+    fn register_properties(builder: &ClassBuilder<Self>) {
+        let board = || argument("board", Variant::from_i64(0));
+        let square = |name: &'static str| argument(name, Variant::from_vector2(&Vector2::zero()));
+        builder.add_signal(Signal {
+            name: "log_update",
+            args: &[board(), argument("entry", Variant::from_str(""))],
+        });
+        builder.add_signal(Signal {
+            name: "move_is_legal",
+            args: &[board(), argument("is_legal", Variant::from_bool(false))],
+        });
+        builder.add_signal(Signal {
+            name: "piece_captured",
+            args: &[board(), square("position")],
+        });
+        builder.add_signal(Signal {
+            name: "castle",
+            args: &[board(), square("rook_start"), square("rook_dest"), square("king_dest")],
+        });
+        builder.add_signal(Signal {
+            name: "piece_dropped",
+            args: &[
+                board(),
+                argument("is_white", Variant::from_bool(false)),
+                argument("piece_type", Variant::from_str("")),
+                square("position"),
+            ],
+        });
+        // a player's reserve changed, which happens on the partner's board
+        // whenever they capture something. get_reserve has the new one
+        builder.add_signal(Signal {
+            name: "reserve_changed",
+            args: &[board(), argument("is_white", Variant::from_bool(false))],
+        });
+        builder.add_signal(Signal {
+            name: "pawn_promoted",
+            args: &[board(), square("position")],
+        });
+        builder.add_signal(Signal {
+            name: "check",
+            args: &[board(), argument("is_white", Variant::from_bool(false))],
+        });
+        // the game on a board ended, winner is for that board
+        builder.add_signal(Signal {
+            name: "game_over",
+            args: &[
+                board(),
+                argument("winner", Variant::from_i64(0)),
+                argument("reason", Variant::from_str("")),
+            ],
+        });
+        // the whole match ended. 1 if the team with white on board 0 won,
+        // -1 if the other team did and 0 for a draw
+        builder.add_signal(Signal {
+            name: "match_over",
+            args: &[argument("winner", Variant::from_i64(0))],
+        });
+        builder.add_signal(Signal {
+            name: "error",
+            args: &[argument("message", Variant::from_str(""))],
+        });
+    }
+}
+
+#[methods]
+impl Bughouse {
+    // send events from either board to godot as signals
+    unsafe fn emit_events(&self, mut owner: Node, events: Vec<BoardEvent>) {
+        for (board, event) in events {
+            let board_index = Variant::from_i64(board as i64);
+            if let Some(entry) = event.log_entry() {
+                owner.emit_signal(
+                    GodotString::from_str("log_update"),
+                    &[board_index.clone(), Variant::from_str(&entry)]);
+            }
+            match event {
+                GameEvent::Captured { square, .. } => {
+                    owner.emit_signal(
+                        GodotString::from_str("piece_captured"),
+                        &[board_index, Variant::from_vector2(&to_vector2(square))]);
+                },
+                GameEvent::Castled { rook_start, rook_dest, king_dest } => {
+                    owner.emit_signal(
+                        GodotString::from_str("castle"),
+                        &[board_index,
+                        Variant::from_vector2(&to_vector2(rook_start)),
+                        Variant::from_vector2(&to_vector2(rook_dest)),
+                        Variant::from_vector2(&to_vector2(king_dest))]);
+                },
+                GameEvent::Dropped { piece, square, .. } => {
+                    owner.emit_signal(
+                        GodotString::from_str("piece_dropped"),
+                        &[board_index,
+                        Variant::from_bool(piece.is_white()),
+                        Variant::from_str(piece.kind().as_str()),
+                        Variant::from_vector2(&to_vector2(square))]);
+                },
+                GameEvent::ReserveChanged { is_white } => {
+                    owner.emit_signal(
+                        GodotString::from_str("reserve_changed"),
+                        &[board_index, Variant::from_bool(is_white)]);
+                },
+                GameEvent::PromotionPending { square } => {
+                    owner.emit_signal(
+                        GodotString::from_str("pawn_promoted"),
+                        &[board_index, Variant::from_vector2(&to_vector2(square))]);
+                },
+                GameEvent::Check { is_white } => {
+                    owner.emit_signal(
+                        GodotString::from_str("check"),
+                        &[board_index, Variant::from_bool(is_white)]);
+                },
+                GameEvent::GameOver { winner, reason } => {
+                    owner.emit_signal(
+                        GodotString::from_str("game_over"),
+                        &[board_index,
+                        Variant::from_i64(winner as i64),
+                        Variant::from_str(reason.as_str())]);
+                    if let Some(winner) = self.game.get_winner() {
+                        owner.emit_signal(
+                            GodotString::from_str("match_over"),
+                            &[Variant::from_i64(winner as i64)]);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+    // tell godot something went wrong
+    unsafe fn emit_error(&self, mut owner: Node, message: &str) {
+        owner.emit_signal(
+            GodotString::from_str("error"),
+            &[Variant::from_str(message)]);
+    }
+    // start a new match where each player has the given number of seconds
+    #[export]
+    fn new_match(&mut self, _owner: Node, seconds: f64) {
+        self.game.new_match((seconds * 1000.0) as u64);
+    }
+    // move a piece on one of the boards
+    #[export]
+    unsafe fn try_move(&mut self, mut owner: Node, board: usize, start: Vector2, dest: Vector2) {
+        if board > 1 {
+            return self.emit_error(owner, &format!("there's no board {}", board));
+        }
+        let events = self.game.take_turn(board, to_square(start), to_square(dest));
+        let is_legal = events.is_some();
+        self.emit_events(owner, events.unwrap_or_default());
+        owner.emit_signal(
+            GodotString::from_str("move_is_legal"),
+            &[Variant::from_i64(board as i64), Variant::from_bool(is_legal)]);
+    }
+    // drop a piece from the reserve. The piece type is the name godot uses
+    // for it, like "knight"
+    #[export]
+    unsafe fn try_drop(&mut self, mut owner: Node, board: usize, piece_type: GodotString, square: Vector2) {
+        if board > 1 {
+            return self.emit_error(owner, &format!("there's no board {}", board));
+        }
+        let piece_type = piece_type.to_string();
+        let kind = PieceType::ALL.iter().copied().find(|kind| kind.as_str() == piece_type);
+        let events = kind.and_then(|kind| self.game.try_drop(board, kind, to_square(square)));
+        let is_legal = events.is_some();
+        self.emit_events(owner, events.unwrap_or_default());
+        owner.emit_signal(
+            GodotString::from_str("move_is_legal"),
+            &[Variant::from_i64(board as i64), Variant::from_bool(is_legal)]);
+    }
+    // turn a pawn into a different piece
+    #[export]
+    unsafe fn upgrade_pawn(&mut self, owner: Node, board: usize, dest: Vector2, piece_type: GodotString) {
+        if board > 1 {
+            return self.emit_error(owner, &format!("there's no board {}", board));
+        }
+        let piece_type = piece_type.to_string();
+        let kind = match PieceType::ALL.iter().copied().find(|kind| kind.as_str() == piece_type) {
+            Some(kind) => kind,
+            None => return self.emit_error(owner, &format!("{} isn't a piece", piece_type)),
+        };
+        match self.game.upgrade_pawn(board, to_square(dest), kind) {
+            Ok(events) => self.emit_events(owner, events),
+            Err(e) => self.emit_error(owner, &e),
+        }
+    }
+    // the player gives up for their team
+    #[export]
+    unsafe fn resign(&mut self, owner: Node, board: usize, is_white: bool) {
+        if board <= 1 {
+            let events = self.game.resign(board, is_white);
+            self.emit_events(owner, events);
+        }
+    }
+    // run the clocks, call this every frame with the frame's delta
+    #[export]
+    unsafe fn tick(&mut self, owner: Node, delta: f64) {
+        let events = self.game.tick((delta * 1000.0) as u64);
+        self.emit_events(owner, events);
+    }
+    // seconds the player has left on the given board
+    #[export]
+    fn get_clock(&self, _owner: Node, board: usize, is_white: bool) -> f64 {
+        self.game.get_clock(board.min(1), is_white) as f64 / 1000.0
+    }
+    // how many of each piece the player has to drop, keyed by the names
+    // godot uses for the pieces
+    #[export]
+    fn get_reserve(&self, _owner: Node, board: usize, is_white: bool) -> Dictionary {
+        let mut reserve = Dictionary::new();
+        let counts = self.game.get_board(board.min(1)).get_reserve(is_white);
+        for (kind, count) in PieceType::ALL.iter().zip(counts.iter()) {
+            reserve.set(&Variant::from_str(kind.as_str()), &Variant::from_i64(*count as i64));
+        }
+        reserve
+    }
+    // the position on a board as FEN, for setting up the pieces
+    #[export]
+    fn get_fen(&self, _owner: Node, board: usize) -> GodotString {
+        GodotString::from_str(&self.game.get_board(board.min(1)).to_fen(false))
+    }
+    unsafe fn _init(_owner: Node) -> Self {
+        Self::new()
+    }
+    // constructor
+    pub fn new() -> Self {
+        Bughouse {
+            game: chess_core::Bughouse::new(DEFAULT_TIME_MS),
+        }
+    }
+}
+
+// every signal argument here uses the defaults for the hint and usage
+fn argument(name: &'static str, default: Variant) -> SignalArgument<'static> {
+    SignalArgument {
+        name,
+        default,
+        hint: PropertyHint::None,
+        usage: PropertyUsage::DEFAULT,
+    }
+}
+// board squares are sent to godot as vectors
+fn to_vector2(space: [usize; 2]) -> Vector2 {
+    Vector2::new(space[0] as f32, space[1] as f32)
+}
+fn to_square(vector: Vector2) -> [usize; 2] {
+    [vector.x as usize, vector.y as usize]
+}
@@ -1,11 +1,12 @@
 mod board;
-mod chesspiece;
-mod game;
+mod bughouse;
 #[macro_use]
 extern crate gdnative as godot;
 use board::ChessBoard;
+use bughouse::Bughouse;
 fn init(handle: godot::init::InitHandle) {
     handle.add_class::<ChessBoard>();
+    handle.add_class::<Bughouse>();
 }
 godot_gdnative_init!();
 godot_nativescript_init!(init);